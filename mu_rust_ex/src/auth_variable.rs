@@ -1,9 +1,19 @@
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
 
 use r_efi::efi;
 use string::OsString;
 
-use crate::{variable::EfiVariable, UefiResult, rustified};
+use crate::der::{self, DerReader, DerResult};
+use crate::image_authentication::{SignatureDatabase, SignatureDatabaseError};
+use crate::pkcs7::SignedData;
+use crate::signer::Signer;
+use crate::util::{read_guid, read_u16_le, read_u32_le};
+use crate::x509::DebugTime;
+use crate::{rustified, variable::EfiVariable, UefiResult};
 
 pub const EFI_SECURE_BOOT_ENABLE_DISABLE_GUID: efi::Guid = efi::Guid::from_fields(
     0xf0a30bc7,
@@ -143,41 +153,107 @@ pub const EFI_CERT_TYPE_PKCS7_GUID: efi::Guid = efi::Guid::from_fields(
 pub const EFI_IMAGE_SECURITY_DATABASE: &str = "db";
 pub const EFI_IMAGE_SECURITY_DATABASE1: &str = "dbx";
 pub const EFI_IMAGE_SECURITY_DATABASE2: &str = "dbt";
+pub const EFI_IMAGE_SECURITY_DATABASE3: &str = "dbr";
+pub const EFI_PLATFORM_KEY_NAME: &str = "PK";
+pub const EFI_KEY_EXCHANGE_KEY_NAME: &str = "KEK";
+
+// Read-only vendor defaults, all under EFI_GLOBAL_VARIABLE_GUID.
+pub const EFI_PLATFORM_KEY_DEFAULT_NAME: &str = "PKDefault";
+pub const EFI_KEY_EXCHANGE_KEY_DEFAULT_NAME: &str = "KEKDefault";
+pub const EFI_DB_DEFAULT_NAME: &str = "dbDefault";
+pub const EFI_DBX_DEFAULT_NAME: &str = "dbxDefault";
+pub const EFI_DBT_DEFAULT_NAME: &str = "dbtDefault";
+pub const EFI_DBR_DEFAULT_NAME: &str = "dbrDefault";
+
+// Vendor GUID for each of the authenticated Secure Boot key databases.
+pub fn get_secure_boot_variable_guid(name: &str) -> Option<efi::Guid> {
+    match name {
+        EFI_PLATFORM_KEY_NAME | EFI_KEY_EXCHANGE_KEY_NAME => {
+            Some(crate::variable::EFI_GLOBAL_VARIABLE_GUID)
+        }
+        EFI_IMAGE_SECURITY_DATABASE
+        | EFI_IMAGE_SECURITY_DATABASE1
+        | EFI_IMAGE_SECURITY_DATABASE2
+        | EFI_IMAGE_SECURITY_DATABASE3 => Some(EFI_IMAGE_SECURITY_DATABASE_GUID),
+        _ => None,
+    }
+}
+
+// Global variables reporting the Secure Boot mode. SecureBoot holds
+// SECURE_BOOT_MODE_ENABLE/DISABLE; the others are 1 when set.
+pub const EFI_SECURE_BOOT_MODE_NAME: &str = "SecureBoot";
+pub const EFI_AUDIT_MODE_NAME: &str = "AuditMode";
+pub const EFI_DEPLOYED_MODE_NAME: &str = "DeployedMode";
+// 1 while PK, KEK, db and dbx are still the platform vendor's.
+pub const EFI_VENDOR_KEYS_NAME: &str = "VendorKeys";
 
 pub const SECURE_BOOT_MODE_ENABLE: u8 = 1;
 pub const SECURE_BOOT_MODE_DISABLE: u8 = 0;
 
+// Global variable reporting SETUP_MODE or USER_MODE.
+pub const EFI_SETUP_MODE_NAME: &str = "SetupMode";
 pub const SETUP_MODE: u8 = 1;
 pub const USER_MODE: u8 = 0;
 
+// Attributes every Secure Boot key database is written with. Appends add
+// EFI_VARIABLE_APPEND_WRITE on top.
+pub const EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES: u32 = efi::VARIABLE_NON_VOLATILE
+    | efi::VARIABLE_BOOTSERVICE_ACCESS
+    | efi::VARIABLE_RUNTIME_ACCESS
+    | efi::VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS;
+
+// The current time in the form TimeStamp requires: UTC, with Nanosecond,
+// TimeZone and Daylight all zero.
+pub fn get_current_timestamp() -> UefiResult<rustified::Time> {
+    let (now, _) = crate::runtime::uefi_rs().get_time()?;
+    Ok(rustified::Time {
+        nanosecond: 0,
+        ..now.to_utc()?
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct EfiAuthVariable2 {
     pub variable: EfiVariable,
     pub time: rustified::Time,
 }
 
+// WIN_CERTIFICATE.wRevision and .wCertificateType for WIN_CERTIFICATE_UEFI_GUID.
+pub const WIN_CERT_REVISION: u16 = 0x0200;
+pub const WIN_CERT_TYPE_EFI_GUID: u16 = 0x0EF1;
+// dwLength + wRevision + wCertificateType + CertType, ahead of CertData.
+pub const WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE: usize = core::mem::size_of::<u32>()
+    + 2 * core::mem::size_of::<u16>()
+    + core::mem::size_of::<efi::Guid>();
+
 impl EfiAuthVariable2 {
+    fn get_name_utf16(&self) -> OsString {
+        OsString::from(self.variable.name.as_str())
+    }
+
     pub fn get_tbs_data_size(&self) -> usize {
         // This is deeply inefficient.
         // A better solution might be to use setters and update an internal size.
-        (self.variable.name.chars().count() * core::mem::size_of::<efi::Char16>())
+        ((self.get_name_utf16().len() - 1) * core::mem::size_of::<efi::Char16>())
             + core::mem::size_of::<efi::Guid>()
             + core::mem::size_of::<u32>()
-            + core::mem::size_of::<efi::Time>()
+            + rustified::Time::SERIALIZED_SIZE
             + self.variable.data.len()
     }
 
     pub fn get_tbs_data_buffer(&self, buffer: &mut [u8]) -> UefiResult<usize> {
+        let tbs_size = self.get_tbs_data_size();
+        if buffer.len() < tbs_size {
+            return Err(efi::Status::BUFFER_TOO_SMALL);
+        }
+
         // Populate the variable_name.
-        let u16_str = OsString::from(self.variable.name.as_str());
+        let u16_str = self.get_name_utf16();
         let u16_str_wo_null = &u16_str.as_u16_slice()[..u16_str.len() - 1];
         let (u16_bytes, remainder) =
-            buffer.split_at_mut(u16_str_wo_null.len() * core::mem::size_of::<u16>());
-        unsafe {
-            let slice_start = u16_bytes.as_mut_ptr();
-            let u16_slice =
-                core::slice::from_raw_parts_mut(slice_start as *mut u16, u16_str_wo_null.len());
-            u16_slice.copy_from_slice(u16_str_wo_null);
+            buffer[..tbs_size].split_at_mut(core::mem::size_of_val(u16_str_wo_null));
+        for (char_bytes, char16) in u16_bytes.chunks_exact_mut(2).zip(u16_str_wo_null) {
+            char_bytes.copy_from_slice(&char16.to_le_bytes());
         }
 
         // Populate the guid.
@@ -186,56 +262,248 @@ impl EfiAuthVariable2 {
 
         // Populate the attributes.
         let (attr_bytes, remainder) = remainder.split_at_mut(core::mem::size_of::<u32>());
-        unsafe {
-            let attr_u32 = attr_bytes.as_mut_ptr();
-            *(attr_u32 as *mut u32) = self.variable.attributes;
-        }
+        attr_bytes.copy_from_slice(&self.variable.attributes.to_le_bytes());
 
         // Populate the timestamp.
-        let (time_bytes, data_bytes) = remainder.split_at_mut(core::mem::size_of::<efi::Time>());
-        let efi_time: efi::Time = self.time.into();
-        unsafe {
-            let time_src_bytes = &efi_time as *const efi::Time as *const u8;
-            time_bytes.copy_from_slice(core::slice::from_raw_parts(
-                time_src_bytes,
-                time_bytes.len(),
-            ));
-        }
+        let (time_bytes, data_bytes) = remainder.split_at_mut(rustified::Time::SERIALIZED_SIZE);
+        time_bytes.copy_from_slice(&self.time.to_bytes());
 
         // Populate the data.
         data_bytes.copy_from_slice(&self.variable.data);
 
-        Ok(buffer.len())
+        Ok(tbs_size)
     }
 
     pub fn get_tbs_data(&self) -> UefiResult<Vec<u8>> {
-        let tbs_size = self.get_tbs_data_size();
-        let mut data = Vec::<u8>::with_capacity(tbs_size);
-        // Safe because we're about to init this exact data.
-        unsafe { data.set_len(tbs_size) };
+        let mut data = vec![0u8; self.get_tbs_data_size()];
         let actual_size = self.get_tbs_data_buffer(&mut data)?;
-        unsafe { data.set_len(actual_size) };
+        data.truncate(actual_size);
         Ok(data)
     }
 
-    pub fn get_set_variable_data_buffer(&self, buffer: &mut [u8]) -> UefiResult<usize> {
-        let (time_bytes, remainder) = buffer.split_at_mut(core::mem::size_of::<efi::Time>());
-        let efi_time: efi::Time = self.time.into();
-        unsafe {
-            let time_src_bytes = &efi_time as *const efi::Time as *const u8;
-            time_bytes.copy_from_slice(core::slice::from_raw_parts(
-                time_src_bytes,
-                time_bytes.len(),
+    // Size of the complete SetVariable() payload for a given signature.
+    pub fn get_set_variable_data_size(&self, signature: &[u8]) -> usize {
+        rustified::Time::SERIALIZED_SIZE
+            + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE
+            + signature.len()
+            + self.variable.data.len()
+    }
+
+    // Builds the EFI_VARIABLE_AUTHENTICATION_2 descriptor followed by the
+    // variable data. `signature` is the DER PKCS#7 SignedData made over
+    // get_tbs_data(), exactly as it should appear in CertData.
+    pub fn get_set_variable_data_buffer(
+        &self,
+        signature: &[u8],
+        buffer: &mut [u8],
+    ) -> UefiResult<usize> {
+        // Firmware rejects timestamps that carry anything beyond the date and time.
+        if self.time.nanosecond != 0 || self.time.timezone != 0 || self.time.daylight != 0 {
+            return Err(efi::Status::INVALID_PARAMETER);
+        }
+        // Make sure CertData holds exactly one DER element.
+        DerReader::new(signature)
+            .read_last(der::TAG_SEQUENCE)
+            .map_err(|_| efi::Status::INVALID_PARAMETER)?;
+
+        let total_size = self.get_set_variable_data_size(signature);
+        if buffer.len() < total_size {
+            return Err(efi::Status::BUFFER_TOO_SMALL);
+        }
+        let cert_length = u32::try_from(WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE + signature.len())
+            .map_err(|_| efi::Status::BAD_BUFFER_SIZE)?;
+
+        // Populate the timestamp.
+        let (time_bytes, remainder) =
+            buffer[..total_size].split_at_mut(rustified::Time::SERIALIZED_SIZE);
+        time_bytes.copy_from_slice(&self.time.to_bytes());
+
+        // Populate the WIN_CERTIFICATE_UEFI_GUID header.
+        let (length_bytes, remainder) = remainder.split_at_mut(core::mem::size_of::<u32>());
+        length_bytes.copy_from_slice(&cert_length.to_le_bytes());
+        let (revision_bytes, remainder) = remainder.split_at_mut(core::mem::size_of::<u16>());
+        revision_bytes.copy_from_slice(&WIN_CERT_REVISION.to_le_bytes());
+        let (type_bytes, remainder) = remainder.split_at_mut(core::mem::size_of::<u16>());
+        type_bytes.copy_from_slice(&WIN_CERT_TYPE_EFI_GUID.to_le_bytes());
+        let (guid_bytes, remainder) = remainder.split_at_mut(core::mem::size_of::<efi::Guid>());
+        guid_bytes.copy_from_slice(EFI_CERT_TYPE_PKCS7_GUID.as_bytes());
+
+        // Populate the CertData and the variable data.
+        let (cert_bytes, data_bytes) = remainder.split_at_mut(signature.len());
+        cert_bytes.copy_from_slice(signature);
+        data_bytes.copy_from_slice(&self.variable.data);
+
+        Ok(total_size)
+    }
+
+    pub fn get_set_variable_data(&self, signature: &[u8]) -> UefiResult<Vec<u8>> {
+        let mut data = vec![0u8; self.get_set_variable_data_size(signature)];
+        let actual_size = self.get_set_variable_data_buffer(signature, &mut data)?;
+        data.truncate(actual_size);
+        Ok(data)
+    }
+
+    // Signs get_tbs_data() with the given backend and builds the complete payload.
+    pub fn get_signed_set_variable_data<S: Signer + ?Sized>(
+        &self,
+        signer: &mut S,
+    ) -> UefiResult<Vec<u8>> {
+        let output = signer.sign(&self.get_tbs_data()?)?;
+        self.get_set_variable_data(&output.signed_data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthVariableError {
+    // The buffer ends inside the EFI_VARIABLE_AUTHENTICATION_2 descriptor.
+    Truncated,
+    BadTime(efi::Status),
+    // (length) WIN_CERTIFICATE.dwLength doesn't fit the buffer.
+    BadCertificateLength(u32),
+    // (revision, type) The certificate isn't a WIN_CERTIFICATE_UEFI_GUID.
+    UnsupportedCertificate(u16, u16),
+    // (cert_type) Only EFI_CERT_TYPE_PKCS7_GUID is allowed for time-based variables.
+    UnsupportedCertType(efi::Guid),
+}
+impl fmt::Display for AuthVariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "Authentication descriptor is truncated"),
+            Self::BadTime(status) => write!(f, "Invalid TimeStamp ({:?})", status),
+            Self::BadCertificateLength(length) => {
+                write!(f, "Invalid WIN_CERTIFICATE length 0x{:X}", length)
+            }
+            Self::UnsupportedCertificate(revision, cert_type) => write!(
+                f,
+                "Unsupported WIN_CERTIFICATE revision 0x{:04X}, type 0x{:04X}",
+                revision, cert_type
+            ),
+            Self::UnsupportedCertType(guid) => write!(
+                f,
+                "Unsupported CertType {:?}",
+                crate::util::DebugGuid::from(*guid)
+            ),
+        }
+    }
+}
+impl From<AuthVariableError> for efi::Status {
+    fn from(f: AuthVariableError) -> Self {
+        match f {
+            AuthVariableError::Truncated => efi::Status::BAD_BUFFER_SIZE,
+            AuthVariableError::BadTime(x) => x,
+            AuthVariableError::BadCertificateLength(_) => efi::Status::BAD_BUFFER_SIZE,
+            AuthVariableError::UnsupportedCertificate(_, _) => efi::Status::UNSUPPORTED,
+            AuthVariableError::UnsupportedCertType(_) => efi::Status::UNSUPPORTED,
+        }
+    }
+}
+
+// A borrowed view of a complete authenticated SetVariable() payload, as
+// found in the .auth files that signed updates ship as.
+#[derive(Clone, Copy)]
+pub struct EfiAuthVariable2Ref<'a> {
+    pub time: rustified::Time,
+    // CertData: the DER PKCS#7 SignedData.
+    pub signature: &'a [u8],
+    // Everything after the descriptor: the new variable contents.
+    pub data: &'a [u8],
+}
+
+impl<'a> EfiAuthVariable2Ref<'a> {
+    pub fn from_bytes(buffer: &'a [u8]) -> Result<Self, AuthVariableError> {
+        let time_size = rustified::Time::SERIALIZED_SIZE;
+        if buffer.len() < time_size + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE {
+            return Err(AuthVariableError::Truncated);
+        }
+        let time = rustified::Time::from_bytes(buffer).map_err(AuthVariableError::BadTime)?;
+
+        // All of these reads are inside the size check above.
+        let length = read_u32_le(buffer, time_size).unwrap();
+        let revision = read_u16_le(buffer, time_size + 4).unwrap();
+        let cert_type = read_u16_le(buffer, time_size + 6).unwrap();
+        let cert_type_guid = read_guid(buffer, time_size + 8).unwrap();
+
+        if revision != WIN_CERT_REVISION || cert_type != WIN_CERT_TYPE_EFI_GUID {
+            return Err(AuthVariableError::UnsupportedCertificate(
+                revision, cert_type,
             ));
         }
+        if cert_type_guid != EFI_CERT_TYPE_PKCS7_GUID {
+            return Err(AuthVariableError::UnsupportedCertType(cert_type_guid));
+        }
+        let descriptor_end = (length as usize)
+            .checked_add(time_size)
+            .filter(|end| {
+                *end >= time_size + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE && *end <= buffer.len()
+            })
+            .ok_or(AuthVariableError::BadCertificateLength(length))?;
+
+        Ok(Self {
+            time,
+            signature: &buffer[time_size + WIN_CERTIFICATE_UEFI_GUID_HEADER_SIZE..descriptor_end],
+            data: &buffer[descriptor_end..],
+        })
+    }
+
+    pub fn signed_data(&self) -> DerResult<SignedData<'a>> {
+        SignedData::from_der(self.signature)
+    }
+
+    // Interprets the new contents as signature lists, which is what every
+    // Secure Boot key database holds.
+    pub fn payload(&self) -> Result<SignatureDatabase, SignatureDatabaseError> {
+        SignatureDatabase::from_bytes(self.data)
+    }
+
+    // The .auth file doesn't say which variable it's meant for, so the
+    // caller has to. The result's get_tbs_data() is what was signed.
+    pub fn to_auth_variable(
+        &self,
+        name: &str,
+        guid: &efi::Guid,
+        attributes: u32,
+    ) -> EfiAuthVariable2 {
+        EfiAuthVariable2 {
+            variable: EfiVariable {
+                name: String::from(name),
+                guid: *guid,
+                data: Vec::from(self.data),
+                attributes,
+            },
+            time: self.time,
+        }
+    }
+}
 
-        Err(efi::Status::UNSUPPORTED)
+impl<'a> fmt::Debug for EfiAuthVariable2Ref<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("EfiAuthVariable2:\n")?;
+            fmtr.write_fmt(format_args!("\tTimeStamp: {:?}\n", DebugTime(&self.time)))?;
+            fmtr.write_fmt(format_args!(
+                "\tCertData: 0x{:X} bytes\n",
+                self.signature.len()
+            ))?;
+            match self.signed_data() {
+                Ok(signed_data) => fmtr.write_fmt(format_args!("{:#?}", signed_data))?,
+                Err(err) => fmtr.write_fmt(format_args!("\tInvalid SignedData: {}\n", err))?,
+            }
+            fmtr.write_fmt(format_args!("\tData: 0x{:X} bytes\n", self.data.len()))?;
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "EfiAuthVariable2 {{ TimeStamp: {:?}, Data: 0x{:X} bytes }}",
+                DebugTime(&self.time),
+                self.data.len()
+            ))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{efi, EfiAuthVariable2, EfiVariable};
+    use super::*;
+    use crate::test_data::auth_files;
 
     #[test]
     fn auth_var_2_should_create_digest_to_sign() {
@@ -265,4 +533,143 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn auth_var_2_should_create_set_variable_payload() {
+        let test_var = EfiAuthVariable2 {
+            variable: EfiVariable {
+                name: String::from("TestVar"),
+                guid: crate::variable::EFI_GLOBAL_VARIABLE_GUID.clone(),
+                data: Vec::<u8>::from([0xDEu8, 0xADu8, 0xBEu8, 0xEFu8]),
+                attributes: (efi::VARIABLE_NON_VOLATILE | efi::VARIABLE_BOOTSERVICE_ACCESS),
+            },
+            time: crate::rustified::Time {
+                year: 2022,
+                month: 04,
+                day: 18,
+                ..Default::default()
+            }
+            .into(),
+        };
+        let signature = [0x30, 0x03, 0x02, 0x01, 0x01];
+        assert_eq!(
+            test_var.get_set_variable_data(&signature).unwrap(),
+            &[
+                0xE6, 0x07, 0x04, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x1D, 0x00, 0x00, 0x00, 0x00, 0x02, 0xF1, 0x0E, 0x9D, 0xD2, 0xAF, 0x4A,
+                0xDF, 0x68, 0xEE, 0x49, 0x8A, 0xA9, 0x34, 0x7D, 0x37, 0x56, 0x65, 0xA7, 0x30, 0x03,
+                0x02, 0x01, 0x01, 0xDE, 0xAD, 0xBE, 0xEF
+            ]
+        );
+
+        let mut buffer = [0u8; 0x40];
+        assert_eq!(
+            test_var.get_set_variable_data_buffer(&signature, &mut buffer[..0x30]),
+            Err(efi::Status::BUFFER_TOO_SMALL)
+        );
+        assert_eq!(
+            test_var.get_set_variable_data_buffer(&signature, &mut buffer),
+            Ok(0x31)
+        );
+    }
+
+    #[test]
+    fn auth_var_2_should_reject_bad_payload_inputs() {
+        let mut test_var = EfiAuthVariable2 {
+            variable: EfiVariable {
+                name: String::from("TestVar"),
+                guid: crate::variable::EFI_GLOBAL_VARIABLE_GUID.clone(),
+                data: Vec::<u8>::from([0xDEu8, 0xADu8, 0xBEu8, 0xEFu8]),
+                attributes: (efi::VARIABLE_NON_VOLATILE | efi::VARIABLE_BOOTSERVICE_ACCESS),
+            },
+            time: crate::rustified::Time {
+                year: 2022,
+                month: 04,
+                day: 18,
+                ..Default::default()
+            }
+            .into(),
+        };
+        assert_eq!(
+            test_var.get_set_variable_data(&[0x30, 0x03, 0x02, 0x01]),
+            Err(efi::Status::INVALID_PARAMETER)
+        );
+        assert_eq!(
+            test_var.get_set_variable_data(&[0x30, 0x00, 0x00]),
+            Err(efi::Status::INVALID_PARAMETER)
+        );
+
+        test_var.time.timezone = 60;
+        assert_eq!(
+            test_var.get_set_variable_data(&[0x30, 0x00]),
+            Err(efi::Status::INVALID_PARAMETER)
+        );
+    }
+
+    #[test]
+    fn auth_var_2_ref_should_parse_auth_files() {
+        let auth = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        assert_eq!(
+            (auth.time.year, auth.time.month, auth.time.day),
+            (2024, 1, 2)
+        );
+        assert_eq!(auth.signature.len(), 1279);
+        assert_eq!(auth.signed_data().unwrap().signers.len(), 1);
+
+        let payload = auth.payload().unwrap();
+        assert_eq!(payload.entries.len(), 1);
+        assert_eq!(payload.entries[0].list_type, EFI_CERT_SHA256_GUID);
+        assert_eq!(payload.entries[0].elements.len(), 2);
+
+        // Re-wrapping with our own serializer should reproduce the file exactly.
+        let variable = auth.to_auth_variable(
+            EFI_IMAGE_SECURITY_DATABASE,
+            &EFI_IMAGE_SECURITY_DATABASE_GUID,
+            efi::VARIABLE_NON_VOLATILE
+                | efi::VARIABLE_BOOTSERVICE_ACCESS
+                | efi::VARIABLE_RUNTIME_ACCESS
+                | efi::VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS
+                | efi::VARIABLE_APPEND_WRITE,
+        );
+        assert_eq!(variable.get_tbs_data_size(), 0xA4);
+        assert_eq!(
+            variable.get_set_variable_data(auth.signature).unwrap(),
+            auth_files::DB_APPEND
+        );
+    }
+
+    #[test]
+    fn auth_var_2_ref_should_reject_bad_descriptors() {
+        let parse = |data: &[u8]| EfiAuthVariable2Ref::from_bytes(data).err();
+        assert_eq!(
+            parse(&auth_files::DB_APPEND[..39]),
+            Some(AuthVariableError::Truncated)
+        );
+
+        let mut data = Vec::from(auth_files::DB_APPEND);
+        data[21] = 0x01;
+        assert_eq!(
+            parse(&data),
+            Some(AuthVariableError::UnsupportedCertificate(0x0100, 0x0EF1))
+        );
+
+        let mut data = Vec::from(auth_files::DB_APPEND);
+        data[24] ^= 0xFF;
+        assert!(matches!(
+            parse(&data),
+            Some(AuthVariableError::UnsupportedCertType(_))
+        ));
+
+        let mut data = Vec::from(auth_files::DB_APPEND);
+        data[16..20].copy_from_slice(&0x1000u32.to_le_bytes());
+        assert_eq!(
+            parse(&data),
+            Some(AuthVariableError::BadCertificateLength(0x1000))
+        );
+        data[16..20].copy_from_slice(&0x10u32.to_le_bytes());
+        assert_eq!(
+            parse(&data),
+            Some(AuthVariableError::BadCertificateLength(0x10))
+        );
+    }
 }
@@ -0,0 +1,462 @@
+/* @file
+Authenticode image digests, the hash that db/dbx entries and embedded
+signatures refer to.
+
+Follows the PE Authenticode rules, as implemented by EDK2's
+DxeImageVerificationLib: the headers are hashed without the CheckSum field
+and the certificate table's directory entry, then each section's raw data
+in file-offset order, then whatever follows the sections except the
+certificate table itself. The image is read through an ImageSource a chunk
+at a time, so it never has to be in memory all at once.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::auth_variable::{EFI_CERT_SHA256_GUID, EFI_CERT_SHA384_GUID, EFI_CERT_SHA512_GUID};
+use crate::der;
+use crate::pe::{PeError, PeImage};
+use crate::shell_protocol::ShellFile;
+
+// How much of the image is read at a time.
+const CHUNK_SIZE: usize = 0x10000;
+// Enough for the headers of nearly every image; more is read if not.
+const INITIAL_HEADER_SIZE: usize = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthenticodeError {
+    // (error) The headers are malformed.
+    Pe(PeError),
+    // (offset) The image ended early while reading at offset.
+    ShortRead(usize),
+    // The certificate table doesn't fit between the sections and the end of
+    // the file.
+    BadCertificateTable,
+    // (status) Reading the image failed.
+    Read(efi::Status),
+}
+impl fmt::Display for AuthenticodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pe(err) => write!(f, "{}", err),
+            Self::ShortRead(offset) => write!(f, "Image ended early at 0x{:X}", offset),
+            Self::BadCertificateTable => write!(f, "Certificate table overlaps the sections"),
+            Self::Read(status) => write!(f, "Reading the image failed ({:?})", status),
+        }
+    }
+}
+impl From<AuthenticodeError> for efi::Status {
+    fn from(f: AuthenticodeError) -> Self {
+        match f {
+            AuthenticodeError::Pe(x) => x.into(),
+            AuthenticodeError::ShortRead(_) => efi::Status::END_OF_FILE,
+            AuthenticodeError::BadCertificateTable => efi::Status::LOAD_ERROR,
+            AuthenticodeError::Read(x) => x,
+        }
+    }
+}
+impl From<PeError> for AuthenticodeError {
+    fn from(f: PeError) -> Self {
+        Self::Pe(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 3] = [Self::Sha256, Self::Sha384, Self::Sha512];
+
+    pub fn digest_size(&self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+
+    // The EFI_CERT_SHAxxx type of db/dbx entries holding this digest.
+    pub fn signature_type(&self) -> efi::Guid {
+        match self {
+            Self::Sha256 => EFI_CERT_SHA256_GUID,
+            Self::Sha384 => EFI_CERT_SHA384_GUID,
+            Self::Sha512 => EFI_CERT_SHA512_GUID,
+        }
+    }
+
+    pub fn from_signature_type(signature_type: &efi::Guid) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.signature_type() == *signature_type)
+    }
+
+    pub fn oid(&self) -> &'static [u8] {
+        match self {
+            Self::Sha256 => der::OID_SHA256,
+            Self::Sha384 => der::OID_SHA384,
+            Self::Sha512 => der::OID_SHA512,
+        }
+    }
+
+    pub fn from_oid(oid: &[u8]) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.oid() == oid)
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Sha384 => Self::Sha384(Sha384::new()),
+            HashAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha384(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha384(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+// Random access to an image's bytes.
+pub trait ImageSource {
+    fn size(&self) -> Result<usize, AuthenticodeError>;
+    // Fills all of `buffer` from `offset` onwards.
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), AuthenticodeError>;
+}
+
+impl ImageSource for &[u8] {
+    fn size(&self) -> Result<usize, AuthenticodeError> {
+        Ok(self.len())
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), AuthenticodeError> {
+        let data = offset
+            .checked_add(buffer.len())
+            .and_then(|end| self.get(offset..end))
+            .ok_or(AuthenticodeError::ShortRead(offset))?;
+        buffer.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+impl ImageSource for ShellFile {
+    fn size(&self) -> Result<usize, AuthenticodeError> {
+        self.get_size()
+            .map_err(|err| AuthenticodeError::Read(err.into()))
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), AuthenticodeError> {
+        self.set_position(offset)
+            .map_err(|err| AuthenticodeError::Read(err.into()))?;
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.read(&mut buffer[filled..]) {
+                Ok(0) => return Err(AuthenticodeError::ShortRead(offset + filled)),
+                Ok(count) => filled += count,
+                Err(err) => return Err(AuthenticodeError::Read(err.into())),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reads and parses the headers, growing the read until the section table
+// fits. Returns the parsed image and at least SizeOfHeaders bytes.
+pub fn read_headers<S: ImageSource + ?Sized>(
+    source: &mut S,
+) -> Result<(PeImage, Vec<u8>), AuthenticodeError> {
+    let file_size = source.size()?;
+    let mut headers = vec![0u8; INITIAL_HEADER_SIZE.min(file_size)];
+    source.read_at(0, &mut headers)?;
+    let image = loop {
+        match PeImage::parse_headers(&headers, file_size) {
+            Ok(image) => break image,
+            Err(PeError::Truncated(offset)) if offset < file_size && headers.len() < file_size => {
+                // Past the end of what's been read, but not of the file.
+                let wanted = (headers.len() * 2).max(offset + 8).min(file_size);
+                let start = headers.len();
+                headers.resize(wanted, 0);
+                source.read_at(start, &mut headers[start..])?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let size_of_headers = image.size_of_headers as usize;
+    if headers.len() < size_of_headers {
+        let start = headers.len();
+        headers.resize(size_of_headers, 0);
+        source.read_at(start, &mut headers[start..])?;
+    }
+    Ok((image, headers))
+}
+
+// The (offset, length) ranges of the file that go into the digest, in order.
+pub fn hashed_ranges(image: &PeImage) -> Result<Vec<(usize, usize)>, AuthenticodeError> {
+    let size_of_headers = image.size_of_headers as usize;
+    let checksum = image.checksum_offset();
+    let mut ranges = vec![(0, checksum)];
+    match image.security_directory_entry_offset() {
+        Some(security) => {
+            ranges.push((checksum + 4, security - (checksum + 4)));
+            ranges.push((security + 8, size_of_headers - (security + 8)));
+        }
+        None => ranges.push((checksum + 4, size_of_headers - (checksum + 4))),
+    }
+
+    let mut sections: Vec<_> = image
+        .sections
+        .iter()
+        .filter(|section| section.size_of_raw_data != 0)
+        .collect();
+    sections.sort_by_key(|section| section.pointer_to_raw_data);
+    let mut hashed = size_of_headers;
+    for section in sections {
+        ranges.push((
+            section.pointer_to_raw_data as usize,
+            section.size_of_raw_data as usize,
+        ));
+        hashed += section.size_of_raw_data as usize;
+    }
+
+    // Trailing data is whatever's left once the certificate table is taken
+    // out. Like EDK2, this counts bytes rather than trusting offsets.
+    let certificate_size = image
+        .security_directory()
+        .map_or(0, |directory| directory.size as usize);
+    if image.file_size > hashed {
+        match image.file_size.checked_sub(hashed + certificate_size) {
+            Some(0) => (),
+            Some(extra) => ranges.push((hashed, extra)),
+            None => return Err(AuthenticodeError::BadCertificateTable),
+        }
+    }
+    Ok(ranges)
+}
+
+// Computes the Authenticode digest of `image` with each of `algorithms` in a
+// single pass over `source`.
+pub fn compute_digests<S: ImageSource + ?Sized>(
+    image: &PeImage,
+    headers: &[u8],
+    source: &mut S,
+    algorithms: &[HashAlgorithm],
+) -> Result<Vec<Vec<u8>>, AuthenticodeError> {
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|a| Hasher::new(*a)).collect();
+    let mut chunk = Vec::new();
+    for (offset, length) in hashed_ranges(image)? {
+        // Header ranges come from what's already been read.
+        if let Some(data) = headers.get(offset..offset + length) {
+            hashers.iter_mut().for_each(|hasher| hasher.update(data));
+            continue;
+        }
+        let mut done = 0;
+        while done < length {
+            let count = (length - done).min(CHUNK_SIZE);
+            chunk.resize(count, 0);
+            source.read_at(offset + done, &mut chunk)?;
+            hashers.iter_mut().for_each(|hasher| hasher.update(&chunk));
+            done += count;
+        }
+    }
+    Ok(hashers.into_iter().map(Hasher::finalize).collect())
+}
+
+// Reads the headers and computes one digest.
+pub fn image_digest<S: ImageSource + ?Sized>(
+    source: &mut S,
+    algorithm: HashAlgorithm,
+) -> Result<Vec<u8>, AuthenticodeError> {
+    let (image, headers) = read_headers(source)?;
+    Ok(compute_digests(&image, &headers, source, &[algorithm])?.remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::SignatureDatabase;
+    use crate::pe::tests::build_image;
+    use crate::test_data::{pe_files, sig_lists};
+
+    // The rules applied by hand to the image build_image() makes.
+    fn expected_digest(buffer: &[u8]) -> Vec<u8> {
+        let image = PeImage::from_bytes(buffer).unwrap();
+        let checksum = image.checksum_offset();
+        let security = image.security_directory_entry_offset().unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(&buffer[..checksum]);
+        hasher.update(&buffer[checksum + 4..security]);
+        hasher.update(&buffer[security + 8..0x400]);
+        hasher.finalize().to_vec()
+    }
+
+    #[test]
+    fn image_digest_should_skip_checksum_and_certificates() {
+        let signed = build_image(0x20);
+        let digest = image_digest(&mut signed.as_slice(), HashAlgorithm::Sha256).unwrap();
+        assert_eq!(digest, expected_digest(&signed));
+
+        // Neither the checksum nor the certificate table affects the digest.
+        let mut changed = signed.clone();
+        let checksum = PeImage::from_bytes(&signed).unwrap().checksum_offset();
+        changed[checksum] ^= 0xFF;
+        changed[0x408] ^= 0xFF;
+        assert_eq!(
+            image_digest(&mut changed.as_slice(), HashAlgorithm::Sha256).unwrap(),
+            digest
+        );
+
+        // But trailing data before the table does.
+        let mut trailing = build_image(0);
+        trailing.extend_from_slice(&[0xAB; 0x10]);
+        let ranges = hashed_ranges(&PeImage::from_bytes(&trailing).unwrap()).unwrap();
+        assert_eq!(ranges.last(), Some(&(0x400, 0x10)));
+    }
+
+    #[test]
+    fn image_digest_should_match_signtool() {
+        let mut source = pe_files::SIGNED_CLI_64;
+        let digest = image_digest(&mut source, HashAlgorithm::Sha256).unwrap();
+        assert_eq!(digest, pe_files::SIGNED_CLI_64_SHA256);
+    }
+
+    #[test]
+    fn image_digest_should_hash_sections_in_file_order() {
+        // Three sections whose table order is the reverse of where their
+        // data sits, then 0x30 bytes of trailing data and a certificate table.
+        let mut buffer = build_image(0);
+        let coff = 0x84;
+        let optional = 0x98;
+        let table = optional + 0xF0;
+        buffer[coff + 2..coff + 4].copy_from_slice(&3u16.to_le_bytes());
+        buffer[optional + 56..optional + 60].copy_from_slice(&0x4000u32.to_le_bytes());
+        for (index, (name, address, raw)) in [
+            (b".data", 0x3000u32, 0x600u32),
+            (b".text", 0x1000, 0x200),
+            (b".rdat", 0x2000, 0x400),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let section = table + index * 40;
+            buffer[section..section + 40].fill(0);
+            buffer[section..section + 5].copy_from_slice(*name);
+            buffer[section + 8..section + 12].copy_from_slice(&0x200u32.to_le_bytes());
+            buffer[section + 12..section + 16].copy_from_slice(&address.to_le_bytes());
+            buffer[section + 16..section + 20].copy_from_slice(&0x200u32.to_le_bytes());
+            buffer[section + 20..section + 24].copy_from_slice(&raw.to_le_bytes());
+        }
+        buffer.resize(0x800, 0);
+        for (index, byte) in buffer[0x400..].iter_mut().enumerate() {
+            *byte = (index as u8).wrapping_mul(7);
+        }
+        buffer.extend_from_slice(&[0xAB; 0x30]);
+        let security = optional + 112 + 4 * 8;
+        buffer[security..security + 4].copy_from_slice(&0x830u32.to_le_bytes());
+        buffer[security + 4..security + 8].copy_from_slice(&0x20u32.to_le_bytes());
+        buffer.extend_from_slice(&[0xCC; 0x20]);
+
+        let image = PeImage::from_bytes(&buffer).unwrap();
+        let ranges = hashed_ranges(&image).unwrap();
+        assert_eq!(
+            ranges[3..],
+            [
+                (0x200, 0x200),
+                (0x400, 0x200),
+                (0x600, 0x200),
+                (0x800, 0x30)
+            ]
+        );
+
+        let checksum = image.checksum_offset();
+        let mut hasher = Sha256::new();
+        hasher.update(&buffer[..checksum]);
+        hasher.update(&buffer[checksum + 4..security]);
+        hasher.update(&buffer[security + 8..0x830]);
+        assert_eq!(
+            image_digest(&mut buffer.as_slice(), HashAlgorithm::Sha256).unwrap(),
+            hasher.finalize().to_vec()
+        );
+    }
+
+    #[test]
+    fn compute_digests_should_match_dbx_entry_format() {
+        let buffer = build_image(0x20);
+        let (image, headers) = read_headers(&mut buffer.as_slice()).unwrap();
+        let digests = compute_digests(
+            &image,
+            &headers,
+            &mut buffer.as_slice(),
+            &HashAlgorithm::ALL,
+        )
+        .unwrap();
+        for (algorithm, digest) in HashAlgorithm::ALL.iter().zip(digests.iter()) {
+            assert_eq!(digest.len(), algorithm.digest_size());
+        }
+
+        assert_eq!(digests[0], expected_digest(&buffer));
+
+        // dbx entries are bare digests in EFI_CERT_SHA256 lists, the same
+        // shape as what's computed here.
+        let dbx = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        let list = &dbx.entries[0];
+        assert_eq!(
+            HashAlgorithm::from_signature_type(&list.list_type),
+            Some(HashAlgorithm::Sha256)
+        );
+        assert!(list
+            .elements
+            .iter()
+            .all(|element| element.data.len() == digests[0].len()));
+    }
+
+    #[test]
+    fn compute_digests_should_reject_bad_certificate_tables() {
+        let mut buffer = build_image(0x20);
+        // Claim a table bigger than what follows the sections.
+        let security = PeImage::from_bytes(&buffer)
+            .unwrap()
+            .security_directory_entry_offset()
+            .unwrap();
+        buffer[security..security + 4].copy_from_slice(&0x300u32.to_le_bytes());
+        buffer[security + 4..security + 8].copy_from_slice(&0x120u32.to_le_bytes());
+        assert_eq!(
+            image_digest(&mut buffer.as_slice(), HashAlgorithm::Sha256),
+            Err(AuthenticodeError::BadCertificateTable)
+        );
+    }
+}
@@ -133,6 +133,19 @@ impl BootServices {
         }
     }
 
+    // Returns a pool buffer that firmware allocated on our behalf.
+    pub fn free_pool(&self, buffer: *mut core::ffi::c_void) -> UefiResult<()> {
+        let bs = unsafe { self.inner.as_ref() };
+
+        let status = (bs.free_pool)(buffer);
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
+
     pub fn wait_for_event(&self, events: &[efi::Event]) -> UefiResult<usize> {
         let bs = unsafe { self.inner.as_ref() };
         let mut index: usize = 0;
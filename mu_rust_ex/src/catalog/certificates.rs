@@ -0,0 +1,196 @@
+// Microsoft Corporation KEK CA 2011
+pub const MICROSOFT_KEK_CA_2011: &[u8] = &[
+    0x30, 0x82, 0x05, 0xE8, 0x30, 0x82, 0x03, 0xD0, 0xA0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x0A, 0x61,
+    0x0A, 0xD1, 0x88, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x30, 0x0D, 0x06, 0x09, 0x2A, 0x86, 0x48,
+    0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B, 0x05, 0x00, 0x30, 0x81, 0x91, 0x31, 0x0B, 0x30, 0x09, 0x06,
+    0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x13, 0x30, 0x11, 0x06, 0x03, 0x55, 0x04,
+    0x08, 0x13, 0x0A, 0x57, 0x61, 0x73, 0x68, 0x69, 0x6E, 0x67, 0x74, 0x6F, 0x6E, 0x31, 0x10, 0x30,
+    0x0E, 0x06, 0x03, 0x55, 0x04, 0x07, 0x13, 0x07, 0x52, 0x65, 0x64, 0x6D, 0x6F, 0x6E, 0x64, 0x31,
+    0x1E, 0x30, 0x1C, 0x06, 0x03, 0x55, 0x04, 0x0A, 0x13, 0x15, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73,
+    0x6F, 0x66, 0x74, 0x20, 0x43, 0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74, 0x69, 0x6F, 0x6E, 0x31,
+    0x3B, 0x30, 0x39, 0x06, 0x03, 0x55, 0x04, 0x03, 0x13, 0x32, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73,
+    0x6F, 0x66, 0x74, 0x20, 0x43, 0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74, 0x69, 0x6F, 0x6E, 0x20,
+    0x54, 0x68, 0x69, 0x72, 0x64, 0x20, 0x50, 0x61, 0x72, 0x74, 0x79, 0x20, 0x4D, 0x61, 0x72, 0x6B,
+    0x65, 0x74, 0x70, 0x6C, 0x61, 0x63, 0x65, 0x20, 0x52, 0x6F, 0x6F, 0x74, 0x30, 0x1E, 0x17, 0x0D,
+    0x31, 0x31, 0x30, 0x36, 0x32, 0x34, 0x32, 0x30, 0x34, 0x31, 0x32, 0x39, 0x5A, 0x17, 0x0D, 0x32,
+    0x36, 0x30, 0x36, 0x32, 0x34, 0x32, 0x30, 0x35, 0x31, 0x32, 0x39, 0x5A, 0x30, 0x81, 0x80, 0x31,
+    0x0B, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x13, 0x30, 0x11,
+    0x06, 0x03, 0x55, 0x04, 0x08, 0x13, 0x0A, 0x57, 0x61, 0x73, 0x68, 0x69, 0x6E, 0x67, 0x74, 0x6F,
+    0x6E, 0x31, 0x10, 0x30, 0x0E, 0x06, 0x03, 0x55, 0x04, 0x07, 0x13, 0x07, 0x52, 0x65, 0x64, 0x6D,
+    0x6F, 0x6E, 0x64, 0x31, 0x1E, 0x30, 0x1C, 0x06, 0x03, 0x55, 0x04, 0x0A, 0x13, 0x15, 0x4D, 0x69,
+    0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x20, 0x43, 0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74,
+    0x69, 0x6F, 0x6E, 0x31, 0x2A, 0x30, 0x28, 0x06, 0x03, 0x55, 0x04, 0x03, 0x13, 0x21, 0x4D, 0x69,
+    0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x20, 0x43, 0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74,
+    0x69, 0x6F, 0x6E, 0x20, 0x4B, 0x45, 0x4B, 0x20, 0x43, 0x41, 0x20, 0x32, 0x30, 0x31, 0x31, 0x30,
+    0x82, 0x01, 0x22, 0x30, 0x0D, 0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01,
+    0x05, 0x00, 0x03, 0x82, 0x01, 0x0F, 0x00, 0x30, 0x82, 0x01, 0x0A, 0x02, 0x82, 0x01, 0x01, 0x00,
+    0xC4, 0xE8, 0xB5, 0x8A, 0xBF, 0xAD, 0x57, 0x26, 0xB0, 0x26, 0xC3, 0xEA, 0xE7, 0xFB, 0x57, 0x7A,
+    0x44, 0x02, 0x5D, 0x07, 0x0D, 0xDA, 0x4A, 0xE5, 0x74, 0x2A, 0xE6, 0xB0, 0x0F, 0xEC, 0x6D, 0xEB,
+    0xEC, 0x7F, 0xB9, 0xE3, 0x5A, 0x63, 0x32, 0x7C, 0x11, 0x17, 0x4F, 0x0E, 0xE3, 0x0B, 0xA7, 0x38,
+    0x15, 0x93, 0x8E, 0xC6, 0xF5, 0xE0, 0x84, 0xB1, 0x9A, 0x9B, 0x2C, 0xE7, 0xF5, 0xB7, 0x91, 0xD6,
+    0x09, 0xE1, 0xE2, 0xC0, 0x04, 0xA8, 0xAC, 0x30, 0x1C, 0xDF, 0x48, 0xF3, 0x06, 0x50, 0x9A, 0x64,
+    0xA7, 0x51, 0x7F, 0xC8, 0x85, 0x4F, 0x8F, 0x20, 0x86, 0xCE, 0xFE, 0x2F, 0xE1, 0x9F, 0xFF, 0x82,
+    0xC0, 0xED, 0xE9, 0xCD, 0xCE, 0xF4, 0x53, 0x6A, 0x62, 0x3A, 0x0B, 0x43, 0xB9, 0xE2, 0x25, 0xFD,
+    0xFE, 0x05, 0xF9, 0xD4, 0xC4, 0x14, 0xAB, 0x11, 0xE2, 0x23, 0x89, 0x8D, 0x70, 0xB7, 0xA4, 0x1D,
+    0x4D, 0xEC, 0xAE, 0xE5, 0x9C, 0xFA, 0x16, 0xC2, 0xD7, 0xC1, 0xCB, 0xD4, 0xE8, 0xC4, 0x2F, 0xE5,
+    0x99, 0xEE, 0x24, 0x8B, 0x03, 0xEC, 0x8D, 0xF2, 0x8B, 0xEA, 0xC3, 0x4A, 0xFB, 0x43, 0x11, 0x12,
+    0x0B, 0x7E, 0xB5, 0x47, 0x92, 0x6C, 0xDC, 0xE6, 0x04, 0x89, 0xEB, 0xF5, 0x33, 0x04, 0xEB, 0x10,
+    0x01, 0x2A, 0x71, 0xE5, 0xF9, 0x83, 0x13, 0x3C, 0xFF, 0x25, 0x09, 0x2F, 0x68, 0x76, 0x46, 0xFF,
+    0xBA, 0x4F, 0xBE, 0xDC, 0xAD, 0x71, 0x2A, 0x58, 0xAA, 0xFB, 0x0E, 0xD2, 0x79, 0x3D, 0xE4, 0x9B,
+    0x65, 0x3B, 0xCC, 0x29, 0x2A, 0x9F, 0xFC, 0x72, 0x59, 0xA2, 0xEB, 0xAE, 0x92, 0xEF, 0xF6, 0x35,
+    0x13, 0x80, 0xC6, 0x02, 0xEC, 0xE4, 0x5F, 0xCC, 0x9D, 0x76, 0xCD, 0xEF, 0x63, 0x92, 0xC1, 0xAF,
+    0x79, 0x40, 0x84, 0x79, 0x87, 0x7F, 0xE3, 0x52, 0xA8, 0xE8, 0x9D, 0x7B, 0x07, 0x69, 0x8F, 0x15,
+    0x02, 0x03, 0x01, 0x00, 0x01, 0xA3, 0x82, 0x01, 0x4F, 0x30, 0x82, 0x01, 0x4B, 0x30, 0x10, 0x06,
+    0x09, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x01, 0x04, 0x03, 0x02, 0x01, 0x00, 0x30,
+    0x1D, 0x06, 0x03, 0x55, 0x1D, 0x0E, 0x04, 0x16, 0x04, 0x14, 0x62, 0xFC, 0x43, 0xCD, 0xA0, 0x3E,
+    0xA4, 0xCB, 0x67, 0x12, 0xD2, 0x5B, 0xD9, 0x55, 0xAC, 0x7B, 0xCC, 0xB6, 0x8A, 0x5F, 0x30, 0x19,
+    0x06, 0x09, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x14, 0x02, 0x04, 0x0C, 0x1E, 0x0A, 0x00,
+    0x53, 0x00, 0x75, 0x00, 0x62, 0x00, 0x43, 0x00, 0x41, 0x30, 0x0B, 0x06, 0x03, 0x55, 0x1D, 0x0F,
+    0x04, 0x04, 0x03, 0x02, 0x01, 0x86, 0x30, 0x0F, 0x06, 0x03, 0x55, 0x1D, 0x13, 0x01, 0x01, 0xFF,
+    0x04, 0x05, 0x30, 0x03, 0x01, 0x01, 0xFF, 0x30, 0x1F, 0x06, 0x03, 0x55, 0x1D, 0x23, 0x04, 0x18,
+    0x30, 0x16, 0x80, 0x14, 0x45, 0x66, 0x52, 0x43, 0xE1, 0x7E, 0x58, 0x11, 0xBF, 0xD6, 0x4E, 0x9E,
+    0x23, 0x55, 0x08, 0x3B, 0x3A, 0x22, 0x6A, 0xA8, 0x30, 0x5C, 0x06, 0x03, 0x55, 0x1D, 0x1F, 0x04,
+    0x55, 0x30, 0x53, 0x30, 0x51, 0xA0, 0x4F, 0xA0, 0x4D, 0x86, 0x4B, 0x68, 0x74, 0x74, 0x70, 0x3A,
+    0x2F, 0x2F, 0x63, 0x72, 0x6C, 0x2E, 0x6D, 0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x2E,
+    0x63, 0x6F, 0x6D, 0x2F, 0x70, 0x6B, 0x69, 0x2F, 0x63, 0x72, 0x6C, 0x2F, 0x70, 0x72, 0x6F, 0x64,
+    0x75, 0x63, 0x74, 0x73, 0x2F, 0x4D, 0x69, 0x63, 0x43, 0x6F, 0x72, 0x54, 0x68, 0x69, 0x50, 0x61,
+    0x72, 0x4D, 0x61, 0x72, 0x52, 0x6F, 0x6F, 0x5F, 0x32, 0x30, 0x31, 0x30, 0x2D, 0x31, 0x30, 0x2D,
+    0x30, 0x35, 0x2E, 0x63, 0x72, 0x6C, 0x30, 0x60, 0x06, 0x08, 0x2B, 0x06, 0x01, 0x05, 0x05, 0x07,
+    0x01, 0x01, 0x04, 0x54, 0x30, 0x52, 0x30, 0x50, 0x06, 0x08, 0x2B, 0x06, 0x01, 0x05, 0x05, 0x07,
+    0x30, 0x02, 0x86, 0x44, 0x68, 0x74, 0x74, 0x70, 0x3A, 0x2F, 0x2F, 0x77, 0x77, 0x77, 0x2E, 0x6D,
+    0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x2E, 0x63, 0x6F, 0x6D, 0x2F, 0x70, 0x6B, 0x69,
+    0x2F, 0x63, 0x65, 0x72, 0x74, 0x73, 0x2F, 0x4D, 0x69, 0x63, 0x43, 0x6F, 0x72, 0x54, 0x68, 0x69,
+    0x50, 0x61, 0x72, 0x4D, 0x61, 0x72, 0x52, 0x6F, 0x6F, 0x5F, 0x32, 0x30, 0x31, 0x30, 0x2D, 0x31,
+    0x30, 0x2D, 0x30, 0x35, 0x2E, 0x63, 0x72, 0x74, 0x30, 0x0D, 0x06, 0x09, 0x2A, 0x86, 0x48, 0x86,
+    0xF7, 0x0D, 0x01, 0x01, 0x0B, 0x05, 0x00, 0x03, 0x82, 0x02, 0x01, 0x00, 0xD4, 0x84, 0x88, 0xF5,
+    0x14, 0x94, 0x18, 0x02, 0xCA, 0x2A, 0x3C, 0xFB, 0x2A, 0x92, 0x1C, 0x0C, 0xD7, 0xA0, 0xD1, 0xF1,
+    0xE8, 0x52, 0x66, 0xA8, 0xEE, 0xA2, 0xB5, 0x75, 0x7A, 0x90, 0x00, 0xAA, 0x2D, 0xA4, 0x76, 0x5A,
+    0xEA, 0x79, 0xB7, 0xB9, 0x37, 0x6A, 0x51, 0x7B, 0x10, 0x64, 0xF6, 0xE1, 0x64, 0xF2, 0x02, 0x67,
+    0xBE, 0xF7, 0xA8, 0x1B, 0x78, 0xBD, 0xBA, 0xCE, 0x88, 0x58, 0x64, 0x0C, 0xD6, 0x57, 0xC8, 0x19,
+    0xA3, 0x5F, 0x05, 0xD6, 0xDB, 0xC6, 0xD0, 0x69, 0xCE, 0x48, 0x4B, 0x32, 0xB7, 0xEB, 0x5D, 0xD2,
+    0x30, 0xF5, 0xC0, 0xF5, 0xB8, 0xBA, 0x78, 0x07, 0xA3, 0x2B, 0xFE, 0x9B, 0xDB, 0x34, 0x56, 0x84,
+    0xEC, 0x82, 0xCA, 0xAE, 0x41, 0x25, 0x70, 0x9C, 0x6B, 0xE9, 0xFE, 0x90, 0x0F, 0xD7, 0x96, 0x1F,
+    0xE5, 0xE7, 0x94, 0x1F, 0xB2, 0x2A, 0x0C, 0x8D, 0x4B, 0xFF, 0x28, 0x29, 0x10, 0x7B, 0xF7, 0xD7,
+    0x7C, 0xA5, 0xD1, 0x76, 0xB9, 0x05, 0xC8, 0x79, 0xED, 0x0F, 0x90, 0x92, 0x9C, 0xC2, 0xFE, 0xDF,
+    0x6F, 0x7E, 0x6C, 0x0F, 0x7B, 0xD4, 0xC1, 0x45, 0xDD, 0x34, 0x51, 0x96, 0x39, 0x0F, 0xE5, 0x5E,
+    0x56, 0xD8, 0x18, 0x05, 0x96, 0xF4, 0x07, 0xA6, 0x42, 0xB3, 0xA0, 0x77, 0xFD, 0x08, 0x19, 0xF2,
+    0x71, 0x56, 0xCC, 0x9F, 0x86, 0x23, 0xA4, 0x87, 0xCB, 0xA6, 0xFD, 0x58, 0x7E, 0xD4, 0x69, 0x67,
+    0x15, 0x91, 0x7E, 0x81, 0xF2, 0x7F, 0x13, 0xE5, 0x0D, 0x8B, 0x8A, 0x3C, 0x87, 0x84, 0xEB, 0xE3,
+    0xCE, 0xBD, 0x43, 0xE5, 0xAD, 0x2D, 0x84, 0x93, 0x8E, 0x6A, 0x2B, 0x5A, 0x7C, 0x44, 0xFA, 0x52,
+    0xAA, 0x81, 0xC8, 0x2D, 0x1C, 0xBB, 0xE0, 0x52, 0xDF, 0x00, 0x11, 0xF8, 0x9A, 0x3D, 0xC1, 0x60,
+    0xB0, 0xE1, 0x33, 0xB5, 0xA3, 0x88, 0xD1, 0x65, 0x19, 0x0A, 0x1A, 0xE7, 0xAC, 0x7C, 0xA4, 0xC1,
+    0x82, 0x87, 0x4E, 0x38, 0xB1, 0x2F, 0x0D, 0xC5, 0x14, 0x87, 0x6F, 0xFD, 0x8D, 0x2E, 0xBC, 0x39,
+    0xB6, 0xE7, 0xE6, 0xC3, 0xE0, 0xE4, 0xCD, 0x27, 0x84, 0xEF, 0x94, 0x42, 0xEF, 0x29, 0x8B, 0x90,
+    0x46, 0x41, 0x3B, 0x81, 0x1B, 0x67, 0xD8, 0xF9, 0x43, 0x59, 0x65, 0xCB, 0x0D, 0xBC, 0xFD, 0x00,
+    0x92, 0x4F, 0xF4, 0x75, 0x3B, 0xA7, 0xA9, 0x24, 0xFC, 0x50, 0x41, 0x40, 0x79, 0xE0, 0x2D, 0x4F,
+    0x0A, 0x6A, 0x27, 0x76, 0x6E, 0x52, 0xED, 0x96, 0x69, 0x7B, 0xAF, 0x0F, 0xF7, 0x87, 0x05, 0xD0,
+    0x45, 0xC2, 0xAD, 0x53, 0x14, 0x81, 0x1F, 0xFB, 0x30, 0x04, 0xAA, 0x37, 0x36, 0x61, 0xDA, 0x4A,
+    0x69, 0x1B, 0x34, 0xD8, 0x68, 0xED, 0xD6, 0x02, 0xCF, 0x6C, 0x94, 0x0C, 0xD3, 0xCF, 0x6C, 0x22,
+    0x79, 0xAD, 0xB1, 0xF0, 0xBC, 0x03, 0xA2, 0x46, 0x60, 0xA9, 0xC4, 0x07, 0xC2, 0x21, 0x82, 0xF1,
+    0xFD, 0xF2, 0xE8, 0x79, 0x32, 0x60, 0xBF, 0xD8, 0xAC, 0xA5, 0x22, 0x14, 0x4B, 0xCA, 0xC1, 0xD8,
+    0x4B, 0xEB, 0x7D, 0x3F, 0x57, 0x35, 0xB2, 0xE6, 0x4F, 0x75, 0xB4, 0xB0, 0x60, 0x03, 0x22, 0x53,
+    0xAE, 0x91, 0x79, 0x1D, 0xD6, 0x9B, 0x41, 0x1F, 0x15, 0x86, 0x54, 0x70, 0xB2, 0xDE, 0x0D, 0x35,
+    0x0F, 0x7C, 0xB0, 0x34, 0x72, 0xBA, 0x97, 0x60, 0x3B, 0xF0, 0x79, 0xEB, 0xA2, 0xB2, 0x1C, 0x5D,
+    0xA2, 0x16, 0xB8, 0x87, 0xC5, 0xE9, 0x1B, 0xF6, 0xB5, 0x97, 0x25, 0x6F, 0x38, 0x9F, 0xE3, 0x91,
+    0xFA, 0x8A, 0x79, 0x98, 0xC3, 0x69, 0x0E, 0xB7, 0xA3, 0x1C, 0x20, 0x05, 0x97, 0xF8, 0xCA, 0x14,
+    0xAE, 0x00, 0xD7, 0xC4, 0xF3, 0xC0, 0x14, 0x10, 0x75, 0x6B, 0x34, 0xA0, 0x1B, 0xB5, 0x99, 0x60,
+    0xF3, 0x5C, 0xB0, 0xC5, 0x57, 0x4E, 0x36, 0xD2, 0x32, 0x84, 0xBF, 0x9E,
+];
+
+// Microsoft Windows Production PCA 2011
+pub const MICROSOFT_WINDOWS_PRODUCTION_PCA_2011: &[u8] = &[
+    0x30, 0x82, 0x05, 0xD7, 0x30, 0x82, 0x03, 0xBF, 0xA0, 0x03, 0x02, 0x01, 0x02, 0x02, 0x0A, 0x61,
+    0x07, 0x76, 0x56, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x30, 0x0D, 0x06, 0x09, 0x2A, 0x86, 0x48,
+    0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B, 0x05, 0x00, 0x30, 0x81, 0x88, 0x31, 0x0B, 0x30, 0x09, 0x06,
+    0x03, 0x55, 0x04, 0x06, 0x13, 0x02, 0x55, 0x53, 0x31, 0x13, 0x30, 0x11, 0x06, 0x03, 0x55, 0x04,
+    0x08, 0x13, 0x0A, 0x57, 0x61, 0x73, 0x68, 0x69, 0x6E, 0x67, 0x74, 0x6F, 0x6E, 0x31, 0x10, 0x30,
+    0x0E, 0x06, 0x03, 0x55, 0x04, 0x07, 0x13, 0x07, 0x52, 0x65, 0x64, 0x6D, 0x6F, 0x6E, 0x64, 0x31,
+    0x1E, 0x30, 0x1C, 0x06, 0x03, 0x55, 0x04, 0x0A, 0x13, 0x15, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73,
+    0x6F, 0x66, 0x74, 0x20, 0x43, 0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74, 0x69, 0x6F, 0x6E, 0x31,
+    0x32, 0x30, 0x30, 0x06, 0x03, 0x55, 0x04, 0x03, 0x13, 0x29, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73,
+    0x6F, 0x66, 0x74, 0x20, 0x52, 0x6F, 0x6F, 0x74, 0x20, 0x43, 0x65, 0x72, 0x74, 0x69, 0x66, 0x69,
+    0x63, 0x61, 0x74, 0x65, 0x20, 0x41, 0x75, 0x74, 0x68, 0x6F, 0x72, 0x69, 0x74, 0x79, 0x20, 0x32,
+    0x30, 0x31, 0x30, 0x30, 0x1E, 0x17, 0x0D, 0x31, 0x31, 0x31, 0x30, 0x31, 0x39, 0x31, 0x38, 0x34,
+    0x31, 0x34, 0x32, 0x5A, 0x17, 0x0D, 0x32, 0x36, 0x31, 0x30, 0x31, 0x39, 0x31, 0x38, 0x35, 0x31,
+    0x34, 0x32, 0x5A, 0x30, 0x81, 0x84, 0x31, 0x0B, 0x30, 0x09, 0x06, 0x03, 0x55, 0x04, 0x06, 0x13,
+    0x02, 0x55, 0x53, 0x31, 0x13, 0x30, 0x11, 0x06, 0x03, 0x55, 0x04, 0x08, 0x13, 0x0A, 0x57, 0x61,
+    0x73, 0x68, 0x69, 0x6E, 0x67, 0x74, 0x6F, 0x6E, 0x31, 0x10, 0x30, 0x0E, 0x06, 0x03, 0x55, 0x04,
+    0x07, 0x13, 0x07, 0x52, 0x65, 0x64, 0x6D, 0x6F, 0x6E, 0x64, 0x31, 0x1E, 0x30, 0x1C, 0x06, 0x03,
+    0x55, 0x04, 0x0A, 0x13, 0x15, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x20, 0x43,
+    0x6F, 0x72, 0x70, 0x6F, 0x72, 0x61, 0x74, 0x69, 0x6F, 0x6E, 0x31, 0x2E, 0x30, 0x2C, 0x06, 0x03,
+    0x55, 0x04, 0x03, 0x13, 0x25, 0x4D, 0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x20, 0x57,
+    0x69, 0x6E, 0x64, 0x6F, 0x77, 0x73, 0x20, 0x50, 0x72, 0x6F, 0x64, 0x75, 0x63, 0x74, 0x69, 0x6F,
+    0x6E, 0x20, 0x50, 0x43, 0x41, 0x20, 0x32, 0x30, 0x31, 0x31, 0x30, 0x82, 0x01, 0x22, 0x30, 0x0D,
+    0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01, 0x05, 0x00, 0x03, 0x82, 0x01,
+    0x0F, 0x00, 0x30, 0x82, 0x01, 0x0A, 0x02, 0x82, 0x01, 0x01, 0x00, 0xDD, 0x0C, 0xBB, 0xA2, 0xE4,
+    0x2E, 0x09, 0xE3, 0xE7, 0xC5, 0xF7, 0x96, 0x69, 0xBC, 0x00, 0x21, 0xBD, 0x69, 0x33, 0x33, 0xEF,
+    0xAD, 0x04, 0xCB, 0x54, 0x80, 0xEE, 0x06, 0x83, 0xBB, 0xC5, 0x20, 0x84, 0xD9, 0xF7, 0xD2, 0x8B,
+    0xF3, 0x38, 0xB0, 0xAB, 0xA4, 0xAD, 0x2D, 0x7C, 0x62, 0x79, 0x05, 0xFF, 0xE3, 0x4A, 0x3F, 0x04,
+    0x35, 0x20, 0x70, 0xE3, 0xC4, 0xE7, 0x6B, 0xE0, 0x9C, 0xC0, 0x36, 0x75, 0xE9, 0x8A, 0x31, 0xDD,
+    0x8D, 0x70, 0xE5, 0xDC, 0x37, 0xB5, 0x74, 0x46, 0x96, 0x28, 0x5B, 0x87, 0x60, 0x23, 0x2C, 0xBF,
+    0xDC, 0x47, 0xA5, 0x67, 0xF7, 0x51, 0x27, 0x9E, 0x72, 0xEB, 0x07, 0xA6, 0xC9, 0xB9, 0x1E, 0x3B,
+    0x53, 0x35, 0x7C, 0xE5, 0xD3, 0xEC, 0x27, 0xB9, 0x87, 0x1C, 0xFE, 0xB9, 0xC9, 0x23, 0x09, 0x6F,
+    0xA8, 0x46, 0x91, 0xC1, 0x6E, 0x96, 0x3C, 0x41, 0xD3, 0xCB, 0xA3, 0x3F, 0x5D, 0x02, 0x6A, 0x4D,
+    0xEC, 0x69, 0x1F, 0x25, 0x28, 0x5C, 0x36, 0xFF, 0xFD, 0x43, 0x15, 0x0A, 0x94, 0xE0, 0x19, 0xB4,
+    0xCF, 0xDF, 0xC2, 0x12, 0xE2, 0xC2, 0x5B, 0x27, 0xEE, 0x27, 0x78, 0x30, 0x8B, 0x5B, 0x2A, 0x09,
+    0x6B, 0x22, 0x89, 0x53, 0x60, 0x16, 0x2C, 0xC0, 0x68, 0x1D, 0x53, 0xBA, 0xEC, 0x49, 0xF3, 0x9D,
+    0x61, 0x8C, 0x85, 0x68, 0x09, 0x73, 0x44, 0x5D, 0x7D, 0xA2, 0x54, 0x2B, 0xDD, 0x79, 0xF7, 0x15,
+    0xCF, 0x35, 0x5D, 0x6C, 0x1C, 0x2B, 0x5C, 0xCE, 0xBC, 0x9C, 0x23, 0x8B, 0x6F, 0x6E, 0xB5, 0x26,
+    0xD9, 0x36, 0x13, 0xC3, 0x4F, 0xD6, 0x27, 0xAE, 0xB9, 0x32, 0x3B, 0x41, 0x92, 0x2C, 0xE1, 0xC7,
+    0xCD, 0x77, 0xE8, 0xAA, 0x54, 0x4E, 0xF7, 0x5C, 0x0B, 0x04, 0x87, 0x65, 0xB4, 0x43, 0x18, 0xA8,
+    0xB2, 0xE0, 0x6D, 0x19, 0x77, 0xEC, 0x5A, 0x24, 0xFA, 0x48, 0x03, 0x02, 0x03, 0x01, 0x00, 0x01,
+    0xA3, 0x82, 0x01, 0x43, 0x30, 0x82, 0x01, 0x3F, 0x30, 0x10, 0x06, 0x09, 0x2B, 0x06, 0x01, 0x04,
+    0x01, 0x82, 0x37, 0x15, 0x01, 0x04, 0x03, 0x02, 0x01, 0x00, 0x30, 0x1D, 0x06, 0x03, 0x55, 0x1D,
+    0x0E, 0x04, 0x16, 0x04, 0x14, 0xA9, 0x29, 0x02, 0x39, 0x8E, 0x16, 0xC4, 0x97, 0x78, 0xCD, 0x90,
+    0xF9, 0x9E, 0x4F, 0x9A, 0xE1, 0x7C, 0x55, 0xAF, 0x53, 0x30, 0x19, 0x06, 0x09, 0x2B, 0x06, 0x01,
+    0x04, 0x01, 0x82, 0x37, 0x14, 0x02, 0x04, 0x0C, 0x1E, 0x0A, 0x00, 0x53, 0x00, 0x75, 0x00, 0x62,
+    0x00, 0x43, 0x00, 0x41, 0x30, 0x0B, 0x06, 0x03, 0x55, 0x1D, 0x0F, 0x04, 0x04, 0x03, 0x02, 0x01,
+    0x86, 0x30, 0x0F, 0x06, 0x03, 0x55, 0x1D, 0x13, 0x01, 0x01, 0xFF, 0x04, 0x05, 0x30, 0x03, 0x01,
+    0x01, 0xFF, 0x30, 0x1F, 0x06, 0x03, 0x55, 0x1D, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0xD5,
+    0xF6, 0x56, 0xCB, 0x8F, 0xE8, 0xA2, 0x5C, 0x62, 0x68, 0xD1, 0x3D, 0x94, 0x90, 0x5B, 0xD7, 0xCE,
+    0x9A, 0x18, 0xC4, 0x30, 0x56, 0x06, 0x03, 0x55, 0x1D, 0x1F, 0x04, 0x4F, 0x30, 0x4D, 0x30, 0x4B,
+    0xA0, 0x49, 0xA0, 0x47, 0x86, 0x45, 0x68, 0x74, 0x74, 0x70, 0x3A, 0x2F, 0x2F, 0x63, 0x72, 0x6C,
+    0x2E, 0x6D, 0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x2E, 0x63, 0x6F, 0x6D, 0x2F, 0x70,
+    0x6B, 0x69, 0x2F, 0x63, 0x72, 0x6C, 0x2F, 0x70, 0x72, 0x6F, 0x64, 0x75, 0x63, 0x74, 0x73, 0x2F,
+    0x4D, 0x69, 0x63, 0x52, 0x6F, 0x6F, 0x43, 0x65, 0x72, 0x41, 0x75, 0x74, 0x5F, 0x32, 0x30, 0x31,
+    0x30, 0x2D, 0x30, 0x36, 0x2D, 0x32, 0x33, 0x2E, 0x63, 0x72, 0x6C, 0x30, 0x5A, 0x06, 0x08, 0x2B,
+    0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x01, 0x04, 0x4E, 0x30, 0x4C, 0x30, 0x4A, 0x06, 0x08, 0x2B,
+    0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x02, 0x86, 0x3E, 0x68, 0x74, 0x74, 0x70, 0x3A, 0x2F, 0x2F,
+    0x77, 0x77, 0x77, 0x2E, 0x6D, 0x69, 0x63, 0x72, 0x6F, 0x73, 0x6F, 0x66, 0x74, 0x2E, 0x63, 0x6F,
+    0x6D, 0x2F, 0x70, 0x6B, 0x69, 0x2F, 0x63, 0x65, 0x72, 0x74, 0x73, 0x2F, 0x4D, 0x69, 0x63, 0x52,
+    0x6F, 0x6F, 0x43, 0x65, 0x72, 0x41, 0x75, 0x74, 0x5F, 0x32, 0x30, 0x31, 0x30, 0x2D, 0x30, 0x36,
+    0x2D, 0x32, 0x33, 0x2E, 0x63, 0x72, 0x74, 0x30, 0x0D, 0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7,
+    0x0D, 0x01, 0x01, 0x0B, 0x05, 0x00, 0x03, 0x82, 0x02, 0x01, 0x00, 0x14, 0xFC, 0x7C, 0x71, 0x51,
+    0xA5, 0x79, 0xC2, 0x6E, 0xB2, 0xEF, 0x39, 0x3E, 0xBC, 0x3C, 0x52, 0x0F, 0x6E, 0x2B, 0x3F, 0x10,
+    0x13, 0x73, 0xFE, 0xA8, 0x68, 0xD0, 0x48, 0xA6, 0x34, 0x4D, 0x8A, 0x96, 0x05, 0x26, 0xEE, 0x31,
+    0x46, 0x90, 0x61, 0x79, 0xD6, 0xFF, 0x38, 0x2E, 0x45, 0x6B, 0xF4, 0xC0, 0xE5, 0x28, 0xB8, 0xDA,
+    0x1D, 0x8F, 0x8A, 0xDB, 0x09, 0xD7, 0x1A, 0xC7, 0x4C, 0x0A, 0x36, 0x66, 0x6A, 0x8C, 0xEC, 0x1B,
+    0xD7, 0x04, 0x90, 0xA8, 0x18, 0x17, 0xA4, 0x9B, 0xB9, 0xE2, 0x40, 0x32, 0x36, 0x76, 0xC4, 0xC1,
+    0x5A, 0xC6, 0xBF, 0xE4, 0x04, 0xC0, 0xEA, 0x16, 0xD3, 0xAC, 0xC3, 0x68, 0xEF, 0x62, 0xAC, 0xDD,
+    0x54, 0x6C, 0x50, 0x30, 0x58, 0xA6, 0xEB, 0x7C, 0xFE, 0x94, 0xA7, 0x4E, 0x8E, 0xF4, 0xEC, 0x7C,
+    0x86, 0x73, 0x57, 0xC2, 0x52, 0x21, 0x73, 0x34, 0x5A, 0xF3, 0xA3, 0x8A, 0x56, 0xC8, 0x04, 0xDA,
+    0x07, 0x09, 0xED, 0xF8, 0x8B, 0xE3, 0xCE, 0xF4, 0x7E, 0x8E, 0xAE, 0xF0, 0xF6, 0x0B, 0x8A, 0x08,
+    0xFB, 0x3F, 0xC9, 0x1D, 0x72, 0x7F, 0x53, 0xB8, 0xEB, 0xBE, 0x63, 0xE0, 0xE3, 0x3D, 0x31, 0x65,
+    0xB0, 0x81, 0xE5, 0xF2, 0xAC, 0xCD, 0x16, 0xA4, 0x9F, 0x3D, 0xA8, 0xB1, 0x9B, 0xC2, 0x42, 0xD0,
+    0x90, 0x84, 0x5F, 0x54, 0x1D, 0xFF, 0x89, 0xEA, 0xBA, 0x1D, 0x47, 0x90, 0x6F, 0xB0, 0x73, 0x4E,
+    0x41, 0x9F, 0x40, 0x9F, 0x5F, 0xE5, 0xA1, 0x2A, 0xB2, 0x11, 0x91, 0x73, 0x8A, 0x21, 0x28, 0xF0,
+    0xCE, 0xDE, 0x73, 0x39, 0x5F, 0x3E, 0xAB, 0x5C, 0x60, 0xEC, 0xDF, 0x03, 0x10, 0xA8, 0xD3, 0x09,
+    0xE9, 0xF4, 0xF6, 0x96, 0x85, 0xB6, 0x7F, 0x51, 0x88, 0x66, 0x47, 0x19, 0x8D, 0xA2, 0xB0, 0x12,
+    0x3D, 0x81, 0x2A, 0x68, 0x05, 0x77, 0xBB, 0x91, 0x4C, 0x62, 0x7B, 0xB6, 0xC1, 0x07, 0xC7, 0xBA,
+    0x7A, 0x87, 0x34, 0x03, 0x0E, 0x4B, 0x62, 0x7A, 0x99, 0xE9, 0xCA, 0xFC, 0xCE, 0x4A, 0x37, 0xC9,
+    0x2D, 0xA4, 0x57, 0x7C, 0x1C, 0xFE, 0x3D, 0xDC, 0xB8, 0x0F, 0x5A, 0xFA, 0xD6, 0xC4, 0xB3, 0x02,
+    0x85, 0x02, 0x3A, 0xEA, 0xB3, 0xD9, 0x6E, 0xE4, 0x69, 0x21, 0x37, 0xDE, 0x81, 0xD1, 0xF6, 0x75,
+    0x19, 0x05, 0x67, 0xD3, 0x93, 0x57, 0x5E, 0x29, 0x1B, 0x39, 0xC8, 0xEE, 0x2D, 0xE1, 0xCD, 0xE4,
+    0x45, 0x73, 0x5B, 0xD0, 0xD2, 0xCE, 0x7A, 0xAB, 0x16, 0x19, 0x82, 0x46, 0x58, 0xD0, 0x5E, 0x9D,
+    0x81, 0xB3, 0x67, 0xAF, 0x6C, 0x35, 0xF2, 0xBC, 0xE5, 0x3F, 0x24, 0xE2, 0x35, 0xA2, 0x0A, 0x75,
+    0x06, 0xF6, 0x18, 0x56, 0x99, 0xD4, 0x78, 0x2C, 0xD1, 0x05, 0x1B, 0xEB, 0xD0, 0x88, 0x01, 0x9D,
+    0xAA, 0x10, 0xF1, 0x05, 0xDF, 0xBA, 0x7E, 0x2C, 0x63, 0xB7, 0x06, 0x9B, 0x23, 0x21, 0xC4, 0xF9,
+    0x78, 0x6C, 0xE2, 0x58, 0x17, 0x06, 0x36, 0x2B, 0x91, 0x12, 0x03, 0xCC, 0xA4, 0xD9, 0xF2, 0x2D,
+    0xBA, 0xF9, 0x94, 0x9D, 0x40, 0xED, 0x18, 0x45, 0xF1, 0xCE, 0x8A, 0x5C, 0x6B, 0x3E, 0xAB, 0x03,
+    0xD3, 0x70, 0x18, 0x2A, 0x0A, 0x6A, 0xE0, 0x5F, 0x47, 0xD1, 0xD5, 0x63, 0x0A, 0x32, 0xF2, 0xAF,
+    0xD7, 0x36, 0x1F, 0x2A, 0x70, 0x5A, 0xE5, 0x42, 0x59, 0x08, 0x71, 0x4B, 0x57, 0xBA, 0x7E, 0x83,
+    0x81, 0xF0, 0x21, 0x3C, 0xF4, 0x1C, 0xC1, 0xC5, 0xB9, 0x90, 0x93, 0x0E, 0x88, 0x45, 0x93, 0x86,
+    0xE9, 0xB1, 0x20, 0x99, 0xBE, 0x98, 0xCB, 0xC5, 0x95, 0xA4, 0x5D, 0x62, 0xD6, 0xA0, 0x63, 0x08,
+    0x20, 0xBD, 0x75, 0x10, 0x77, 0x7D, 0x3D, 0xF3, 0x45, 0xB9, 0x9F, 0x97, 0x9F, 0xCB, 0x57, 0x80,
+    0x6F, 0x33, 0xA9, 0x04, 0xCF, 0x77, 0xA4, 0x62, 0x1C, 0x59, 0x7E,
+];
@@ -0,0 +1,397 @@
+/* @file
+Catalog of well-known KEK and db certificates, and profiles that pick from it.
+
+Entries are identified by SHA-1 thumbprint. An entry's certificate is its
+bundled DER, or, where none is bundled, the certificate with that thumbprint
+in a file the user supplies. Anything else in that file is a "custom"
+certificate and needs an owner GUID.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+mod certificates;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use r_efi::efi;
+
+use crate::auth_variable::{
+    EFI_CERT_X509_GUID, EFI_IMAGE_SECURITY_DATABASE, EFI_KEY_EXCHANGE_KEY_NAME,
+};
+use crate::der::DerError;
+use crate::image_authentication::{SignatureDatabase, SignatureList, SignatureListElement};
+use crate::name_db::MICROSOFT_SIGNATURE_OWNER_GUID;
+use crate::x509::Certificate;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatalogError {
+    // A name that's neither a profile nor a catalog entry.
+    UnknownName,
+    // (entry) Not bundled, and no supplied certificate has its thumbprint.
+    NotAvailable(&'static str),
+    // Supplied certificates that aren't in the catalog need an owner GUID.
+    NoCustomOwner,
+    // (error) A supplied certificate failed to parse.
+    BadCertificate(DerError),
+}
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownName => write!(f, "Unknown profile or catalog entry"),
+            Self::NotAvailable(name) => write!(
+                f,
+                "{} isn't bundled; supply its certificate in a file",
+                name
+            ),
+            Self::NoCustomOwner => write!(f, "Custom certificates need an owner GUID"),
+            Self::BadCertificate(err) => write!(f, "Invalid certificate ({})", err),
+        }
+    }
+}
+impl From<CatalogError> for efi::Status {
+    fn from(f: CatalogError) -> Self {
+        match f {
+            CatalogError::UnknownName => efi::Status::NOT_FOUND,
+            CatalogError::NotAvailable(_) => efi::Status::NOT_FOUND,
+            CatalogError::NoCustomOwner => efi::Status::INVALID_PARAMETER,
+            CatalogError::BadCertificate(x) => x.into(),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub struct CatalogEntry {
+    // Short, stable name for selecting the entry on the command line.
+    pub name: &'static str,
+    pub description: &'static str,
+    // The variable it's installed into: KEK or db.
+    pub variable: &'static str,
+    pub owner: efi::Guid,
+    pub sha1_thumbprint: [u8; 20],
+    pub certificate: Option<&'static [u8]>,
+}
+
+impl fmt::Debug for CatalogEntry {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "{} ({} -> {}{})",
+            self.name,
+            self.description,
+            self.variable,
+            if self.certificate.is_some() {
+                ""
+            } else {
+                ", not bundled"
+            }
+        ))
+    }
+}
+
+pub const CATALOG: &[CatalogEntry] = &[
+    CatalogEntry {
+        name: "ms-kek-2011",
+        description: "Microsoft Corporation KEK CA 2011",
+        variable: EFI_KEY_EXCHANGE_KEY_NAME,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x31, 0x59, 0x0B, 0xFD, 0x89, 0xC9, 0xD7, 0x4E, 0xD0, 0x87, 0xDF, 0xAC, 0x66, 0x33,
+            0x4B, 0x39, 0x31, 0x25, 0x4B, 0x30,
+        ],
+        certificate: Some(certificates::MICROSOFT_KEK_CA_2011),
+    },
+    CatalogEntry {
+        name: "ms-kek-2023",
+        description: "Microsoft Corporation KEK 2K CA 2023",
+        variable: EFI_KEY_EXCHANGE_KEY_NAME,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x45, 0x9A, 0xB6, 0xFB, 0x5E, 0x28, 0x4D, 0x27, 0x2D, 0x5E, 0x3E, 0x6A, 0xBC, 0x8E,
+            0xD6, 0x63, 0x82, 0x9D, 0x63, 0x2B,
+        ],
+        certificate: None,
+    },
+    CatalogEntry {
+        name: "ms-windows-pca-2011",
+        description: "Microsoft Windows Production PCA 2011",
+        variable: EFI_IMAGE_SECURITY_DATABASE,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x58, 0x0A, 0x6F, 0x4C, 0xC4, 0xE4, 0xB6, 0x69, 0xB9, 0xEB, 0xDC, 0x1B, 0x2B, 0x3E,
+            0x08, 0x7B, 0x80, 0xD0, 0x67, 0x8D,
+        ],
+        certificate: Some(certificates::MICROSOFT_WINDOWS_PRODUCTION_PCA_2011),
+    },
+    CatalogEntry {
+        name: "ms-windows-uefi-2023",
+        description: "Windows UEFI CA 2023",
+        variable: EFI_IMAGE_SECURITY_DATABASE,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x45, 0xA0, 0xFA, 0x32, 0x60, 0x47, 0x73, 0xC8, 0x24, 0x33, 0xC3, 0xB7, 0xD5, 0x9E,
+            0x74, 0x66, 0xB3, 0xAC, 0x0C, 0x67,
+        ],
+        certificate: None,
+    },
+    CatalogEntry {
+        name: "ms-uefi-ca-2011",
+        description: "Microsoft Corporation UEFI CA 2011 (third-party)",
+        variable: EFI_IMAGE_SECURITY_DATABASE,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x46, 0xDE, 0xF6, 0x3B, 0x5C, 0xE6, 0x1C, 0xF8, 0xBA, 0x0D, 0xE2, 0xE6, 0x63, 0x9C,
+            0x10, 0x19, 0xD0, 0xED, 0x14, 0xF3,
+        ],
+        certificate: None,
+    },
+    CatalogEntry {
+        name: "ms-uefi-ca-2023",
+        description: "Microsoft UEFI CA 2023 (third-party)",
+        variable: EFI_IMAGE_SECURITY_DATABASE,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0xB5, 0xEE, 0xB4, 0xA6, 0x70, 0x60, 0x48, 0x07, 0x3F, 0x0E, 0xD2, 0x96, 0xE7, 0xF5,
+            0x80, 0xA7, 0x90, 0xB5, 0x9E, 0xAA,
+        ],
+        certificate: None,
+    },
+    CatalogEntry {
+        name: "ms-option-rom-2023",
+        description: "Microsoft Option ROM UEFI CA 2023",
+        variable: EFI_IMAGE_SECURITY_DATABASE,
+        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+        sha1_thumbprint: [
+            0x3F, 0xB3, 0x9E, 0x2B, 0x8B, 0xD1, 0x83, 0xBF, 0x9E, 0x45, 0x94, 0xE7, 0x21, 0x83,
+            0xCA, 0x60, 0xAF, 0xCD, 0x42, 0x77,
+        ],
+        certificate: None,
+    },
+];
+
+pub fn find_entry(name: &str) -> Option<&'static CatalogEntry> {
+    CATALOG
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    // Boots Windows only.
+    MicrosoftOnly,
+    // Also trusts the third-party CA that signs shim, option ROMs, etc.
+    MicrosoftAndThirdParty,
+    // Nothing from the catalog; only certificates the user supplies.
+    CustomOnly,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [
+        Profile::MicrosoftOnly,
+        Profile::MicrosoftAndThirdParty,
+        Profile::CustomOnly,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::MicrosoftOnly => "microsoft",
+            Profile::MicrosoftAndThirdParty => "microsoft-3p",
+            Profile::CustomOnly => "custom",
+        }
+    }
+
+    pub fn entries(&self) -> Vec<&'static CatalogEntry> {
+        let names: &[&str] = match self {
+            Profile::MicrosoftOnly => &[
+                "ms-kek-2011",
+                "ms-kek-2023",
+                "ms-windows-pca-2011",
+                "ms-windows-uefi-2023",
+            ],
+            Profile::MicrosoftAndThirdParty => &[
+                "ms-kek-2011",
+                "ms-kek-2023",
+                "ms-windows-pca-2011",
+                "ms-windows-uefi-2023",
+                "ms-uefi-ca-2011",
+                "ms-uefi-ca-2023",
+                "ms-option-rom-2023",
+            ],
+            Profile::CustomOnly => &[],
+        };
+        names.iter().filter_map(|name| find_entry(name)).collect()
+    }
+}
+
+impl FromStr for Profile {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|profile| profile.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or(())
+    }
+}
+
+// A profile name, or a comma-separated list of entry names.
+pub fn select(selection: &str) -> Result<Vec<&'static CatalogEntry>, CatalogError> {
+    if let Ok(profile) = selection.parse::<Profile>() {
+        return Ok(profile.entries());
+    }
+    selection
+        .split(',')
+        .map(|name| find_entry(name.trim()).ok_or(CatalogError::UnknownName))
+        .collect()
+}
+
+// Builds the signature lists to append to `variable`: the selected entries
+// that belong there, plus any `supplied` certificates that aren't catalog
+// entries, owned by `custom_owner`.
+pub fn build_append(
+    entries: &[&'static CatalogEntry],
+    variable: &str,
+    supplied: &[Vec<u8>],
+    custom_owner: Option<&efi::Guid>,
+) -> Result<SignatureDatabase, CatalogError> {
+    let mut supplied_thumbprints = Vec::new();
+    for certificate in supplied {
+        let parsed = Certificate::from_der(certificate).map_err(CatalogError::BadCertificate)?;
+        supplied_thumbprints.push(parsed.sha1_thumbprint());
+    }
+
+    let mut certificates: Vec<(efi::Guid, Vec<u8>)> = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.variable == variable) {
+        let certificate = match entry.certificate {
+            Some(certificate) => Vec::from(certificate),
+            None => supplied_thumbprints
+                .iter()
+                .position(|thumbprint| *thumbprint == entry.sha1_thumbprint)
+                .map(|index| supplied[index].clone())
+                .ok_or(CatalogError::NotAvailable(entry.name))?,
+        };
+        certificates.push((entry.owner, certificate));
+    }
+
+    for (certificate, thumbprint) in supplied.iter().zip(supplied_thumbprints.iter()) {
+        if entries
+            .iter()
+            .copied()
+            .chain(CATALOG.iter())
+            .any(|entry| entry.sha1_thumbprint == *thumbprint)
+        {
+            continue;
+        }
+        let owner = custom_owner.ok_or(CatalogError::NoCustomOwner)?;
+        certificates.push((*owner, certificate.clone()));
+    }
+
+    // Certificates differ in size, so each one gets its own list.
+    Ok(SignatureDatabase {
+        entries: certificates
+            .into_iter()
+            .map(|(owner, data)| SignatureList {
+                list_type: EFI_CERT_X509_GUID,
+                header: None,
+                elements: vec![SignatureListElement { owner, data }],
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::auth_files;
+    use alloc::string::String;
+
+    #[test]
+    fn catalog_should_match_bundled_certificates() {
+        let bundled = CATALOG
+            .iter()
+            .filter_map(|entry| Some((entry, entry.certificate?)));
+        assert_eq!(bundled.clone().count(), 2);
+        for (entry, certificate) in bundled {
+            let parsed = Certificate::from_der(certificate).unwrap();
+            assert_eq!(parsed.sha1_thumbprint(), entry.sha1_thumbprint);
+            assert_eq!(
+                parsed.subject.common_name(),
+                Some(String::from(entry.description))
+            );
+        }
+    }
+
+    #[test]
+    fn catalog_should_select_profiles_and_entries() {
+        assert_eq!(select("Microsoft").unwrap().len(), 4);
+        assert_eq!(select("microsoft-3p").unwrap().len(), 7);
+        assert!(select("custom").unwrap().is_empty());
+        let entries = select("ms-kek-2011, ms-windows-pca-2011").unwrap();
+        assert_eq!(entries[1].variable, "db");
+        assert_eq!(
+            select("ms-kek-2011,nonsense").err(),
+            Some(CatalogError::UnknownName)
+        );
+    }
+
+    #[test]
+    fn catalog_should_build_append_lists() {
+        let entries = select("ms-kek-2011").unwrap();
+        let kek = build_append(&entries, "KEK", &[], None).unwrap();
+        assert_eq!(kek.entries.len(), 1);
+        assert_eq!(
+            kek.entries[0].elements[0].owner,
+            MICROSOFT_SIGNATURE_OWNER_GUID
+        );
+        assert!(build_append(&entries, "db", &[], None)
+            .unwrap()
+            .entries
+            .is_empty());
+
+        let microsoft = select("microsoft").unwrap();
+        assert_eq!(
+            build_append(&microsoft, "KEK", &[], None).err(),
+            Some(CatalogError::NotAvailable("ms-kek-2023"))
+        );
+
+        // An unbundled entry is taken from the supplied file by thumbprint,
+        // keeping the catalog's owner.
+        static UNBUNDLED: CatalogEntry = CatalogEntry {
+            name: "unbundled",
+            description: "Not bundled",
+            variable: EFI_KEY_EXCHANGE_KEY_NAME,
+            owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+            sha1_thumbprint: [
+                0xAF, 0x59, 0x7F, 0x94, 0x94, 0x3D, 0xCC, 0xFF, 0x79, 0x58, 0x17, 0xF4, 0xBE, 0xE0,
+                0x17, 0x45, 0xFB, 0xE6, 0xB5, 0xAC,
+            ],
+            certificate: None,
+        };
+        assert_eq!(
+            build_append(&[&UNBUNDLED], "KEK", &[], None).err(),
+            Some(CatalogError::NotAvailable("unbundled"))
+        );
+        let supplied = vec![Vec::from(auth_files::TEST_KEK_CERT)];
+        let kek = build_append(&[&UNBUNDLED], "KEK", &supplied, None).unwrap();
+        assert_eq!(kek.entries.len(), 1);
+        assert_eq!(
+            kek.entries[0].elements[0].owner,
+            MICROSOFT_SIGNATURE_OWNER_GUID
+        );
+        assert_eq!(kek.entries[0].elements[0].data, supplied[0]);
+
+        let custom = vec![Vec::from(auth_files::TEST_KEK_CERT)];
+        assert_eq!(
+            build_append(&[], "KEK", &custom, None).err(),
+            Some(CatalogError::NoCustomOwner)
+        );
+        let owner = crate::name_db::CANONICAL_SIGNATURE_OWNER_GUID;
+        let kek = build_append(&entries, "KEK", &custom, Some(&owner)).unwrap();
+        assert_eq!(kek.entries.len(), 2);
+        assert_eq!(kek.entries[1].elements[0].owner, owner);
+        assert_eq!(kek.entries[1].elements[0].data, custom[0]);
+    }
+}
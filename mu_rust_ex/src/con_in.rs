@@ -2,13 +2,21 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use alloc::string::String;
 use core::convert::TryFrom;
 use core::ptr::NonNull;
+use core_con_out::print;
 use r_efi::{efi, protocols::simple_text_input::InputKey as EfiInputKey};
+use zeroize::Zeroizing;
 
 use crate::boot::uefi_bs;
 use crate::{println, UefiResult};
 
+const SCAN_ESC: u16 = 0x17;
+// Room is reserved up front so the buffer never reallocates and leaves
+// unzeroized copies of a partial password behind.
+const MAX_PASSWORD_LENGTH: usize = 256;
+
 // #[repr(u8)]
 // pub enum CtrlKey {
 //     Null = 0,
@@ -86,4 +94,32 @@ impl ConIn {
             Err(status)
         }
     }
+
+    // Reads a line, echoing '*' for each character. Enter finishes the
+    // line and Esc abandons it with ABORTED.
+    pub fn read_password(&self) -> UefiResult<Zeroizing<String>> {
+        let mut password = Zeroizing::new(String::with_capacity(MAX_PASSWORD_LENGTH));
+        loop {
+            match self.get_char()? {
+                InputKey::Char('\r') | InputKey::Char('\n') => break,
+                InputKey::Char('\u{8}') => {
+                    if password.pop().is_none() {
+                        continue;
+                    }
+                    print!("\u{8} \u{8}");
+                }
+                InputKey::Char(c) if password.len() + c.len_utf8() <= MAX_PASSWORD_LENGTH => {
+                    password.push(c);
+                    print!("*");
+                }
+                InputKey::Ctrl(SCAN_ESC) => {
+                    println!();
+                    return Err(efi::Status::ABORTED);
+                }
+                _ => (),
+            }
+        }
+        println!();
+        Ok(password)
+    }
 }
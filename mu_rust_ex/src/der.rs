@@ -0,0 +1,574 @@
+/* @file
+Minimal DER (X.690) reader. Covers what we need to walk X.509 certificates
+and PKCS#7 structures: definite lengths, single-byte tags and a handful of
+primitive decoders. Nothing here allocates, and every access is bounds-checked.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use r_efi::efi;
+
+use crate::rustified;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0C;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_T61_STRING: u8 = 0x14;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_BMP_STRING: u8 = 0x1E;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+// [n] EXPLICIT or constructed IMPLICIT context-specific tag.
+pub const fn tag_context(n: u8) -> u8 {
+    0xA0 | n
+}
+// [n] IMPLICIT context-specific tag on a primitive type.
+pub const fn tag_context_primitive(n: u8) -> u8 {
+    0x80 | n
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DerError {
+    // (offset) Ran out of data while reading the element at offset.
+    Truncated(usize),
+    // (expected, found) The next element has the wrong tag.
+    UnexpectedTag(u8, u8),
+    // Indefinite, non-minimal or oversized length encoding.
+    BadLength,
+    // The element was well-formed, but its contents don't make sense for its type.
+    BadValue,
+}
+impl fmt::Display for DerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(offset) => write!(f, "DER data truncated at 0x{:X}", offset),
+            Self::UnexpectedTag(expected, found) => write!(
+                f,
+                "Unexpected DER tag 0x{:02X} (expected 0x{:02X})",
+                found, expected
+            ),
+            Self::BadLength => write!(f, "Invalid DER length"),
+            Self::BadValue => write!(f, "Invalid DER value"),
+        }
+    }
+}
+impl From<DerError> for efi::Status {
+    fn from(f: DerError) -> Self {
+        match f {
+            DerError::Truncated(_) => efi::Status::BAD_BUFFER_SIZE,
+            DerError::UnexpectedTag(_, _) => efi::Status::INVALID_PARAMETER,
+            DerError::BadLength => efi::Status::BAD_BUFFER_SIZE,
+            DerError::BadValue => efi::Status::INVALID_PARAMETER,
+        }
+    }
+}
+
+pub type DerResult<T> = Result<T, DerError>;
+
+// A single TLV. `raw` is the complete encoding (tag, length and contents),
+// which is what gets hashed or compared when we need the exact bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DerObject<'a> {
+    pub tag: u8,
+    pub contents: &'a [u8],
+    pub raw: &'a [u8],
+}
+
+impl<'a> DerObject<'a> {
+    pub fn reader(&self) -> DerReader<'a> {
+        DerReader::new(self.contents)
+    }
+
+    pub fn expect(self, tag: u8) -> DerResult<Self> {
+        match self.tag == tag {
+            true => Ok(self),
+            false => Err(DerError::UnexpectedTag(tag, self.tag)),
+        }
+    }
+
+    pub fn as_bool(&self) -> DerResult<bool> {
+        match self.contents {
+            [0x00] => Ok(false),
+            [0xFF] => Ok(true),
+            _ => Err(DerError::BadValue),
+        }
+    }
+
+    // Big-endian two's complement INTEGER contents with any sign padding removed.
+    pub fn as_unsigned_integer(&self) -> DerResult<&'a [u8]> {
+        match self.contents {
+            [] => Err(DerError::BadValue),
+            [0x00, rest @ ..] if !rest.is_empty() => Ok(rest),
+            contents => Ok(contents),
+        }
+    }
+
+    pub fn as_small_integer(&self) -> DerResult<u64> {
+        let bytes = self.as_unsigned_integer()?;
+        if bytes.len() > 8 || (self.contents[0] & 0x80) != 0 {
+            return Err(DerError::BadValue);
+        }
+        Ok(bytes
+            .iter()
+            .fold(0u64, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    // BIT STRING contents, minus the leading unused-bits byte.
+    pub fn as_bit_string(&self) -> DerResult<(&'a [u8], u8)> {
+        match self.contents {
+            [unused, bits @ ..] if *unused < 8 => Ok((bits, *unused)),
+            _ => Err(DerError::BadValue),
+        }
+    }
+
+    pub fn as_oid(&self) -> DerResult<Oid<'a>> {
+        self.expect(TAG_OID)?;
+        match self.contents.last() {
+            Some(byte) if byte & 0x80 == 0 => Ok(Oid(self.contents)),
+            _ => Err(DerError::BadValue),
+        }
+    }
+
+    pub fn as_string(&self) -> DerResult<String> {
+        match self.tag {
+            TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING => {
+                core::str::from_utf8(self.contents)
+                    .map(String::from)
+                    .map_err(|_| DerError::BadValue)
+            }
+            // Close enough for display purposes.
+            TAG_T61_STRING => Ok(self.contents.iter().map(|byte| char::from(*byte)).collect()),
+            TAG_BMP_STRING => {
+                if !self.contents.len().is_multiple_of(2) {
+                    return Err(DerError::BadValue);
+                }
+                let units = self
+                    .contents
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+                Ok(core::char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(core::char::REPLACEMENT_CHARACTER))
+                    .collect())
+            }
+            _ => Err(DerError::UnexpectedTag(TAG_UTF8_STRING, self.tag)),
+        }
+    }
+
+    pub fn as_time(&self) -> DerResult<rustified::Time> {
+        let digits = |bytes: &[u8]| -> DerResult<u16> {
+            bytes.iter().try_fold(0u16, |value, byte| match byte {
+                b'0'..=b'9' => Ok(value * 10 + (byte - b'0') as u16),
+                _ => Err(DerError::BadValue),
+            })
+        };
+
+        // DER requires UTC ("Z") and seconds for both forms.
+        let (year, rest) = match (self.tag, self.contents) {
+            (TAG_UTC_TIME, [yy @ .., b'Z']) if yy.len() == 12 => {
+                let year = digits(&yy[..2])?;
+                (if year >= 50 { 1900 + year } else { 2000 + year }, &yy[2..])
+            }
+            (TAG_GENERALIZED_TIME, [yyyy @ .., b'Z']) if yyyy.len() == 14 => {
+                (digits(&yyyy[..4])?, &yyyy[4..])
+            }
+            (TAG_UTC_TIME, _) | (TAG_GENERALIZED_TIME, _) => return Err(DerError::BadValue),
+            (tag, _) => return Err(DerError::UnexpectedTag(TAG_UTC_TIME, tag)),
+        };
+
+        let time = rustified::Time {
+            year,
+            month: digits(&rest[0..2])? as u8,
+            day: digits(&rest[2..4])? as u8,
+            hour: digits(&rest[4..6])? as u8,
+            minute: digits(&rest[6..8])? as u8,
+            second: digits(&rest[8..10])? as u8,
+            ..Default::default()
+        };
+        time.validate().map_err(|_| DerError::BadValue)?;
+        Ok(time)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DerReader<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.buffer.len()
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.buffer[self.offset..]
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.buffer.get(self.offset).copied()
+    }
+
+    pub fn read_any(&mut self) -> DerResult<DerObject<'a>> {
+        let start = self.offset;
+        let truncated = DerError::Truncated(start);
+        let tag = *self.buffer.get(start).ok_or(truncated)?;
+        // Multi-byte tags never show up in the structures we care about.
+        if tag & 0x1F == 0x1F {
+            return Err(DerError::BadValue);
+        }
+
+        let first_length = *self.buffer.get(start + 1).ok_or(truncated)?;
+        let (length, header_size) = match first_length {
+            0x00..=0x7F => (first_length as usize, 2),
+            0x81..=0x84 => {
+                let count = (first_length & 0x7F) as usize;
+                let length_bytes = self
+                    .buffer
+                    .get(start + 2..start + 2 + count)
+                    .ok_or(truncated)?;
+                if length_bytes[0] == 0 {
+                    return Err(DerError::BadLength);
+                }
+                let length = length_bytes
+                    .iter()
+                    .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+                if length < 0x80 {
+                    return Err(DerError::BadLength);
+                }
+                (length, 2 + count)
+            }
+            // 0x80 is the BER indefinite form, which DER forbids.
+            _ => return Err(DerError::BadLength),
+        };
+
+        let end = (start + header_size)
+            .checked_add(length)
+            .ok_or(DerError::BadLength)?;
+        if end > self.buffer.len() {
+            return Err(truncated);
+        }
+        self.offset = end;
+
+        Ok(DerObject {
+            tag,
+            contents: &self.buffer[start + header_size..end],
+            raw: &self.buffer[start..end],
+        })
+    }
+
+    pub fn read(&mut self, tag: u8) -> DerResult<DerObject<'a>> {
+        match self.peek_tag() {
+            Some(found) if found != tag => Err(DerError::UnexpectedTag(tag, found)),
+            _ => self.read_any(),
+        }
+    }
+
+    pub fn read_optional(&mut self, tag: u8) -> DerResult<Option<DerObject<'a>>> {
+        match self.peek_tag() {
+            Some(found) if found == tag => self.read_any().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // Reads the next element and requires that it was the last one.
+    pub fn read_last(&mut self, tag: u8) -> DerResult<DerObject<'a>> {
+        let object = self.read(tag)?;
+        self.finish()?;
+        Ok(object)
+    }
+
+    pub fn finish(&self) -> DerResult<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(DerError::BadLength),
+        }
+    }
+}
+
+// Encodes a single TLV with a minimal definite length.
+pub fn encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let length = contents.len();
+    let length_bytes = length.to_be_bytes();
+    let skip = length_bytes
+        .iter()
+        .take_while(|byte| **byte == 0)
+        .count()
+        .min(length_bytes.len() - 1);
+
+    let mut encoded = Vec::with_capacity(contents.len() + 2 + length_bytes.len());
+    encoded.push(tag);
+    if length < 0x80 {
+        encoded.push(length as u8);
+    } else {
+        encoded.push(0x80 | (length_bytes.len() - skip) as u8);
+        encoded.extend_from_slice(&length_bytes[skip..]);
+    }
+    encoded.extend_from_slice(contents);
+    encoded
+}
+
+// Encodes a constructed element from already-encoded children.
+pub fn encode_constructed(tag: u8, children: &[&[u8]]) -> Vec<u8> {
+    encode(tag, &children.concat())
+}
+
+// Encodes big-endian magnitude bytes as a non-negative INTEGER.
+pub fn encode_unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len().saturating_sub(1));
+    let bytes = &bytes[start..];
+    match bytes.first() {
+        None => encode(TAG_INTEGER, &[0x00]),
+        Some(top) if top & 0x80 != 0 => encode(TAG_INTEGER, &[&[0x00], bytes].concat()),
+        Some(_) => encode(TAG_INTEGER, bytes),
+    }
+}
+
+// AlgorithmIdentifier with NULL parameters, as used for RSA and SHA-2.
+pub fn encode_algorithm(oid: &[u8]) -> Vec<u8> {
+    encode_constructed(
+        TAG_SEQUENCE,
+        &[&encode(TAG_OID, oid), &encode(TAG_NULL, &[])],
+    )
+}
+
+// Encoded OBJECT IDENTIFIER contents. Comparisons are against the encoded
+// form, so well-known OIDs are kept as byte constants.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Oid<'a>(pub &'a [u8]);
+
+impl<'a> Oid<'a> {
+    pub fn name(&self) -> Option<&'static str> {
+        KNOWN_OIDS
+            .iter()
+            .find(|(oid, _)| *oid == self.0)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl<'a> fmt::Display for Oid<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let mut arcs = String::new();
+        let mut value: u64 = 0;
+        let mut first = true;
+        for byte in self.0.iter() {
+            value = (value << 7) | (byte & 0x7F) as u64;
+            if byte & 0x80 != 0 {
+                continue;
+            }
+            if first {
+                let (top, second) = match value {
+                    0..=39 => (0, value),
+                    40..=79 => (1, value - 40),
+                    _ => (2, value - 80),
+                };
+                arcs.write_fmt(format_args!("{}.{}", top, second))?;
+                first = false;
+            } else {
+                arcs.write_fmt(format_args!(".{}", value))?;
+            }
+            value = 0;
+        }
+        fmtr.write_str(&arcs)
+    }
+}
+
+impl<'a> fmt::Debug for Oid<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => fmtr.write_str(name),
+            None => fmtr.write_fmt(format_args!("{}", self)),
+        }
+    }
+}
+
+pub const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+pub const OID_SERIAL_NUMBER: &[u8] = &[0x55, 0x04, 0x05];
+pub const OID_COUNTRY: &[u8] = &[0x55, 0x04, 0x06];
+pub const OID_LOCALITY: &[u8] = &[0x55, 0x04, 0x07];
+pub const OID_STATE: &[u8] = &[0x55, 0x04, 0x08];
+pub const OID_ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0A];
+pub const OID_ORGANIZATIONAL_UNIT: &[u8] = &[0x55, 0x04, 0x0B];
+pub const OID_EMAIL_ADDRESS: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x01];
+pub const OID_DOMAIN_COMPONENT: &[u8] =
+    &[0x09, 0x92, 0x26, 0x89, 0x93, 0xF2, 0x2C, 0x64, 0x01, 0x19];
+
+pub const OID_RSA_ENCRYPTION: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+pub const OID_SHA1_WITH_RSA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x05];
+pub const OID_SHA256_WITH_RSA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B];
+pub const OID_SHA384_WITH_RSA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0C];
+pub const OID_SHA512_WITH_RSA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0D];
+pub const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+pub const OID_EC_P256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+pub const OID_EC_P384: &[u8] = &[0x2B, 0x81, 0x04, 0x00, 0x22];
+pub const OID_EC_P521: &[u8] = &[0x2B, 0x81, 0x04, 0x00, 0x23];
+pub const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+pub const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x03];
+
+pub const OID_SHA1: &[u8] = &[0x2B, 0x0E, 0x03, 0x02, 0x1A];
+pub const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+pub const OID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+pub const OID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+
+pub const OID_SUBJECT_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1D, 0x0E];
+pub const OID_KEY_USAGE: &[u8] = &[0x55, 0x1D, 0x0F];
+pub const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1D, 0x13];
+pub const OID_AUTHORITY_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1D, 0x23];
+pub const OID_EXTENDED_KEY_USAGE: &[u8] = &[0x55, 0x1D, 0x25];
+
+const KNOWN_OIDS: &[(&[u8], &str)] = &[
+    (OID_COMMON_NAME, "CN"),
+    (OID_SERIAL_NUMBER, "serialNumber"),
+    (OID_COUNTRY, "C"),
+    (OID_LOCALITY, "L"),
+    (OID_STATE, "ST"),
+    (OID_ORGANIZATION, "O"),
+    (OID_ORGANIZATIONAL_UNIT, "OU"),
+    (OID_EMAIL_ADDRESS, "emailAddress"),
+    (OID_DOMAIN_COMPONENT, "DC"),
+    (OID_RSA_ENCRYPTION, "rsaEncryption"),
+    (OID_SHA1_WITH_RSA, "sha1WithRSAEncryption"),
+    (OID_SHA256_WITH_RSA, "sha256WithRSAEncryption"),
+    (OID_SHA384_WITH_RSA, "sha384WithRSAEncryption"),
+    (OID_SHA512_WITH_RSA, "sha512WithRSAEncryption"),
+    (OID_EC_PUBLIC_KEY, "id-ecPublicKey"),
+    (OID_EC_P256, "secp256r1"),
+    (OID_EC_P384, "secp384r1"),
+    (OID_EC_P521, "secp521r1"),
+    (OID_ECDSA_WITH_SHA256, "ecdsa-with-SHA256"),
+    (OID_ECDSA_WITH_SHA384, "ecdsa-with-SHA384"),
+    (OID_SHA1, "sha1"),
+    (OID_SHA256, "sha256"),
+    (OID_SHA384, "sha384"),
+    (OID_SHA512, "sha512"),
+    (OID_SUBJECT_KEY_IDENTIFIER, "subjectKeyIdentifier"),
+    (OID_KEY_USAGE, "keyUsage"),
+    (OID_BASIC_CONSTRAINTS, "basicConstraints"),
+    (OID_AUTHORITY_KEY_IDENTIFIER, "authorityKeyIdentifier"),
+    (OID_EXTENDED_KEY_USAGE, "extendedKeyUsage"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn der_reader_should_handle_short_and_long_lengths() {
+        let mut long_form = vec![0x04, 0x81, 0x80];
+        long_form.extend_from_slice(&[0xA5; 0x80]);
+        long_form.extend_from_slice(&[0x05, 0x00]);
+
+        let mut reader = DerReader::new(&long_form);
+        let object = reader.read(TAG_OCTET_STRING).unwrap();
+        assert_eq!(object.contents.len(), 0x80);
+        assert_eq!(object.raw.len(), 0x83);
+        assert_eq!(reader.read_optional(TAG_SEQUENCE).unwrap(), None);
+        assert_eq!(reader.read_last(TAG_NULL).unwrap().contents.len(), 0);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn der_reader_should_reject_malformed_lengths() {
+        assert_eq!(
+            DerReader::new(&[0x30, 0x05, 0x01]).read_any(),
+            Err(DerError::Truncated(0))
+        );
+        assert_eq!(
+            DerReader::new(&[0x30, 0x80, 0x00, 0x00]).read_any(),
+            Err(DerError::BadLength)
+        );
+        assert_eq!(
+            DerReader::new(&[0x30, 0x81, 0x05, 0, 0, 0, 0, 0]).read_any(),
+            Err(DerError::BadLength)
+        );
+        assert_eq!(
+            DerReader::new(&[0x30, 0x84, 0xFF, 0xFF, 0xFF, 0xFF]).read_any(),
+            Err(DerError::Truncated(0))
+        );
+        assert_eq!(
+            DerReader::new(&[0x02, 0x01, 0x00]).read(TAG_SEQUENCE),
+            Err(DerError::UnexpectedTag(TAG_SEQUENCE, TAG_INTEGER))
+        );
+    }
+
+    #[test]
+    fn der_object_should_decode_primitives() {
+        let oid = DerReader::new(&[
+            0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B,
+        ])
+        .read_any()
+        .unwrap()
+        .as_oid()
+        .unwrap();
+        assert_eq!(format!("{}", oid), "1.2.840.113549.1.1.11");
+        assert_eq!(format!("{:?}", oid), "sha256WithRSAEncryption");
+
+        let integer = DerReader::new(&[0x02, 0x03, 0x00, 0x80, 0x01])
+            .read_any()
+            .unwrap();
+        assert_eq!(integer.as_unsigned_integer().unwrap(), &[0x80, 0x01]);
+        assert_eq!(integer.as_small_integer().unwrap(), 0x8001);
+
+        let time = DerReader::new(b"\x17\x0D151105223230Z").read_any().unwrap();
+        let time = time.as_time().unwrap();
+        assert_eq!((time.year, time.month, time.day), (2015, 11, 5));
+        assert_eq!((time.hour, time.minute, time.second), (22, 32, 30));
+
+        let time = DerReader::new(b"\x18\x0F20500101000000Z")
+            .read_any()
+            .unwrap();
+        assert_eq!(time.as_time().unwrap().year, 2050);
+        let time = DerReader::new(b"\x17\x0D151305223230Z").read_any().unwrap();
+        assert_eq!(time.as_time(), Err(DerError::BadValue));
+    }
+
+    #[test]
+    fn der_encode_should_round_trip_through_reader() {
+        assert_eq!(encode(TAG_NULL, &[]), &[0x05, 0x00]);
+        assert_eq!(
+            encode_unsigned_integer(&[0x00, 0x00, 0x10, 0x01]),
+            &[0x02, 0x02, 0x10, 0x01]
+        );
+        assert_eq!(encode_unsigned_integer(&[0x80]), &[0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(encode_unsigned_integer(&[0x00]), &[0x02, 0x01, 0x00]);
+
+        let long = encode(TAG_OCTET_STRING, &[0xA5; 0x100]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x00]);
+        let sequence = encode_constructed(TAG_SEQUENCE, &[&long, &encode_algorithm(OID_SHA256)]);
+        let mut reader = DerReader::new(&sequence)
+            .read_last(TAG_SEQUENCE)
+            .unwrap()
+            .reader();
+        assert_eq!(
+            reader.read(TAG_OCTET_STRING).unwrap().contents,
+            &[0xA5; 0x100][..]
+        );
+        let mut algorithm = reader.read_last(TAG_SEQUENCE).unwrap().reader();
+        assert_eq!(
+            algorithm.read_any().unwrap().as_oid().unwrap().0,
+            OID_SHA256
+        );
+        assert_eq!(algorithm.read_last(TAG_NULL).unwrap().contents.len(), 0);
+    }
+}
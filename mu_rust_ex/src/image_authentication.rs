@@ -1,57 +1,291 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
 use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
 use core::fmt;
 use r_efi::efi;
 use r_efi::efi::Guid;
 
-use crate::util::{DebugBuffer, DebugGuid};
+use crate::name_db;
+use crate::rustified;
+use crate::util::{read_guid, read_u32_le, DebugBuffer, DebugGuid};
+use crate::variable::EfiVariable;
+use crate::x509::Certificate;
 use crate::UefiResult;
 
-pub const EFI_CERT_X509_GUID: Guid = Guid::from_fields(
-    0xa5c059a1,
-    0x94e4,
-    0x4aa7,
-    0x87,
-    0xb5,
-    &[0xab, 0x15, 0x5c, 0x2b, 0xf0, 0x72],
-);
-
-pub const EFI_CERT_SHA256_GUID: Guid = Guid::from_fields(
-    0xc1c41626,
-    0x504c,
-    0x4092,
-    0xac,
-    0xa9,
-    &[0x41, 0xf9, 0x36, 0x93, 0x43, 0x28],
-);
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-struct RawEfiSignatureList {
-    signature_type: Guid,
-    signature_list_size: u32,
-    signature_header_size: u32,
-    signature_size: u32,
-}
-// Have to do this because Guid doesn't support #[repr(packed)]
-const RAW_EFI_SIGNATURE_LIST_SIZE: usize =
-    core::mem::size_of::<Guid>() + 3 * core::mem::size_of::<u32>();
-
-impl RawEfiSignatureList {
-    unsafe fn is_valid(slf: *const Self) -> bool {
+pub use crate::auth_variable::{
+    EFI_CERT_RSA2048_GUID, EFI_CERT_RSA2048_SHA1_GUID, EFI_CERT_RSA2048_SHA256_GUID,
+    EFI_CERT_SHA1_GUID, EFI_CERT_SHA224_GUID, EFI_CERT_SHA256_GUID, EFI_CERT_SHA384_GUID,
+    EFI_CERT_SHA512_GUID, EFI_CERT_X509_GUID, EFI_CERT_X509_SHA256_GUID, EFI_CERT_X509_SHA384_GUID,
+    EFI_CERT_X509_SHA512_GUID,
+};
+
+// EFI_SIGNATURE_LIST header layout. Fields are decoded individually because
+// the buffers we parse carry no alignment guarantees.
+const SIGNATURE_LIST_SIZE_OFFSET: usize = core::mem::size_of::<Guid>();
+const SIGNATURE_HEADER_SIZE_OFFSET: usize = SIGNATURE_LIST_SIZE_OFFSET + 4;
+const SIGNATURE_SIZE_OFFSET: usize = SIGNATURE_HEADER_SIZE_OFFSET + 4;
+const RAW_EFI_SIGNATURE_LIST_SIZE: usize = SIGNATURE_SIZE_OFFSET + 4;
+
+// Returns the size of the SignatureData (not including the owner) that the
+// given signature type requires, or None if the type is variable-length or unknown.
+pub fn signature_data_size(signature_type: &Guid) -> Option<usize> {
+    match *signature_type {
+        EFI_CERT_SHA1_GUID => Some(20),
+        EFI_CERT_SHA224_GUID => Some(28),
+        EFI_CERT_SHA256_GUID => Some(32),
+        EFI_CERT_SHA384_GUID => Some(48),
+        EFI_CERT_SHA512_GUID => Some(64),
+        EFI_CERT_RSA2048_GUID | EFI_CERT_RSA2048_SHA1_GUID | EFI_CERT_RSA2048_SHA256_GUID => {
+            Some(256)
+        }
+        EFI_CERT_X509_SHA256_GUID => Some(32 + rustified::Time::SERIALIZED_SIZE),
+        EFI_CERT_X509_SHA384_GUID => Some(48 + rustified::Time::SERIALIZED_SIZE),
+        EFI_CERT_X509_SHA512_GUID => Some(64 + rustified::Time::SERIALIZED_SIZE),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureTypeError {
+    // (expected, actual) SignatureData size doesn't match the declared type.
+    BadSize(usize, usize),
+    // The declared type requires data, but there is none.
+    Empty,
+    // An embedded EFI_TIME failed to parse.
+    BadTime(efi::Status),
+}
+impl fmt::Display for SignatureTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadSize(expected, actual) => write!(
+                f,
+                "Signature data is 0x{:X} bytes, expected 0x{:X}",
+                actual, expected
+            ),
+            Self::Empty => write!(f, "Signature data is empty"),
+            Self::BadTime(status) => write!(f, "Invalid TimeOfRevocation ({:?})", status),
+        }
+    }
+}
+impl From<SignatureTypeError> for efi::Status {
+    fn from(f: SignatureTypeError) -> Self {
+        match f {
+            SignatureTypeError::BadSize(_, _) => efi::Status::BAD_BUFFER_SIZE,
+            SignatureTypeError::Empty => efi::Status::BAD_BUFFER_SIZE,
+            SignatureTypeError::BadTime(x) => x,
+        }
+    }
+}
+
+// A typed view of the SignatureData in a SignatureListElement,
+// as determined by the EFI_CERT_* type of the list that holds it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypedSignature<'a> {
+    Sha1(&'a [u8; 20]),
+    Sha224(&'a [u8; 28]),
+    Sha256(&'a [u8; 32]),
+    Sha384(&'a [u8; 48]),
+    Sha512(&'a [u8; 64]),
+    Rsa2048(&'a [u8; 256]),
+    Rsa2048Sha1(&'a [u8; 256]),
+    Rsa2048Sha256(&'a [u8; 256]),
+    X509(&'a [u8]),
+    X509Sha256(&'a [u8; 32], rustified::Time),
+    X509Sha384(&'a [u8; 48], rustified::Time),
+    X509Sha512(&'a [u8; 64], rustified::Time),
+    // Anything we don't recognize is passed through untouched.
+    Unknown(Guid, &'a [u8]),
+}
+
+impl<'a> TypedSignature<'a> {
+    pub fn new(signature_type: &Guid, data: &'a [u8]) -> Result<Self, SignatureTypeError> {
+        if let Some(expected_size) = signature_data_size(signature_type) {
+            if data.len() != expected_size {
+                return Err(SignatureTypeError::BadSize(expected_size, data.len()));
+            }
+        }
+
+        // Sizes have been checked above, so the conversions below can't fail.
+        fn fixed<const N: usize>(data: &[u8]) -> &[u8; N] {
+            data[..N].try_into().unwrap()
+        }
+        fn revocation_time(data: &[u8]) -> Result<rustified::Time, SignatureTypeError> {
+            rustified::Time::from_bytes(data).map_err(SignatureTypeError::BadTime)
+        }
+
+        Ok(match *signature_type {
+            EFI_CERT_SHA1_GUID => Self::Sha1(fixed(data)),
+            EFI_CERT_SHA224_GUID => Self::Sha224(fixed(data)),
+            EFI_CERT_SHA256_GUID => Self::Sha256(fixed(data)),
+            EFI_CERT_SHA384_GUID => Self::Sha384(fixed(data)),
+            EFI_CERT_SHA512_GUID => Self::Sha512(fixed(data)),
+            EFI_CERT_RSA2048_GUID => Self::Rsa2048(fixed(data)),
+            EFI_CERT_RSA2048_SHA1_GUID => Self::Rsa2048Sha1(fixed(data)),
+            EFI_CERT_RSA2048_SHA256_GUID => Self::Rsa2048Sha256(fixed(data)),
+            EFI_CERT_X509_GUID => match data.len() {
+                0 => return Err(SignatureTypeError::Empty),
+                _ => Self::X509(data),
+            },
+            EFI_CERT_X509_SHA256_GUID => {
+                Self::X509Sha256(fixed(data), revocation_time(&data[32..])?)
+            }
+            EFI_CERT_X509_SHA384_GUID => {
+                Self::X509Sha384(fixed(data), revocation_time(&data[48..])?)
+            }
+            EFI_CERT_X509_SHA512_GUID => {
+                Self::X509Sha512(fixed(data), revocation_time(&data[64..])?)
+            }
+            _ => Self::Unknown(*signature_type, data),
+        })
+    }
+}
+
+// A borrowed view of a single EFI_SIGNATURE_DATA within a list.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SignatureElementRef<'a> {
+    pub owner: Guid,
+    pub data: &'a [u8],
+}
+
+impl<'a> SignatureElementRef<'a> {
+    pub fn from_bytes(buffer: &'a [u8]) -> UefiResult<Self> {
+        Ok(Self {
+            owner: read_guid(buffer, 0).ok_or(efi::Status::BAD_BUFFER_SIZE)?,
+            data: &buffer[core::mem::size_of::<Guid>()..],
+        })
+    }
+
+    pub fn typed(&self, signature_type: &Guid) -> Result<TypedSignature<'a>, SignatureTypeError> {
+        TypedSignature::new(signature_type, self.data)
+    }
+}
+
+impl<'a> fmt::Debug for SignatureElementRef<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "SignatureElementRef {{ Owner: {:?}, Size: 0x{:X} }}",
+            DebugGuid::from(self.owner),
+            self.data.len()
+        ))
+    }
+}
+
+// A borrowed, validated view of an EFI_SIGNATURE_LIST. Elements are
+// decoded lazily as they're iterated, so nothing is copied up front.
+#[derive(Clone, Copy)]
+pub struct SignatureListRef<'a> {
+    list_type: Guid,
+    header: &'a [u8],
+    signature_size: usize,
+    elements: &'a [u8],
+}
+
+impl<'a> SignatureListRef<'a> {
+    pub fn from_bytes(buffer: &'a [u8]) -> UefiResult<Self> {
+        if buffer.len() < RAW_EFI_SIGNATURE_LIST_SIZE {
+            return Err(efi::Status::INVALID_PARAMETER);
+        }
+
+        let read_size = |offset| read_u32_le(buffer, offset).map(|size| size as usize);
+        let list_type = read_guid(buffer, 0).ok_or(efi::Status::INVALID_PARAMETER)?;
+        let total_size =
+            read_size(SIGNATURE_LIST_SIZE_OFFSET).ok_or(efi::Status::INVALID_PARAMETER)?;
+        let header_size =
+            read_size(SIGNATURE_HEADER_SIZE_OFFSET).ok_or(efi::Status::INVALID_PARAMETER)?;
+        let signature_size =
+            read_size(SIGNATURE_SIZE_OFFSET).ok_or(efi::Status::INVALID_PARAMETER)?;
+
+        // Make sure that the buffer sizes live within a contraint.
+        // Every element must at least hold an owner, and the list must fit in the buffer.
+        if signature_size < core::mem::size_of::<Guid>() || total_size > buffer.len() {
+            return Err(efi::Status::BAD_BUFFER_SIZE);
+        }
         // Check that all internal sizes are less than the full size.
-        ((RAW_EFI_SIGNATURE_LIST_SIZE
-            + ((*slf).signature_header_size as usize)
-            + ((*slf).signature_size as usize))
-            <= ((*slf).signature_list_size as usize)) &&
-        // Check that the total size is an even multiple of the
-        // signature size.
-        ((*slf).signature_list_size as usize
-            - RAW_EFI_SIGNATURE_LIST_SIZE
-            - (*slf).signature_header_size as usize)
-            % (*slf).signature_size as usize == 0
+        let elements_start = RAW_EFI_SIGNATURE_LIST_SIZE
+            .checked_add(header_size)
+            .ok_or(efi::Status::BAD_BUFFER_SIZE)?;
+        match elements_start.checked_add(signature_size) {
+            Some(minimum_size) if minimum_size <= total_size => (),
+            _ => return Err(efi::Status::BAD_BUFFER_SIZE),
+        }
+        // Check that the total size is an even multiple of the signature size.
+        if (total_size - elements_start) % signature_size != 0 {
+            return Err(efi::Status::BAD_BUFFER_SIZE);
+        }
+
+        Ok(Self {
+            list_type,
+            header: &buffer[RAW_EFI_SIGNATURE_LIST_SIZE..elements_start],
+            signature_size,
+            elements: &buffer[elements_start..total_size],
+        })
+    }
+
+    pub fn list_type(&self) -> Guid {
+        self.list_type
+    }
+
+    pub fn header(&self) -> Option<&'a [u8]> {
+        match self.header.len() {
+            0 => None,
+            _ => Some(self.header),
+        }
+    }
+
+    pub fn signature_size(&self) -> usize {
+        self.signature_size
+    }
+
+    pub fn get_size(&self) -> usize {
+        RAW_EFI_SIGNATURE_LIST_SIZE + self.header.len() + self.elements.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len() / self.signature_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn elements(&self) -> SignatureElements<'a> {
+        SignatureElements {
+            inner: self.elements.chunks_exact(self.signature_size),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for SignatureListRef<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "SignatureListRef {{ Type: {:?}, Count: {:?} }}",
+            DebugGuid::from(self.list_type),
+            self.len()
+        ))
     }
 }
 
+pub struct SignatureElements<'a> {
+    inner: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Iterator for SignatureElements<'a> {
+    type Item = SignatureElementRef<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // SignatureListRef guarantees every chunk is large enough to hold an owner.
+        self.inner
+            .next()
+            .map(|chunk| SignatureElementRef::from_bytes(chunk).unwrap())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for SignatureElements<'a> {}
+
 #[derive(Clone)]
 pub struct SignatureListElement {
     pub owner: Guid,
@@ -60,14 +294,44 @@ pub struct SignatureListElement {
 
 impl SignatureListElement {
     pub fn from_bytes(buffer: &[u8]) -> UefiResult<Self> {
-        if buffer.len() < core::mem::size_of::<Guid>() {
-            return Err(efi::Status::BAD_BUFFER_SIZE);
+        SignatureElementRef::from_bytes(buffer).map(Self::from)
+    }
+
+    pub fn get_size(&self) -> usize {
+        core::mem::size_of::<Guid>() + self.data.len()
+    }
+
+    pub fn typed(&self, signature_type: &Guid) -> Result<TypedSignature<'_>, SignatureTypeError> {
+        TypedSignature::new(signature_type, &self.data)
+    }
+
+    pub fn write_into(&self, buffer: &mut [u8]) -> UefiResult<usize> {
+        let size = self.get_size();
+        if buffer.len() < size {
+            return Err(efi::Status::BUFFER_TOO_SMALL);
         }
 
-        Ok(SignatureListElement {
-            owner: unsafe { *(buffer.as_ptr() as *const Guid) },
-            data: Vec::<u8>::from(&buffer[core::mem::size_of::<Guid>()..]),
-        })
+        let (owner_bytes, data_bytes) = buffer[..size].split_at_mut(core::mem::size_of::<Guid>());
+        owner_bytes.copy_from_slice(self.owner.as_bytes());
+        data_bytes.copy_from_slice(&self.data);
+
+        Ok(size)
+    }
+
+    pub fn to_bytes(&self) -> UefiResult<Vec<u8>> {
+        let mut data = vec![0u8; self.get_size()];
+        let actual_size = self.write_into(&mut data)?;
+        data.truncate(actual_size);
+        Ok(data)
+    }
+}
+
+impl<'a> From<SignatureElementRef<'a>> for SignatureListElement {
+    fn from(f: SignatureElementRef<'a>) -> Self {
+        Self {
+            owner: f.owner,
+            data: Vec::<u8>::from(f.data),
+        }
     }
 }
 
@@ -79,6 +343,9 @@ impl fmt::Debug for SignatureListElement {
                 "\tOwner: {:#?}\n",
                 DebugGuid::from(self.owner)
             ))?;
+            if let Some(name) = name_db::lookup_digest(&self.data) {
+                fmtr.write_fmt(format_args!("\tName: {}\n", name))?;
+            }
             fmtr.write_fmt(format_args!("\tData: 0x{:X?} bytes\n", self.data.len()))?;
             fmtr.write_fmt(format_args!("{:#?}\n", DebugBuffer::new(&self.data)))?;
             Ok(())
@@ -100,51 +367,101 @@ pub struct SignatureList {
 
 impl SignatureList {
     pub fn from_bytes(buffer: &[u8]) -> UefiResult<Self> {
-        if buffer.len() < RAW_EFI_SIGNATURE_LIST_SIZE {
+        SignatureListRef::from_bytes(buffer).map(Self::from)
+    }
+
+    // Interprets every element according to the list type. Fails if any
+    // element doesn't fit the declared type.
+    pub fn typed_elements(&self) -> Result<Vec<TypedSignature<'_>>, SignatureTypeError> {
+        self.elements
+            .iter()
+            .map(|element| element.typed(&self.list_type))
+            .collect()
+    }
+
+    pub fn get_header_size(&self) -> usize {
+        self.header.as_ref().map_or(0, |header| header.len())
+    }
+
+    // All elements in a list share a single signature_size, so a list
+    // with no elements (or mismatched elements) can't be represented.
+    pub fn get_signature_size(&self) -> UefiResult<usize> {
+        let signature_size = self
+            .elements
+            .first()
+            .ok_or(efi::Status::INVALID_PARAMETER)?
+            .get_size();
+        if self
+            .elements
+            .iter()
+            .any(|element| element.get_size() != signature_size)
+        {
             return Err(efi::Status::INVALID_PARAMETER);
         }
+        Ok(signature_size)
+    }
 
-        let total_size: usize;
-        let header_size: usize;
-        let signature_size: usize;
-        unsafe {
-            let raw_ptr = buffer.as_ptr() as *const RawEfiSignatureList;
-            // Make sure that the buffer sizes live within a contraint.
-            if !RawEfiSignatureList::is_valid(raw_ptr)
-                || (*raw_ptr).signature_list_size as usize > buffer.len()
-            {
-                return Err(efi::Status::BAD_BUFFER_SIZE);
-            }
+    pub fn get_size(&self) -> UefiResult<usize> {
+        Ok(RAW_EFI_SIGNATURE_LIST_SIZE
+            + self.get_header_size()
+            + self.elements.len() * self.get_signature_size()?)
+    }
 
-            total_size = (*raw_ptr).signature_list_size as usize;
-            header_size = (*raw_ptr).signature_header_size as usize;
-            signature_size = (*raw_ptr).signature_size as usize;
+    pub fn write_into(&self, buffer: &mut [u8]) -> UefiResult<usize> {
+        let total_size = self.get_size()?;
+        if buffer.len() < total_size {
+            return Err(efi::Status::BUFFER_TOO_SMALL);
         }
 
-        // Create the basic result.
-        let mut result = Self {
-            list_type: unsafe { *(buffer.as_ptr() as *const Guid) },
-            header: match header_size {
-                0 => None,
-                _ => Some(Vec::<u8>::from(
-                    &buffer[RAW_EFI_SIGNATURE_LIST_SIZE..RAW_EFI_SIGNATURE_LIST_SIZE + header_size],
-                )),
-            },
-            elements: Vec::<SignatureListElement>::new(),
-        };
+        // Populate the raw list header.
+        let size_fields = [
+            total_size,
+            self.get_header_size(),
+            self.get_signature_size()?,
+        ];
+        let (guid_bytes, remainder) =
+            buffer[..total_size].split_at_mut(core::mem::size_of::<Guid>());
+        guid_bytes.copy_from_slice(self.list_type.as_bytes());
+        let (mut size_bytes, mut remainder) =
+            remainder.split_at_mut(3 * core::mem::size_of::<u32>());
+        for size in size_fields.iter() {
+            let size = u32::try_from(*size).map_err(|_| efi::Status::BAD_BUFFER_SIZE)?;
+            let (field_bytes, next) = size_bytes.split_at_mut(core::mem::size_of::<u32>());
+            field_bytes.copy_from_slice(&size.to_le_bytes());
+            size_bytes = next;
+        }
+
+        // Populate the optional signature header.
+        if let Some(header) = &self.header {
+            let (header_bytes, next) = remainder.split_at_mut(header.len());
+            header_bytes.copy_from_slice(header);
+            remainder = next;
+        }
 
-        // Add any signature elements.
-        let elements_start = RAW_EFI_SIGNATURE_LIST_SIZE + header_size;
-        let elements_size = total_size - elements_start;
-        for list_index in 0..(elements_size / signature_size) {
-            let element_start = elements_start + list_index * signature_size;
-            let element_end = element_start + signature_size;
-            result.elements.push(SignatureListElement::from_bytes(
-                &buffer[element_start..element_end],
-            )?);
+        // Populate the signature elements.
+        for element in self.elements.iter() {
+            let element_size = element.write_into(remainder)?;
+            remainder = &mut remainder[element_size..];
         }
 
-        Ok(result)
+        Ok(total_size)
+    }
+
+    pub fn to_bytes(&self) -> UefiResult<Vec<u8>> {
+        let mut data = vec![0u8; self.get_size()?];
+        let actual_size = self.write_into(&mut data)?;
+        data.truncate(actual_size);
+        Ok(data)
+    }
+}
+
+impl<'a> From<SignatureListRef<'a>> for SignatureList {
+    fn from(f: SignatureListRef<'a>) -> Self {
+        Self {
+            list_type: f.list_type(),
+            header: f.header().map(Vec::<u8>::from),
+            elements: f.elements().map(SignatureListElement::from).collect(),
+        }
     }
 }
 
@@ -157,7 +474,26 @@ impl fmt::Debug for SignatureList {
                 DebugGuid::from(self.list_type)
             ))?;
             for i in 0..self.elements.len() {
-                fmtr.write_fmt(format_args!("{:#?}", self.elements[i]))?;
+                // Certificates are far more useful decoded than dumped.
+                // Anything that won't parse still gets the hexdump.
+                let certificate = match self.list_type == EFI_CERT_X509_GUID {
+                    true => Certificate::from_der(&self.elements[i].data).ok(),
+                    false => None,
+                };
+                match certificate {
+                    Some(certificate) => {
+                        fmtr.write_str("SignatureListElement:\n")?;
+                        fmtr.write_fmt(format_args!(
+                            "\tOwner: {:#?}\n",
+                            DebugGuid::from(self.elements[i].owner)
+                        ))?;
+                        if let Some(name) = name_db::lookup_certificate(&certificate) {
+                            fmtr.write_fmt(format_args!("\tName: {}\n", name))?;
+                        }
+                        fmtr.write_fmt(format_args!("{:#?}", certificate))?;
+                    }
+                    None => fmtr.write_fmt(format_args!("{:#?}", self.elements[i]))?,
+                }
             }
             Ok(())
         } else {
@@ -170,11 +506,493 @@ impl fmt::Debug for SignatureList {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureDatabaseError {
+    // (offset, status) The list starting at offset failed to parse.
+    InvalidList(usize, efi::Status),
+    // (offset, list_size) The list starting at offset claims more bytes than remain.
+    ListOverrun(usize, usize),
+    // (offset) Bytes starting at offset are too short to be another list.
+    TrailingData(usize),
+}
+impl fmt::Display for SignatureDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidList(offset, status) => {
+                write!(f, "Invalid SignatureList at 0x{:X} ({:?})", offset, status)
+            }
+            Self::ListOverrun(offset, size) => write!(
+                f,
+                "SignatureList at 0x{:X} overruns the buffer (size 0x{:X})",
+                offset, size
+            ),
+            Self::TrailingData(offset) => write!(f, "Trailing data at 0x{:X}", offset),
+        }
+    }
+}
+impl From<SignatureDatabaseError> for efi::Status {
+    fn from(f: SignatureDatabaseError) -> Self {
+        match f {
+            SignatureDatabaseError::InvalidList(_, x) => x,
+            SignatureDatabaseError::ListOverrun(_, _) => efi::Status::BAD_BUFFER_SIZE,
+            SignatureDatabaseError::TrailingData(_) => efi::Status::BAD_BUFFER_SIZE,
+        }
+    }
+}
+
+// A borrowed view of a complete db/dbx/KEK payload. The whole buffer is
+// validated up front, then lists are handed out lazily.
+#[derive(Clone, Copy)]
+pub struct SignatureDatabaseRef<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> SignatureDatabaseRef<'a> {
+    pub fn from_bytes(buffer: &'a [u8]) -> Result<Self, SignatureDatabaseError> {
+        let mut offset = 0;
+        while offset < buffer.len() {
+            let remainder = &buffer[offset..];
+            if remainder.len() < RAW_EFI_SIGNATURE_LIST_SIZE {
+                return Err(SignatureDatabaseError::TrailingData(offset));
+            }
+
+            // Every list states its own size, so make sure it stays inside the buffer
+            // before handing it off to be parsed.
+            let list_size = read_u32_le(remainder, SIGNATURE_LIST_SIZE_OFFSET)
+                .ok_or(SignatureDatabaseError::TrailingData(offset))?
+                as usize;
+            if list_size > remainder.len() {
+                return Err(SignatureDatabaseError::ListOverrun(offset, list_size));
+            }
+            if list_size < RAW_EFI_SIGNATURE_LIST_SIZE {
+                return Err(SignatureDatabaseError::InvalidList(
+                    offset,
+                    efi::Status::BAD_BUFFER_SIZE,
+                ));
+            }
+
+            SignatureListRef::from_bytes(&remainder[..list_size])
+                .map_err(|status| SignatureDatabaseError::InvalidList(offset, status))?;
+            offset += list_size;
+        }
+
+        Ok(Self { buffer })
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buffer
+    }
+
+    pub fn lists(&self) -> SignatureLists<'a> {
+        SignatureLists {
+            remainder: self.buffer,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for SignatureDatabaseRef<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_list().entries(self.lists()).finish()
+    }
+}
+
+pub struct SignatureLists<'a> {
+    remainder: &'a [u8],
+}
+
+impl<'a> Iterator for SignatureLists<'a> {
+    type Item = SignatureListRef<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remainder.is_empty() {
+            return None;
+        }
+
+        // SignatureDatabaseRef has already validated every list in the buffer.
+        let list = SignatureListRef::from_bytes(self.remainder).unwrap();
+        self.remainder = &self.remainder[list.get_size()..];
+        Some(list)
+    }
+}
+
+// A single signature detached from the list that held it. Used to report
+// what changed when a database is modified.
+#[derive(Clone, PartialEq)]
+pub struct SignatureEntry {
+    pub signature_type: Guid,
+    pub owner: Guid,
+    pub data: Vec<u8>,
+}
+
+impl SignatureEntry {
+    pub fn new(signature_type: &Guid, element: &SignatureListElement) -> Self {
+        Self {
+            signature_type: *signature_type,
+            owner: element.owner,
+            data: element.data.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for SignatureEntry {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "SignatureEntry {{ Type: {:?}, Owner: {:?}, Size: 0x{:X} }}",
+            DebugGuid::from(self.signature_type),
+            DebugGuid::from(self.owner),
+            self.data.len()
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDatabaseReport {
+    pub added: Vec<SignatureEntry>,
+    pub duplicates: Vec<SignatureEntry>,
+    pub removed: Vec<SignatureEntry>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SignatureOwnerChange {
+    pub entry: SignatureEntry,
+    pub new_owner: Guid,
+}
+
+// Result of comparing two databases. Entries are matched by (type, owner, data);
+// an entry whose type and data match but whose owner differs is reported
+// as an owner change rather than a remove + add.
+#[derive(Clone, Default)]
+pub struct SignatureDatabaseDiff {
+    pub added: Vec<SignatureEntry>,
+    pub removed: Vec<SignatureEntry>,
+    pub owner_changed: Vec<SignatureOwnerChange>,
+}
+
+impl SignatureDatabaseDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.owner_changed.is_empty()
+    }
+}
+
+// Short hashes are printed inline, anything larger just reports its size.
+struct DebugEntryData<'a>(&'a [u8]);
+impl<'a> fmt::Debug for DebugEntryData<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.len() <= 64 {
+            for byte in self.0.iter() {
+                fmtr.write_fmt(format_args!("{:02X}", byte))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!("0x{:X} bytes", self.0.len()))
+        }
+    }
+}
+
+impl fmt::Debug for SignatureDatabaseDiff {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("SignatureDatabaseDiff:\n")?;
+            fmtr.write_fmt(format_args!("\tAdded: {}\n", self.added.len()))?;
+            for entry in self.added.iter() {
+                fmtr.write_fmt(format_args!(
+                    "\t+ {:?} {:?}\n\t    {:?}\n",
+                    DebugGuid::from(entry.signature_type),
+                    DebugGuid::from(entry.owner),
+                    DebugEntryData(&entry.data)
+                ))?;
+            }
+            fmtr.write_fmt(format_args!("\tRemoved: {}\n", self.removed.len()))?;
+            for entry in self.removed.iter() {
+                fmtr.write_fmt(format_args!(
+                    "\t- {:?} {:?}\n\t    {:?}\n",
+                    DebugGuid::from(entry.signature_type),
+                    DebugGuid::from(entry.owner),
+                    DebugEntryData(&entry.data)
+                ))?;
+            }
+            fmtr.write_fmt(format_args!(
+                "\tOwner Changed: {}\n",
+                self.owner_changed.len()
+            ))?;
+            for change in self.owner_changed.iter() {
+                fmtr.write_fmt(format_args!(
+                    "\t~ {:?} {:?} -> {:?}\n\t    {:?}\n",
+                    DebugGuid::from(change.entry.signature_type),
+                    DebugGuid::from(change.entry.owner),
+                    DebugGuid::from(change.new_owner),
+                    DebugEntryData(&change.entry.data)
+                ))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "SignatureDatabaseDiff {{ Added: {}, Removed: {}, Owner Changed: {} }}",
+                self.added.len(),
+                self.removed.len(),
+                self.owner_changed.len()
+            ))
+        }
+    }
+}
+
+// Signatures are matched on (type, data) first, with the owner compared
+// separately so that owner changes can be told apart from add/remove.
+type SignatureKey<'a> = ([u8; 16], &'a [u8]);
+
+fn signature_key(entry: &SignatureEntry) -> SignatureKey<'_> {
+    (*entry.signature_type.as_bytes(), entry.data.as_slice())
+}
+
+fn index_by_signature(entries: &[SignatureEntry]) -> BTreeMap<SignatureKey<'_>, Vec<Guid>> {
+    let mut index = BTreeMap::<SignatureKey, Vec<Guid>>::new();
+    for entry in entries.iter() {
+        index
+            .entry(signature_key(entry))
+            .or_default()
+            .push(entry.owner);
+    }
+    index
+}
+
+// Takes one occurrence of `entry` out of `index`, so duplicates are only
+// matched as many times as they appear.
+fn take_from_index<'a>(
+    index: &mut BTreeMap<SignatureKey<'a>, Vec<Guid>>,
+    entry: &'a SignatureEntry,
+) -> bool {
+    let owners = match index.get_mut(&signature_key(entry)) {
+        Some(owners) => owners,
+        None => return false,
+    };
+    match owners.iter().position(|owner| *owner == entry.owner) {
+        Some(position) => {
+            owners.swap_remove(position);
+            true
+        }
+        None => false,
+    }
+}
+
 #[derive(Clone)]
 pub struct SignatureDatabase {
     pub entries: Vec<SignatureList>,
 }
 
+impl SignatureDatabase {
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, SignatureDatabaseError> {
+        Ok(Self::from(SignatureDatabaseRef::from_bytes(buffer)?))
+    }
+
+    // Matches the firmware check for an existing signature: the same type,
+    // and the same SignatureData (owner included).
+    pub fn contains(&self, signature_type: &Guid, element: &SignatureListElement) -> bool {
+        self.entries
+            .iter()
+            .filter(|list| list.list_type == *signature_type)
+            .any(|list| {
+                list.elements.iter().any(|existing| {
+                    existing.owner == element.owner && existing.data == element.data
+                })
+            })
+    }
+
+    // Applies `other` the way firmware applies an EFI_VARIABLE_APPEND_WRITE to
+    // db/dbx/KEK. Existing lists are left untouched. Each incoming list is
+    // filtered against the *existing* contents only, and whatever survives
+    // is appended as a new list with the same type and header.
+    pub fn merge(&mut self, other: &SignatureDatabase) -> SignatureDatabaseReport {
+        let mut report = SignatureDatabaseReport::default();
+        let mut new_lists = Vec::<SignatureList>::new();
+
+        for list in other.entries.iter() {
+            let mut new_list = SignatureList {
+                list_type: list.list_type,
+                header: list.header.clone(),
+                elements: Vec::<SignatureListElement>::new(),
+            };
+            for element in list.elements.iter() {
+                let entry = SignatureEntry::new(&list.list_type, element);
+                if self.contains(&list.list_type, element) {
+                    report.duplicates.push(entry);
+                } else {
+                    report.added.push(entry);
+                    new_list.elements.push(element.clone());
+                }
+            }
+            if !new_list.elements.is_empty() {
+                new_lists.push(new_list);
+            }
+        }
+
+        self.entries.append(&mut new_lists);
+        report
+    }
+
+    // Drops every repeat of a signature that already appeared earlier in the
+    // database, along with any lists that end up empty.
+    pub fn dedup(&mut self) -> SignatureDatabaseReport {
+        let mut report = SignatureDatabaseReport::default();
+        let mut seen = Vec::<SignatureEntry>::new();
+
+        for list in self.entries.iter_mut() {
+            let list_type = list.list_type;
+            list.elements.retain(|element| {
+                let entry = SignatureEntry::new(&list_type, element);
+                if seen.contains(&entry) {
+                    report.duplicates.push(entry);
+                    false
+                } else {
+                    seen.push(entry);
+                    true
+                }
+            });
+        }
+
+        self.entries.retain(|list| !list.elements.is_empty());
+        report
+    }
+
+    // Removes every signature for which `predicate(signature_type, element)`
+    // returns true, along with any lists that end up empty.
+    pub fn remove_entries<F>(&mut self, mut predicate: F) -> SignatureDatabaseReport
+    where
+        F: FnMut(&Guid, &SignatureListElement) -> bool,
+    {
+        let mut report = SignatureDatabaseReport::default();
+
+        for list in self.entries.iter_mut() {
+            let list_type = list.list_type;
+            list.elements.retain(|element| {
+                if predicate(&list_type, element) {
+                    report
+                        .removed
+                        .push(SignatureEntry::new(&list_type, element));
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        self.entries.retain(|list| !list.elements.is_empty());
+        report
+    }
+
+    // Flattens the database into one entry per signature, in order.
+    pub fn signature_entries(&self) -> Vec<SignatureEntry> {
+        self.entries
+            .iter()
+            .flat_map(|list| {
+                list.elements
+                    .iter()
+                    .map(move |element| SignatureEntry::new(&list.list_type, element))
+            })
+            .collect()
+    }
+
+    // Describes what it would take to turn `self` into `other`.
+    pub fn diff(&self, other: &SignatureDatabase) -> SignatureDatabaseDiff {
+        let old_entries = self.signature_entries();
+        let new_entries = other.signature_entries();
+        let mut old_index = index_by_signature(&old_entries);
+        let mut new_index = index_by_signature(&new_entries);
+        let mut result = SignatureDatabaseDiff::default();
+
+        // Old entries missing from `other`, still in database order. Owner
+        // changes are taken out of this list as they are matched below.
+        let mut removed: Vec<Option<&SignatureEntry>> = Vec::new();
+        let mut removed_index = BTreeMap::<SignatureKey, VecDeque<usize>>::new();
+        for entry in old_entries.iter() {
+            if !take_from_index(&mut new_index, entry) {
+                removed_index
+                    .entry(signature_key(entry))
+                    .or_default()
+                    .push_back(removed.len());
+                removed.push(Some(entry));
+            }
+        }
+
+        for entry in new_entries.iter() {
+            if take_from_index(&mut old_index, entry) {
+                continue;
+            }
+
+            // If the same signature only disappeared under a different owner,
+            // call it an owner change.
+            let same_signature = removed_index
+                .get_mut(&signature_key(entry))
+                .and_then(|positions| positions.pop_front());
+            match same_signature {
+                Some(index) => result.owner_changed.push(SignatureOwnerChange {
+                    entry: removed[index].take().unwrap().clone(),
+                    new_owner: entry.owner,
+                }),
+                None => result.added.push(entry.clone()),
+            }
+        }
+        result.removed = removed.into_iter().flatten().cloned().collect();
+
+        result
+    }
+
+    pub fn get_size(&self) -> UefiResult<usize> {
+        self.entries
+            .iter()
+            .try_fold(0usize, |total, list| Ok(total + list.get_size()?))
+    }
+
+    pub fn write_into(&self, buffer: &mut [u8]) -> UefiResult<usize> {
+        let total_size = self.get_size()?;
+        if buffer.len() < total_size {
+            return Err(efi::Status::BUFFER_TOO_SMALL);
+        }
+
+        let mut remainder = &mut buffer[..total_size];
+        for list in self.entries.iter() {
+            let list_size = list.write_into(remainder)?;
+            remainder = &mut remainder[list_size..];
+        }
+
+        Ok(total_size)
+    }
+
+    pub fn to_bytes(&self) -> UefiResult<Vec<u8>> {
+        let mut data = vec![0u8; self.get_size()?];
+        let actual_size = self.write_into(&mut data)?;
+        data.truncate(actual_size);
+        Ok(data)
+    }
+}
+
+impl<'a> From<SignatureDatabaseRef<'a>> for SignatureDatabase {
+    fn from(f: SignatureDatabaseRef<'a>) -> Self {
+        Self {
+            entries: f.lists().map(SignatureList::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<EfiVariable> for SignatureDatabase {
+    type Error = SignatureDatabaseError;
+    fn try_from(tf: EfiVariable) -> Result<Self, Self::Error> {
+        Self::from_bytes(&tf.data)
+    }
+}
+
+impl fmt::Debug for SignatureDatabase {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("SignatureDatabase:\n")?;
+            for list in self.entries.iter() {
+                fmtr.write_fmt(format_args!("{:#?}", list))?;
+            }
+            Ok(())
+        } else {
+            fmtr.debug_list().entries(self.entries.iter()).finish()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,4 +1015,384 @@ mod tests {
         assert_eq!(test_list.list_type, EFI_CERT_SHA256_GUID);
         assert_eq!(test_list.elements.len(), 77);
     }
+
+    #[test]
+    fn efi_sig_list_debug_should_decode_x509_elements() {
+        let test_list = SignatureList::from_bytes(sig_lists::KEK).unwrap();
+        let output = format!("{:#?}", test_list);
+        assert!(output.contains("CN=Microsoft Corporation KEK CA 2011"));
+        assert!(output.contains("Validity: 2011-06-24T20:41:29Z -> 2026-06-24T20:51:29Z"));
+        assert!(output.contains("Key: RSA 2048"));
+
+        // Garbage in an X509 list falls back to the hexdump.
+        let mut test_list = test_list;
+        test_list.elements[0].data.truncate(16);
+        let output = format!("{:#?}", test_list);
+        assert!(!output.contains("X509Certificate"));
+        assert!(output.contains("Data: 0x10 bytes"));
+    }
+
+    #[test]
+    fn efi_sig_list_to_bytes_should_round_trip_test_data() {
+        for test_data in [sig_lists::PK, sig_lists::KEK, sig_lists::DB, sig_lists::DBX].iter() {
+            let test_list = SignatureList::from_bytes(test_data).unwrap();
+            assert_eq!(test_list.get_size().unwrap(), test_data.len());
+            assert_eq!(test_list.to_bytes().unwrap(), *test_data);
+        }
+    }
+
+    #[test]
+    fn efi_sig_list_to_bytes_should_fail_for_mismatched_elements() {
+        let mut test_list = SignatureList::from_bytes(sig_lists::DBX).unwrap();
+        test_list.elements[3].data.push(0x00);
+        assert_eq!(test_list.to_bytes(), Err(efi::Status::INVALID_PARAMETER));
+
+        test_list.elements.clear();
+        assert_eq!(test_list.to_bytes(), Err(efi::Status::INVALID_PARAMETER));
+    }
+
+    #[test]
+    fn efi_sig_db_to_bytes_should_concatenate_lists() {
+        let test_db = SignatureDatabase {
+            entries: vec![
+                SignatureList::from_bytes(sig_lists::DB).unwrap(),
+                SignatureList::from_bytes(sig_lists::DBX).unwrap(),
+            ],
+        };
+
+        let mut expected = Vec::<u8>::from(sig_lists::DB);
+        expected.extend_from_slice(sig_lists::DBX);
+        assert_eq!(test_db.to_bytes().unwrap(), expected);
+
+        let mut short_buffer = vec![0u8; expected.len() - 1];
+        assert_eq!(
+            test_db.write_into(&mut short_buffer),
+            Err(efi::Status::BUFFER_TOO_SMALL)
+        );
+    }
+
+    #[test]
+    fn efi_sig_db_from_bytes_should_parse_concatenated_lists() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DB);
+        test_buffer.extend_from_slice(sig_lists::DBX);
+
+        let test_db = SignatureDatabase::from_bytes(&test_buffer).unwrap();
+        assert_eq!(test_db.entries.len(), 2);
+        assert_eq!(test_db.entries[0].list_type, EFI_CERT_X509_GUID);
+        assert_eq!(test_db.entries[1].list_type, EFI_CERT_SHA256_GUID);
+        assert_eq!(test_db.to_bytes().unwrap(), test_buffer);
+
+        assert_eq!(SignatureDatabase::from_bytes(&[]).unwrap().entries.len(), 0);
+    }
+
+    #[test]
+    fn efi_sig_db_from_bytes_should_reject_bad_layouts() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DB);
+        test_buffer.extend_from_slice(&[0xA5; 8]);
+        assert_eq!(
+            SignatureDatabase::from_bytes(&test_buffer).err(),
+            Some(SignatureDatabaseError::TrailingData(sig_lists::DB.len()))
+        );
+
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DB);
+        test_buffer.extend_from_slice(&sig_lists::DBX[..sig_lists::DBX.len() - 1]);
+        assert_eq!(
+            SignatureDatabase::from_bytes(&test_buffer).err(),
+            Some(SignatureDatabaseError::ListOverrun(
+                sig_lists::DB.len(),
+                sig_lists::DBX.len()
+            ))
+        );
+
+        // Shrink the first list so that it ends part-way through its only certificate.
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DB);
+        test_buffer[16..20].copy_from_slice(&0x100u32.to_le_bytes());
+        assert_eq!(
+            SignatureDatabase::from_bytes(&test_buffer).err(),
+            Some(SignatureDatabaseError::InvalidList(
+                0,
+                efi::Status::BAD_BUFFER_SIZE
+            ))
+        );
+    }
+
+    #[test]
+    fn efi_sig_list_typed_elements_should_match_list_type() {
+        let test_list = SignatureList::from_bytes(sig_lists::DBX).unwrap();
+        let typed = test_list.typed_elements().unwrap();
+        assert_eq!(typed.len(), 77);
+        assert!(typed
+            .iter()
+            .all(|element| matches!(element, TypedSignature::Sha256(_))));
+
+        let test_list = SignatureList::from_bytes(sig_lists::PK).unwrap();
+        match test_list.typed_elements().unwrap()[0] {
+            TypedSignature::X509(cert) => assert_eq!(cert, &test_list.elements[0].data[..]),
+            _ => panic!("PK should contain an X509 certificate"),
+        }
+    }
+
+    #[test]
+    fn typed_signature_should_check_size_against_type() {
+        let data = [0xA5u8; 48];
+        assert_eq!(
+            TypedSignature::new(&EFI_CERT_SHA256_GUID, &data),
+            Err(SignatureTypeError::BadSize(32, 48))
+        );
+        assert_eq!(
+            TypedSignature::new(&EFI_CERT_X509_GUID, &[]),
+            Err(SignatureTypeError::Empty)
+        );
+        assert_eq!(
+            TypedSignature::new(&EFI_CERT_SHA384_GUID, &data),
+            Ok(TypedSignature::Sha384(&data))
+        );
+
+        let unknown_type = crate::variable::EFI_GLOBAL_VARIABLE_GUID;
+        assert_eq!(
+            TypedSignature::new(&unknown_type, &data),
+            Ok(TypedSignature::Unknown(unknown_type, &data[..]))
+        );
+    }
+
+    #[test]
+    fn typed_signature_should_parse_time_of_revocation() {
+        let mut data = vec![0x5Au8; 32];
+        data.extend_from_slice(&[
+            0xE6, 0x07, 0x04, 0x12, 0x0C, 0x22, 0x38, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x07,
+            0x00, 0x00,
+        ]);
+        match TypedSignature::new(&EFI_CERT_X509_SHA256_GUID, &data).unwrap() {
+            TypedSignature::X509Sha256(digest, time) => {
+                assert_eq!(digest, &[0x5Au8; 32]);
+                assert_eq!((time.year, time.month, time.day), (2022, 4, 18));
+                assert_eq!((time.hour, time.minute, time.second), (12, 34, 56));
+                assert_eq!(time.timezone, efi::UNSPECIFIED_TIMEZONE);
+            }
+            _ => panic!("Should have been an X509Sha256 element"),
+        }
+
+        data[46] = 0x02;
+        assert_eq!(
+            TypedSignature::new(&EFI_CERT_X509_SHA256_GUID, &data),
+            Err(SignatureTypeError::BadTime(efi::Status::INVALID_PARAMETER))
+        );
+    }
+
+    #[test]
+    fn efi_sig_list_ref_should_borrow_elements_lazily() {
+        let test_list = SignatureListRef::from_bytes(sig_lists::DBX).unwrap();
+        assert_eq!(test_list.list_type(), EFI_CERT_SHA256_GUID);
+        assert_eq!(test_list.header(), None);
+        assert_eq!(test_list.signature_size(), 48);
+        assert_eq!(test_list.len(), 77);
+        assert_eq!(test_list.get_size(), sig_lists::DBX.len());
+
+        let owned_list = SignatureList::from_bytes(sig_lists::DBX).unwrap();
+        for (element_ref, element) in test_list.elements().zip(owned_list.elements.iter()) {
+            assert_eq!(element_ref.owner, element.owner);
+            assert_eq!(element_ref.data, &element.data[..]);
+            // The data should point straight into the source buffer.
+            assert!(sig_lists::DBX
+                .as_ptr_range()
+                .contains(&element_ref.data.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn efi_sig_list_ref_should_parse_unaligned_buffers() {
+        for shift in 0..4 {
+            let mut test_buffer = vec![0u8; shift];
+            test_buffer.extend_from_slice(sig_lists::KEK);
+            let test_list = SignatureListRef::from_bytes(&test_buffer[shift..]).unwrap();
+            assert_eq!(test_list.list_type(), EFI_CERT_X509_GUID);
+            assert_eq!(test_list.len(), 1);
+        }
+    }
+
+    #[test]
+    fn efi_sig_list_ref_should_reject_bad_signature_sizes() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DBX);
+        test_buffer[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(
+            SignatureListRef::from_bytes(&test_buffer).err(),
+            Some(efi::Status::BAD_BUFFER_SIZE)
+        );
+
+        test_buffer[24..28].copy_from_slice(&47u32.to_le_bytes());
+        assert_eq!(
+            SignatureListRef::from_bytes(&test_buffer).err(),
+            Some(efi::Status::BAD_BUFFER_SIZE)
+        );
+
+        test_buffer[24..28].copy_from_slice(&48u32.to_le_bytes());
+        test_buffer[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            SignatureListRef::from_bytes(&test_buffer).err(),
+            Some(efi::Status::BAD_BUFFER_SIZE)
+        );
+    }
+
+    #[test]
+    fn efi_sig_db_ref_should_iterate_lists() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::PK);
+        test_buffer.extend_from_slice(sig_lists::DBX);
+        test_buffer.extend_from_slice(sig_lists::KEK);
+
+        let test_db = SignatureDatabaseRef::from_bytes(&test_buffer).unwrap();
+        let lists: Vec<SignatureListRef> = test_db.lists().collect();
+        assert_eq!(lists.len(), 3);
+        assert_eq!(lists[1].list_type(), EFI_CERT_SHA256_GUID);
+        assert_eq!(lists[1].len(), 77);
+        assert_eq!(lists[2].list_type(), EFI_CERT_X509_GUID);
+        assert_eq!(
+            SignatureDatabase::from(test_db).to_bytes().unwrap(),
+            test_buffer
+        );
+    }
+
+    #[test]
+    fn efi_sig_db_merge_should_skip_existing_entries() {
+        let mut test_db = SignatureDatabase::from_bytes(sig_lists::DB).unwrap();
+
+        // Split the DBX list so that part of it is already present.
+        let mut partial_dbx = SignatureList::from_bytes(sig_lists::DBX).unwrap();
+        partial_dbx.elements.truncate(10);
+        test_db.entries.push(partial_dbx);
+
+        let update = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        let report = test_db.merge(&update);
+        assert_eq!(report.duplicates.len(), 10);
+        assert_eq!(report.added.len(), 67);
+        assert_eq!(report.removed.len(), 0);
+        assert_eq!(test_db.entries.len(), 3);
+        assert_eq!(test_db.entries[2].elements.len(), 67);
+
+        // Merging again should be a no-op.
+        let report = test_db.merge(&update);
+        assert_eq!(report.added.len(), 0);
+        assert_eq!(report.duplicates.len(), 77);
+        assert_eq!(test_db.entries.len(), 3);
+    }
+
+    #[test]
+    fn efi_sig_db_merge_should_compare_owner_and_type() {
+        let mut test_db = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        let mut update = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        update.entries[0].elements.truncate(2);
+        update.entries[0].elements[0].owner = crate::variable::EFI_GLOBAL_VARIABLE_GUID;
+        update.entries[0].list_type = EFI_CERT_SHA384_GUID;
+        update.entries[0].elements[1]
+            .data
+            .extend_from_slice(&[0u8; 16]);
+
+        let report = test_db.merge(&update);
+        assert_eq!(report.added.len(), 2);
+        assert_eq!(report.added[0].signature_type, EFI_CERT_SHA384_GUID);
+        assert_eq!(test_db.entries.len(), 2);
+    }
+
+    #[test]
+    fn efi_sig_db_dedup_should_drop_repeats_and_empty_lists() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DBX);
+        test_buffer.extend_from_slice(sig_lists::KEK);
+        test_buffer.extend_from_slice(sig_lists::DBX);
+        let mut test_db = SignatureDatabase::from_bytes(&test_buffer).unwrap();
+
+        let report = test_db.dedup();
+        assert_eq!(report.duplicates.len(), 77);
+        assert_eq!(test_db.entries.len(), 2);
+        assert_eq!(test_db.entries[0].elements.len(), 77);
+        assert_eq!(test_db.entries[1].list_type, EFI_CERT_X509_GUID);
+    }
+
+    #[test]
+    fn efi_sig_db_remove_entries_should_report_removals() {
+        let mut test_buffer = Vec::<u8>::from(sig_lists::PK);
+        test_buffer.extend_from_slice(sig_lists::DBX);
+        let mut test_db = SignatureDatabase::from_bytes(&test_buffer).unwrap();
+        let pk_owner = test_db.entries[0].elements[0].owner;
+
+        let report = test_db.remove_entries(|_, element| element.owner == pk_owner);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].signature_type, EFI_CERT_X509_GUID);
+        assert_eq!(test_db.entries.len(), 1);
+
+        let report = test_db.remove_entries(|signature_type, element| {
+            *signature_type == EFI_CERT_SHA256_GUID && element.data[0] == 0x80
+        });
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].data[0], 0x80);
+        assert_eq!(test_db.entries[0].elements.len(), 76);
+    }
+
+    #[test]
+    fn efi_sig_db_diff_should_report_added_and_removed() {
+        let old_db = SignatureDatabase::from_bytes(sig_lists::DB).unwrap();
+        let mut test_buffer = Vec::<u8>::from(sig_lists::DB);
+        test_buffer.extend_from_slice(sig_lists::DBX);
+        let new_db = SignatureDatabase::from_bytes(&test_buffer).unwrap();
+
+        let diff = old_db.diff(&new_db);
+        assert_eq!(diff.added.len(), 77);
+        assert_eq!(diff.removed.len(), 0);
+        assert!(diff
+            .added
+            .iter()
+            .all(|entry| entry.signature_type == EFI_CERT_SHA256_GUID));
+
+        let diff = new_db.diff(&old_db);
+        assert_eq!(diff.added.len(), 0);
+        assert_eq!(diff.removed.len(), 77);
+
+        assert!(new_db.diff(&new_db).is_empty());
+    }
+
+    #[test]
+    fn efi_sig_db_diff_should_count_duplicates() {
+        let old_db = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        let mut new_db = old_db.clone();
+        let duplicate = new_db.entries[0].elements[3].clone();
+        new_db.entries[0].elements.push(duplicate.clone());
+
+        let diff = old_db.diff(&new_db);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].data, duplicate.data);
+        assert!(diff.removed.is_empty());
+
+        // Dropping one of the two copies is a removal too.
+        let diff = new_db.diff(&old_db);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].data, duplicate.data);
+        assert!(diff.added.is_empty());
+    }
+
+    #[test]
+    fn efi_sig_db_diff_should_detect_owner_changes() {
+        let old_db = SignatureDatabase::from_bytes(sig_lists::DBX).unwrap();
+        let mut new_db = old_db.clone();
+        new_db.entries[0].elements[5].owner = crate::variable::EFI_GLOBAL_VARIABLE_GUID;
+        new_db.entries[0].elements.remove(0);
+
+        let diff = old_db.diff(&new_db);
+        assert_eq!(diff.added.len(), 0);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.owner_changed.len(), 1);
+        assert_eq!(
+            diff.owner_changed[0].entry.data,
+            old_db.entries[0].elements[5].data
+        );
+        assert_eq!(
+            diff.owner_changed[0].new_owner,
+            crate::variable::EFI_GLOBAL_VARIABLE_GUID
+        );
+
+        let rendered = format!("{:#?}", diff);
+        assert!(rendered.contains("Owner Changed: 1"));
+        assert!(rendered.contains("EFI_CERT_SHA256_GUID"));
+        assert_eq!(
+            format!("{:?}", diff),
+            "SignatureDatabaseDiff { Added: 0, Removed: 1, Owner Changed: 1 }"
+        );
+    }
 }
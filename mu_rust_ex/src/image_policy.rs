@@ -0,0 +1,756 @@
+/* @file
+Offline image verification: would firmware run this image?
+
+This follows the decision EDK2's DxeImageVerificationLib makes when Secure
+Boot is enforced, but against whatever databases it's given, so a db or dbx
+change can be tried out before it's applied. For each signature in the
+certificate table, in order:
+  - dbx denies it if an X509 entry anchors the signer chain, or an
+    X509_SHAxxx entry matches a chain certificate's TBS hash (unless an RFC
+    3161 timestamp trusted by dbt shows it was signed before the entry's
+    revocation time), or it holds the image digest;
+  - db allows it if an X509 entry anchors the signer chain or it holds the
+    image digest.
+An image with no certificate table is judged by its SHA-256 digest alone.
+A missing database counts as empty, but one that's present and doesn't parse
+denies every image, since what it allows or forbids can't be known. Like the
+firmware, only the outermost signature in each WIN_CERTIFICATE is
+considered, and signature validity periods are ignored.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi::Guid;
+
+use crate::auth_variable::{
+    EFI_CERT_X509_GUID, EFI_CERT_X509_SHA256_GUID, EFI_CERT_X509_SHA384_GUID,
+    EFI_CERT_X509_SHA512_GUID, EFI_IMAGE_SECURITY_DATABASE, EFI_IMAGE_SECURITY_DATABASE1,
+    EFI_IMAGE_SECURITY_DATABASE2,
+};
+use crate::authenticode::{self, AuthenticodeError, HashAlgorithm, ImageSource};
+use crate::image_authentication::{
+    SignatureDatabase, SignatureDatabaseError, SignatureEntry, SignatureListElement,
+};
+use crate::image_signature::{self, DigestCheck, ImageSignature, ImageSignatureError};
+use crate::rustified::Time;
+use crate::secure_boot_status::{DatabaseSnapshot, KeyDatabases};
+use crate::util::{hex_to_string, DebugGuid};
+use crate::x509::{compute_digest, Certificate};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryMatch {
+    // (algorithm) The entry is the image's Authenticode digest.
+    ImageDigest(HashAlgorithm),
+    // (subject) The entry is, or issued, a certificate in the signer chain.
+    Certificate(String),
+    // (subject, revocation time) The entry is the TBS hash of a certificate
+    // in the signer chain. No time means it's always revoked.
+    CertificateHash(String, Option<Time>),
+}
+
+impl fmt::Display for EntryMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImageDigest(algorithm) => write!(f, "image {:?} digest", algorithm),
+            Self::Certificate(subject) => write!(f, "signer chain certificate \"{}\"", subject),
+            Self::CertificateHash(subject, Some(time)) => {
+                write!(f, "TBS hash of \"{}\", revoked from {}", subject, time)
+            }
+            Self::CertificateHash(subject, None) => {
+                write!(f, "TBS hash of \"{}\", always revoked", subject)
+            }
+        }
+    }
+}
+
+// The db or dbx entry a decision rests on.
+#[derive(Clone, PartialEq)]
+pub struct MatchedEntry {
+    pub variable: &'static str,
+    pub entry: SignatureEntry,
+    pub matched: EntryMatch,
+}
+
+impl fmt::Debug for MatchedEntry {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_fmt(format_args!("{} entry:\n", self.variable))?;
+            fmtr.write_fmt(format_args!(
+                "\tType: {:?}\n",
+                DebugGuid::from(self.entry.signature_type)
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tOwner: {:?}\n",
+                DebugGuid::from(self.entry.owner)
+            ))?;
+            match Certificate::from_der(&self.entry.data) {
+                Ok(certificate) if self.entry.signature_type == EFI_CERT_X509_GUID => {
+                    fmtr.write_fmt(format_args!("\tCertificate: {}\n", certificate.subject))?
+                }
+                _ => fmtr.write_fmt(format_args!(
+                    "\tData: {}\n",
+                    hex_to_string(&self.entry.data)
+                ))?,
+            }
+            fmtr.write_fmt(format_args!("\tMatched: {}\n", self.matched))
+        } else {
+            fmtr.write_fmt(format_args!(
+                "MatchedEntry {{ {}: {:?}, {} }}",
+                self.variable,
+                DebugGuid::from(self.entry.signature_type),
+                self.matched
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    // (entry) db allows the image.
+    Allow(MatchedEntry),
+    // (entry) dbx forbids the image.
+    Forbidden(MatchedEntry),
+    // Nothing in db allows the image.
+    NotAllowed,
+    // (error) The certificate table is malformed, which fails verification outright.
+    BadCertificateTable(ImageSignatureError),
+    // (variable, error) db, dbx or dbt is present but doesn't parse.
+    BadDatabase(&'static str, SignatureDatabaseError),
+}
+
+impl Verdict {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allow(_))
+    }
+}
+
+#[derive(Clone)]
+pub struct PolicyReport {
+    // Number of WIN_CERTIFICATE entries; zero for an unsigned image.
+    pub signatures: usize,
+    pub verdict: Verdict,
+    // Signatures that were skipped and dbx entries that were overridden,
+    // which often explain an unexpected verdict.
+    pub notes: Vec<String>,
+}
+
+impl fmt::Debug for PolicyReport {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            let decision = match self.verdict.is_allowed() {
+                true => "ALLOW",
+                false => "DENY",
+            };
+            fmtr.write_fmt(format_args!("Decision: {}\n", decision))?;
+            fmtr.write_fmt(format_args!("Signatures: {}\n", self.signatures))?;
+            match &self.verdict {
+                Verdict::Allow(entry) => fmtr.write_fmt(format_args!("Allowed by {:#?}", entry))?,
+                Verdict::Forbidden(entry) => {
+                    fmtr.write_fmt(format_args!("Forbidden by {:#?}", entry))?
+                }
+                Verdict::NotAllowed => fmtr.write_str("No db entry allows the image\n")?,
+                Verdict::BadCertificateTable(err) => fmtr.write_fmt(format_args!("{}\n", err))?,
+                Verdict::BadDatabase(variable, err) => {
+                    fmtr.write_fmt(format_args!("{} doesn't parse: {}\n", variable, err))?
+                }
+            }
+            for note in self.notes.iter() {
+                fmtr.write_fmt(format_args!("Note: {}\n", note))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "PolicyReport {{ Signatures: {}, Verdict: {:?} }}",
+                self.signatures, self.verdict
+            ))
+        }
+    }
+}
+
+// The parsed database, or None if the variable doesn't exist.
+fn database<'a>(
+    variable: &'static str,
+    snapshot: &'a Option<DatabaseSnapshot>,
+) -> Result<Option<&'a SignatureDatabase>, Verdict> {
+    match snapshot.as_ref().map(|snapshot| &snapshot.database) {
+        Some(Ok(database)) => Ok(Some(database)),
+        Some(Err(err)) => Err(Verdict::BadDatabase(variable, *err)),
+        None => Ok(None),
+    }
+}
+
+fn entries(database: Option<&SignatureDatabase>) -> Vec<(&Guid, &SignatureListElement)> {
+    database
+        .into_iter()
+        .flat_map(|database| database.entries.iter())
+        .flat_map(|list| {
+            list.elements
+                .iter()
+                .map(move |element| (&list.list_type, element))
+        })
+        .collect()
+}
+
+fn subject(certificate: &Certificate) -> String {
+    certificate
+        .subject
+        .common_name()
+        .unwrap_or_else(|| format!("{}", certificate.subject))
+}
+
+fn x509_hash_algorithm(signature_type: &Guid) -> Option<HashAlgorithm> {
+    match *signature_type {
+        EFI_CERT_X509_SHA256_GUID => Some(HashAlgorithm::Sha256),
+        EFI_CERT_X509_SHA384_GUID => Some(HashAlgorithm::Sha384),
+        EFI_CERT_X509_SHA512_GUID => Some(HashAlgorithm::Sha512),
+        _ => None,
+    }
+}
+
+// Whether `trusted` is one of the certificates in `chain`, or issued one,
+// with every link up to that point checking out.
+fn anchored_by(chain: &[&Certificate], trusted: &Certificate) -> bool {
+    for (index, certificate) in chain.iter().enumerate() {
+        if certificate.as_bytes() == trusted.as_bytes()
+            || (certificate.issuer.raw == trusted.subject.raw
+                && certificate.verify_issued_by(trusted).is_ok())
+        {
+            return true;
+        }
+        match chain.get(index + 1) {
+            Some(issuer) if certificate.verify_issued_by(issuer).is_ok() => continue,
+            _ => return false,
+        }
+    }
+    false
+}
+
+struct Evaluator<'d> {
+    db: Vec<(&'d Guid, &'d SignatureListElement)>,
+    dbx: Vec<(&'d Guid, &'d SignatureListElement)>,
+    dbt: Vec<Certificate<'d>>,
+    notes: Vec<String>,
+}
+
+impl<'d> Evaluator<'d> {
+    fn new(databases: &'d KeyDatabases) -> Result<Self, Verdict> {
+        let dbt = entries(database(EFI_IMAGE_SECURITY_DATABASE2, &databases.dbt)?)
+            .into_iter()
+            .filter(|(signature_type, _)| **signature_type == EFI_CERT_X509_GUID)
+            .filter_map(|(_, element)| Certificate::from_der(&element.data).ok())
+            .collect();
+        Ok(Self {
+            db: entries(database(EFI_IMAGE_SECURITY_DATABASE, &databases.db)?),
+            dbx: entries(database(EFI_IMAGE_SECURITY_DATABASE1, &databases.dbx)?),
+            dbt,
+            notes: Vec::new(),
+        })
+    }
+
+    fn find_digest(
+        entries: &[(&Guid, &SignatureListElement)],
+        variable: &'static str,
+        algorithm: HashAlgorithm,
+        digest: &[u8],
+    ) -> Option<MatchedEntry> {
+        entries
+            .iter()
+            .find(|(signature_type, element)| {
+                **signature_type == algorithm.signature_type() && element.data == digest
+            })
+            .map(|(signature_type, element)| MatchedEntry {
+                variable,
+                entry: SignatureEntry::new(signature_type, element),
+                matched: EntryMatch::ImageDigest(algorithm),
+            })
+    }
+
+    // X509 entries in `entries` that anchor `chain`.
+    fn find_anchor(
+        entries: &[(&Guid, &SignatureListElement)],
+        variable: &'static str,
+        chain: &[&Certificate],
+    ) -> Vec<(MatchedEntry, Vec<u8>)> {
+        entries
+            .iter()
+            .filter(|(signature_type, _)| **signature_type == EFI_CERT_X509_GUID)
+            .filter_map(|(signature_type, element)| {
+                let trusted = Certificate::from_der(&element.data).ok()?;
+                match anchored_by(chain, &trusted) {
+                    true => Some((
+                        MatchedEntry {
+                            variable,
+                            entry: SignatureEntry::new(signature_type, element),
+                            matched: EntryMatch::Certificate(subject(&trusted)),
+                        },
+                        element.data.clone(),
+                    )),
+                    false => None,
+                }
+            })
+            .collect()
+    }
+
+    // When dbt vouches for the signing time.
+    fn timestamp(&self, signature: &ImageSignature) -> Option<Time> {
+        signature.verified_timestamp(|chain| {
+            self.dbt
+                .iter()
+                .any(|authority| anchored_by(chain, authority))
+        })
+    }
+
+    // A dbx X509_SHAxxx entry revoking `certificate`, unless the image was
+    // timestamped before the revocation took effect.
+    fn revoked(
+        &mut self,
+        certificate: &Certificate,
+        signature: &ImageSignature,
+    ) -> Option<MatchedEntry> {
+        for (signature_type, element) in self.dbx.iter() {
+            let algorithm = match x509_hash_algorithm(signature_type) {
+                Some(algorithm) => algorithm,
+                None => continue,
+            };
+            let size = algorithm.digest_size();
+            if element.data.len() < size
+                || compute_digest(algorithm.oid(), certificate.tbs_bytes()).as_deref()
+                    != Some(&element.data[..size])
+            {
+                continue;
+            }
+            // An all-zero TimeOfRevocation means "always", as does one that's garbage.
+            let revocation = Time::from_bytes(&element.data[size..])
+                .ok()
+                .filter(|time| time.year != 0 || time.month != 0 || time.day != 0);
+            if let (Some(revocation), Some(signed)) = (revocation, self.timestamp(signature)) {
+                if signed < revocation {
+                    let note = format!(
+                        "\"{}\" is revoked by dbx from {}, but dbt timestamps the signature at {}",
+                        subject(certificate),
+                        revocation,
+                        signed
+                    );
+                    // The db certificate that anchors the chain is checked
+                    // here too, so the same override can come up twice.
+                    if !self.notes.contains(&note) {
+                        self.notes.push(note);
+                    }
+                    continue;
+                }
+            }
+            return Some(MatchedEntry {
+                variable: EFI_IMAGE_SECURITY_DATABASE1,
+                entry: SignatureEntry::new(signature_type, element),
+                matched: EntryMatch::CertificateHash(subject(certificate), revocation),
+            });
+        }
+        None
+    }
+
+    fn forbidden(&mut self, signature: &ImageSignature, authentic: bool) -> Option<MatchedEntry> {
+        let chain = signature.chain();
+        if authentic {
+            let anchors = Self::find_anchor(&self.dbx, EFI_IMAGE_SECURITY_DATABASE1, &chain);
+            if let Some((entry, _)) = anchors.into_iter().next() {
+                return Some(entry);
+            }
+        }
+        chain
+            .iter()
+            .find_map(|certificate| self.revoked(certificate, signature))
+    }
+
+    fn allowed(&mut self, signature: &ImageSignature) -> Option<MatchedEntry> {
+        let chain = signature.chain();
+        for (entry, data) in Self::find_anchor(&self.db, EFI_IMAGE_SECURITY_DATABASE, &chain) {
+            // The db certificate itself may be revoked by hash.
+            let trusted = Certificate::from_der(&data).ok()?;
+            if let Some(revoked) = self.revoked(&trusted, signature) {
+                self.notes.push(format!(
+                    "db certificate \"{}\" is revoked: {:?}",
+                    subject(&trusted),
+                    revoked
+                ));
+                continue;
+            }
+            return Some(entry);
+        }
+        None
+    }
+
+    fn evaluate(mut self, digests: &[(HashAlgorithm, Vec<u8>)], table: &[u8]) -> PolicyReport {
+        let digest = |algorithm: HashAlgorithm| {
+            digests
+                .iter()
+                .find(|(a, _)| *a == algorithm)
+                .map(|(_, digest)| digest.as_slice())
+        };
+
+        let certificates = match image_signature::parse_certificate_table(table) {
+            Ok(certificates) => certificates,
+            Err(err) => {
+                return PolicyReport {
+                    signatures: 0,
+                    verdict: Verdict::BadCertificateTable(err),
+                    notes: self.notes,
+                }
+            }
+        };
+
+        if certificates.is_empty() {
+            let verdict = match digest(HashAlgorithm::Sha256) {
+                None => Verdict::NotAllowed,
+                Some(sha256) => {
+                    if let Some(entry) = Self::find_digest(
+                        &self.dbx,
+                        EFI_IMAGE_SECURITY_DATABASE1,
+                        HashAlgorithm::Sha256,
+                        sha256,
+                    ) {
+                        Verdict::Forbidden(entry)
+                    } else if let Some(entry) = Self::find_digest(
+                        &self.db,
+                        EFI_IMAGE_SECURITY_DATABASE,
+                        HashAlgorithm::Sha256,
+                        sha256,
+                    ) {
+                        Verdict::Allow(entry)
+                    } else {
+                        Verdict::NotAllowed
+                    }
+                }
+            };
+            return PolicyReport {
+                signatures: 0,
+                verdict,
+                notes: self.notes,
+            };
+        }
+
+        let mut allowed = None;
+        for (index, certificate) in certificates.iter().enumerate() {
+            let signature = match certificate.signed_data().and_then(ImageSignature::from_der) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    self.notes
+                        .push(format!("Signature {} skipped: {}", index + 1, err));
+                    continue;
+                }
+            };
+            let (algorithm, image_digest) = match signature
+                .digest_algorithm()
+                .and_then(|algorithm| Some((algorithm, digest(algorithm)?)))
+            {
+                Some(found) => found,
+                None => {
+                    self.notes.push(format!(
+                        "Signature {} skipped: unsupported digest algorithm {:?}",
+                        index + 1,
+                        signature.indirect.digest_algorithm
+                    ));
+                    continue;
+                }
+            };
+
+            let authentic = match (signature.check_digest(digests), signature.verify()) {
+                (DigestCheck::Match, Ok(_)) => true,
+                (DigestCheck::Match, Err(err)) => {
+                    self.notes
+                        .push(format!("Signature {} doesn't verify: {:?}", index + 1, err));
+                    false
+                }
+                (_, _) => {
+                    self.notes.push(format!(
+                        "Signature {} doesn't match the image digest",
+                        index + 1
+                    ));
+                    false
+                }
+            };
+
+            if let Some(entry) = self.forbidden(&signature, authentic) {
+                return PolicyReport {
+                    signatures: certificates.len(),
+                    verdict: Verdict::Forbidden(entry),
+                    notes: self.notes,
+                };
+            }
+            if allowed.is_none() && authentic {
+                allowed = self.allowed(&signature);
+            }
+            if let Some(entry) = Self::find_digest(
+                &self.dbx,
+                EFI_IMAGE_SECURITY_DATABASE1,
+                algorithm,
+                image_digest,
+            ) {
+                return PolicyReport {
+                    signatures: certificates.len(),
+                    verdict: Verdict::Forbidden(entry),
+                    notes: self.notes,
+                };
+            }
+            if allowed.is_none() {
+                allowed = Self::find_digest(
+                    &self.db,
+                    EFI_IMAGE_SECURITY_DATABASE,
+                    algorithm,
+                    image_digest,
+                );
+            }
+        }
+
+        PolicyReport {
+            signatures: certificates.len(),
+            verdict: allowed.map_or(Verdict::NotAllowed, Verdict::Allow),
+            notes: self.notes,
+        }
+    }
+}
+
+// Decides whether `databases` allow an image, given its Authenticode
+// digests as (algorithm, digest) pairs and its certificate table.
+pub fn evaluate(
+    databases: &KeyDatabases,
+    digests: &[(HashAlgorithm, Vec<u8>)],
+    table: &[u8],
+) -> PolicyReport {
+    match Evaluator::new(databases) {
+        Ok(evaluator) => evaluator.evaluate(digests, table),
+        Err(verdict) => PolicyReport {
+            signatures: image_signature::parse_certificate_table(table)
+                .map_or(0, |certificates| certificates.len()),
+            verdict,
+            notes: Vec::new(),
+        },
+    }
+}
+
+pub fn evaluate_image<S: ImageSource + ?Sized>(
+    databases: &KeyDatabases,
+    source: &mut S,
+) -> Result<PolicyReport, AuthenticodeError> {
+    let (image, headers) = authenticode::read_headers(source)?;
+    let digests = authenticode::compute_digests(&image, &headers, source, &HashAlgorithm::ALL)?;
+    let digests: Vec<(HashAlgorithm, Vec<u8>)> =
+        HashAlgorithm::ALL.iter().copied().zip(digests).collect();
+    let table = image_signature::read_certificate_table(&image, source)?;
+    Ok(evaluate(databases, &digests, &table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_variable::EFI_CERT_SHA256_GUID;
+    use crate::image_authentication::SignatureList;
+    use crate::image_signature::tests::{
+        rfc3161_timestamp, sign_with, signed_image, win_certificate,
+    };
+    use crate::name_db::MICROSOFT_SIGNATURE_OWNER_GUID;
+    use crate::pe::tests::build_image;
+    use crate::test_data::auth_files;
+    use alloc::vec;
+
+    fn snapshot(name: &str, lists: Vec<(Guid, Vec<u8>)>) -> Option<DatabaseSnapshot> {
+        let database = SignatureDatabase {
+            entries: lists
+                .into_iter()
+                .map(|(list_type, data)| SignatureList {
+                    list_type,
+                    header: None,
+                    elements: vec![SignatureListElement {
+                        owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+                        data,
+                    }],
+                })
+                .collect(),
+        };
+        Some(DatabaseSnapshot::from_bytes(
+            name,
+            &database.to_bytes().unwrap(),
+        ))
+    }
+
+    fn test_kek_hash(revocation: Option<&str>) -> Vec<u8> {
+        let certificate = Certificate::from_der(auth_files::TEST_KEK_CERT).unwrap();
+        let mut data =
+            compute_digest(HashAlgorithm::Sha256.oid(), certificate.tbs_bytes()).unwrap();
+        match revocation {
+            Some(time) => data.extend_from_slice(&time.parse::<Time>().unwrap().to_bytes()),
+            None => data.extend_from_slice(&[0u8; Time::SERIALIZED_SIZE]),
+        }
+        data
+    }
+
+    // build_image() signed by the test KEK and, if `timestamped`, given an
+    // RFC 3161 timestamp from 2024-05-01, also signed by the test KEK.
+    // Returns the image and its SHA-256 digest.
+    fn image(timestamped: bool) -> (Vec<u8>, Vec<u8>) {
+        let digest =
+            authenticode::image_digest(&mut build_image(0).as_slice(), HashAlgorithm::Sha256)
+                .unwrap();
+        let signature = sign_with(&digest, |signature| match timestamped {
+            true => rfc3161_timestamp(signature, "20240501120000Z"),
+            false => Vec::new(),
+        });
+        (signed_image(&win_certificate(&signature)), digest)
+    }
+
+    #[test]
+    fn evaluate_should_follow_db_and_dbx() {
+        let (buffer, digest) = image(false);
+        let test_kek = (EFI_CERT_X509_GUID, Vec::from(auth_files::TEST_KEK_CERT));
+        let mut databases = KeyDatabases {
+            db: snapshot("db", vec![test_kek.clone()]),
+            ..Default::default()
+        };
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert_eq!(report.signatures, 1);
+        match report.verdict {
+            Verdict::Allow(entry) => {
+                assert_eq!(entry.variable, "db");
+                assert_eq!(entry.entry.data, auth_files::TEST_KEK_CERT);
+                assert_eq!(
+                    entry.matched,
+                    EntryMatch::Certificate(String::from("Test KEK 2024"))
+                );
+            }
+            other => panic!("unexpected verdict {:?}", other),
+        }
+
+        // The image digest in dbx wins over the certificate in db.
+        databases.dbx = snapshot("dbx", vec![(EFI_CERT_SHA256_GUID, digest.clone())]);
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert_eq!(
+            report.verdict,
+            Verdict::Forbidden(MatchedEntry {
+                variable: "dbx",
+                entry: SignatureEntry {
+                    signature_type: EFI_CERT_SHA256_GUID,
+                    owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+                    data: digest.clone(),
+                },
+                matched: EntryMatch::ImageDigest(HashAlgorithm::Sha256),
+            })
+        );
+
+        // Without the signature only the digest counts.
+        let unsigned = build_image(0);
+        databases.dbx = None;
+        let report = evaluate_image(&databases, &mut unsigned.as_slice()).unwrap();
+        assert_eq!(report.signatures, 0);
+        assert_eq!(report.verdict, Verdict::NotAllowed);
+        databases.db = snapshot("db", vec![test_kek, (EFI_CERT_SHA256_GUID, digest)]);
+        let report = evaluate_image(&databases, &mut unsigned.as_slice()).unwrap();
+        assert!(report.verdict.is_allowed());
+
+        // A modified image isn't allowed by its signature.
+        let mut patched = buffer;
+        patched[0x210] ^= 0xFF;
+        databases.db = snapshot(
+            "db",
+            vec![(EFI_CERT_X509_GUID, Vec::from(auth_files::TEST_KEK_CERT))],
+        );
+        let report = evaluate_image(&databases, &mut patched.as_slice()).unwrap();
+        assert_eq!(report.verdict, Verdict::NotAllowed);
+        assert_eq!(report.notes.len(), 1);
+    }
+
+    #[test]
+    fn evaluate_should_deny_when_a_database_does_not_parse() {
+        let (buffer, _) = image(false);
+        let garbage = |name| Some(DatabaseSnapshot::from_bytes(name, &[0xA5; 0x30]));
+        let mut databases = KeyDatabases {
+            db: snapshot(
+                "db",
+                vec![(EFI_CERT_X509_GUID, Vec::from(auth_files::TEST_KEK_CERT))],
+            ),
+            dbx: garbage("dbx"),
+            ..Default::default()
+        };
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert_eq!(report.signatures, 1);
+        assert!(matches!(report.verdict, Verdict::BadDatabase("dbx", _)));
+        assert!(format!("{:#?}", report).starts_with("Decision: DENY"));
+
+        databases.dbx = None;
+        databases.dbt = garbage("dbt");
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert!(matches!(report.verdict, Verdict::BadDatabase("dbt", _)));
+
+        // Missing is fine; it's the same as empty.
+        databases.dbt = None;
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert!(report.verdict.is_allowed());
+    }
+
+    #[test]
+    fn evaluate_should_apply_dbt_to_revoked_certificates() {
+        let (buffer, _) = image(true);
+        let test_kek = (EFI_CERT_X509_GUID, Vec::from(auth_files::TEST_KEK_CERT));
+        let mut databases = KeyDatabases {
+            db: snapshot("db", vec![test_kek.clone()]),
+            dbx: snapshot(
+                "dbx",
+                vec![(
+                    EFI_CERT_X509_SHA256_GUID,
+                    test_kek_hash(Some("2025-01-01T00:00:00Z")),
+                )],
+            ),
+            ..Default::default()
+        };
+
+        // Without dbt the timestamp isn't trusted.
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        match report.verdict {
+            Verdict::Forbidden(entry) => assert_eq!(
+                entry.matched,
+                EntryMatch::CertificateHash(
+                    String::from("Test KEK 2024"),
+                    Some("2025-01-01T00:00:00Z".parse().unwrap())
+                )
+            ),
+            other => panic!("unexpected verdict {:?}", other),
+        }
+
+        // Signed in 2024, revoked from 2025.
+        databases.dbt = snapshot("dbt", vec![test_kek]);
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert!(report.verdict.is_allowed());
+        assert_eq!(report.notes.len(), 1);
+        assert!(report.notes[0].contains("dbt timestamps the signature"));
+
+        // Revoked before it was signed.
+        databases.dbx = snapshot(
+            "dbx",
+            vec![(
+                EFI_CERT_X509_SHA256_GUID,
+                test_kek_hash(Some("2024-01-01T00:00:00Z")),
+            )],
+        );
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        assert!(!report.verdict.is_allowed());
+
+        // No revocation time: always revoked.
+        databases.dbx = snapshot(
+            "dbx",
+            vec![(EFI_CERT_X509_SHA256_GUID, test_kek_hash(None))],
+        );
+        let report = evaluate_image(&databases, &mut buffer.as_slice()).unwrap();
+        match report.verdict {
+            Verdict::Forbidden(entry) => assert_eq!(
+                entry.matched,
+                EntryMatch::CertificateHash(String::from("Test KEK 2024"), None)
+            ),
+            other => panic!("unexpected verdict {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,833 @@
+/* @file
+Embedded Authenticode signatures: the WIN_CERTIFICATE entries in a PE
+image's certificate table, and the PKCS#7 SignedData inside them.
+
+An Authenticode SignedData carries an SpcIndirectDataContent, which holds
+the image's Authenticode digest; the signature covers that, not the file.
+Further signatures can be nested in the first signer's unauthenticated
+attributes, alongside PKCS#9 countersignatures or RFC 3161 timestamps that
+say when the signing happened.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+
+use crate::auth_variable::{EFI_CERT_TYPE_PKCS7_GUID, WIN_CERT_TYPE_EFI_GUID};
+use crate::authenticode::{AuthenticodeError, HashAlgorithm, ImageSource};
+use crate::der::{self, DerError, DerObject, DerReader, Oid};
+use crate::pe::PeImage;
+use crate::pkcs7::{SignedData, SignerInfo, OID_COUNTER_SIGNATURE, OID_SIGNING_TIME, OID_TST_INFO};
+use crate::rustified::Time;
+use crate::util::{read_guid, read_u16_le, read_u32_le};
+use crate::x509::{compute_digest, Certificate, DebugHex, VerifyError};
+
+pub const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+const WIN_CERTIFICATE_HEADER_SIZE: usize = 8;
+
+// 1.3.6.1.4.1.311.2.1.4
+pub const OID_SPC_INDIRECT_DATA: &[u8] =
+    &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x04];
+// 1.3.6.1.4.1.311.2.1.15
+pub const OID_SPC_PE_IMAGE_DATA: &[u8] =
+    &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x0F];
+// 1.3.6.1.4.1.311.2.4.1
+pub const OID_SPC_NESTED_SIGNATURE: &[u8] =
+    &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x04, 0x01];
+// 1.3.6.1.4.1.311.3.3.1
+pub const OID_SPC_RFC3161_TIMESTAMP: &[u8] =
+    &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x03, 0x03, 0x01];
+
+// Nested signatures can hold nested signatures; this is plenty.
+const MAX_NESTING: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageSignatureError {
+    // (offset) A WIN_CERTIFICATE doesn't fit in the certificate table.
+    BadCertificateTable(usize),
+    // (type) Not a PKCS#7 signature.
+    UnsupportedCertificateType(u16),
+    // (error) The SignedData doesn't decode.
+    BadSignedData(DerError),
+    // The SignedData's content isn't an SpcIndirectDataContent.
+    NotAuthenticode,
+    NestedTooDeep,
+}
+impl fmt::Display for ImageSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadCertificateTable(offset) => {
+                write!(f, "Bad WIN_CERTIFICATE at table offset 0x{:X}", offset)
+            }
+            Self::UnsupportedCertificateType(kind) => {
+                write!(f, "Unsupported certificate type 0x{:04X}", kind)
+            }
+            Self::BadSignedData(err) => write!(f, "Invalid SignedData ({})", err),
+            Self::NotAuthenticode => write!(f, "SignedData isn't an Authenticode signature"),
+            Self::NestedTooDeep => write!(f, "Signatures nested too deeply"),
+        }
+    }
+}
+impl From<ImageSignatureError> for efi::Status {
+    fn from(f: ImageSignatureError) -> Self {
+        match f {
+            ImageSignatureError::BadCertificateTable(_) => efi::Status::LOAD_ERROR,
+            ImageSignatureError::UnsupportedCertificateType(_) => efi::Status::UNSUPPORTED,
+            ImageSignatureError::BadSignedData(x) => x.into(),
+            ImageSignatureError::NotAuthenticode => efi::Status::UNSUPPORTED,
+            ImageSignatureError::NestedTooDeep => efi::Status::UNSUPPORTED,
+        }
+    }
+}
+impl From<DerError> for ImageSignatureError {
+    fn from(f: DerError) -> Self {
+        Self::BadSignedData(f)
+    }
+}
+
+// One entry from the certificate table.
+#[derive(Clone, Copy, PartialEq)]
+pub struct WinCertificate<'a> {
+    pub offset: usize,
+    pub revision: u16,
+    pub certificate_type: u16,
+    // bCertificate, minus the CertType GUID for WIN_CERT_TYPE_EFI_GUID.
+    pub data: &'a [u8],
+}
+
+impl<'a> WinCertificate<'a> {
+    // The PKCS#7 SignedData, for the types that hold one.
+    pub fn signed_data(&self) -> Result<&'a [u8], ImageSignatureError> {
+        match self.certificate_type {
+            WIN_CERT_TYPE_PKCS_SIGNED_DATA => Ok(self.data),
+            WIN_CERT_TYPE_EFI_GUID if read_guid(self.data, 0) == Some(EFI_CERT_TYPE_PKCS7_GUID) => {
+                Ok(&self.data[16..])
+            }
+            other => Err(ImageSignatureError::UnsupportedCertificateType(other)),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for WinCertificate<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "WinCertificate {{ Offset: 0x{:X}, Revision: 0x{:04X}, Type: 0x{:04X}, Size: 0x{:X} }}",
+            self.offset,
+            self.revision,
+            self.certificate_type,
+            self.data.len()
+        ))
+    }
+}
+
+// Reads the certificate table, if the image has one.
+pub fn read_certificate_table<S: ImageSource + ?Sized>(
+    image: &PeImage,
+    source: &mut S,
+) -> Result<Vec<u8>, AuthenticodeError> {
+    let directory = match image.security_directory() {
+        Some(directory) => directory,
+        None => return Ok(Vec::new()),
+    };
+    let mut table = vec![0u8; directory.size as usize];
+    source.read_at(directory.virtual_address as usize, &mut table)?;
+    Ok(table)
+}
+
+// Splits a certificate table into its entries. Each is 8-byte aligned, and
+// anything too short to be a header at the end is padding.
+pub fn parse_certificate_table(
+    table: &[u8],
+) -> Result<Vec<WinCertificate<'_>>, ImageSignatureError> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while table.len() - offset > WIN_CERTIFICATE_HEADER_SIZE {
+        let bad = ImageSignatureError::BadCertificateTable(offset);
+        let length = read_u32_le(table, offset).ok_or(bad)? as usize;
+        if length <= WIN_CERTIFICATE_HEADER_SIZE || length > table.len() - offset {
+            return Err(bad);
+        }
+        let certificate_type = read_u16_le(table, offset + 6).ok_or(bad)?;
+        // WIN_CERTIFICATE_UEFI_GUID entries need room for the CertType GUID.
+        if certificate_type == WIN_CERT_TYPE_EFI_GUID && length < WIN_CERTIFICATE_HEADER_SIZE + 16 {
+            return Err(bad);
+        }
+        entries.push(WinCertificate {
+            offset,
+            revision: read_u16_le(table, offset + 4).ok_or(bad)?,
+            certificate_type,
+            data: &table[offset + WIN_CERTIFICATE_HEADER_SIZE..offset + length],
+        });
+        offset += (length + 7) & !7;
+        if offset >= table.len() {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+// SpcIndirectDataContent: what an Authenticode signature actually signs.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpcIndirectData<'a> {
+    // SPC_PE_IMAGE_DATA for PE images.
+    pub data_type: Oid<'a>,
+    pub digest_algorithm: Oid<'a>,
+    // The signed Authenticode digest of the image.
+    pub digest: &'a [u8],
+    // The SEQUENCE's contents, which is what the messageDigest covers.
+    pub contents: &'a [u8],
+}
+
+impl<'a> SpcIndirectData<'a> {
+    pub fn from_der_object(object: DerObject<'a>) -> Result<Self, DerError> {
+        let object = object.expect(der::TAG_SEQUENCE)?;
+        let mut fields = object.reader();
+        let data_type = fields
+            .read(der::TAG_SEQUENCE)?
+            .reader()
+            .read_any()?
+            .as_oid()?;
+        let mut digest_info = fields.read_last(der::TAG_SEQUENCE)?.reader();
+        let digest_algorithm = digest_info
+            .read(der::TAG_SEQUENCE)?
+            .reader()
+            .read_any()?
+            .as_oid()?;
+        let digest = digest_info.read_last(der::TAG_OCTET_STRING)?.contents;
+        Ok(Self {
+            data_type,
+            digest_algorithm,
+            digest,
+            contents: object.contents,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampKind {
+    // A PKCS#9 countersignature with a signingTime attribute.
+    Countersignature,
+    // An RFC 3161 timestamp token.
+    Rfc3161,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Timestamp {
+    pub kind: TimestampKind,
+    pub time: Time,
+    // Common name of the timestamping certificate, if it's included.
+    pub authority: Option<String>,
+}
+
+impl fmt::Debug for Timestamp {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!("{} ({:?}", self.time, self.kind))?;
+        if let Some(authority) = &self.authority {
+            fmtr.write_fmt(format_args!(", {}", authority))?;
+        }
+        fmtr.write_str(")")
+    }
+}
+
+// GeneralizedTime in timestamp tokens may carry fractional seconds, which
+// DerObject::as_time() doesn't accept.
+fn generalized_time(object: DerObject) -> Result<Time, DerError> {
+    let object = object.expect(der::TAG_GENERALIZED_TIME)?;
+    match object.contents.iter().position(|b| *b == b'.') {
+        None => object.as_time(),
+        Some(dot) => {
+            let mut trimmed = Vec::from(&object.contents[..dot]);
+            trimmed.push(b'Z');
+            DerObject {
+                tag: der::TAG_GENERALIZED_TIME,
+                contents: &trimmed,
+                raw: &trimmed,
+            }
+            .as_time()
+        }
+    }
+}
+
+// The parts of an RFC 3161 TSTInfo we use.
+struct TstInfo<'a> {
+    // The DER TSTInfo, which is what the token's signature covers.
+    raw: &'a [u8],
+    imprint_algorithm: Oid<'a>,
+    imprint: &'a [u8],
+    time: Time,
+}
+
+impl<'a> TstInfo<'a> {
+    fn from_token(token: &SignedData<'a>) -> Result<Self, ImageSignatureError> {
+        if token.content_type.0 != OID_TST_INFO {
+            return Err(ImageSignatureError::BadSignedData(DerError::BadValue));
+        }
+        // eContent is an OCTET STRING holding the DER TSTInfo.
+        let content = token
+            .content
+            .ok_or(ImageSignatureError::BadSignedData(DerError::BadValue))?
+            .expect(der::TAG_OCTET_STRING)?;
+        let mut fields = DerReader::new(content.contents)
+            .read_last(der::TAG_SEQUENCE)?
+            .reader();
+        fields.read(der::TAG_INTEGER)?;
+        fields.read(der::TAG_OID)?;
+        let mut message_imprint = fields.read(der::TAG_SEQUENCE)?.reader();
+        let imprint_algorithm = message_imprint
+            .read(der::TAG_SEQUENCE)?
+            .reader()
+            .read_any()?
+            .as_oid()?;
+        let imprint = message_imprint.read_last(der::TAG_OCTET_STRING)?.contents;
+        fields.read(der::TAG_INTEGER)?;
+        let time = generalized_time(fields.read_any()?)?;
+        Ok(Self {
+            raw: content.contents,
+            imprint_algorithm,
+            imprint,
+            time,
+        })
+    }
+}
+
+// `certificate` followed by each issuer embedded in `signed_data`, as far as
+// the chain goes.
+pub fn certificate_chain<'a, 'b>(
+    signed_data: &'b SignedData<'a>,
+    certificate: &'b Certificate<'a>,
+) -> Vec<&'b Certificate<'a>> {
+    let mut chain = vec![certificate];
+    while let Some(current) = chain.last() {
+        if current.is_self_issued() {
+            break;
+        }
+        let issuer = signed_data.certificates.iter().find(|candidate| {
+            candidate.subject.raw == current.issuer.raw
+                && !chain
+                    .iter()
+                    .any(|seen| seen.as_bytes() == candidate.as_bytes())
+        });
+        match issuer {
+            Some(issuer) => chain.push(issuer),
+            None => break,
+        }
+    }
+    chain
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestCheck {
+    Match,
+    // The image has been modified since it was signed.
+    Mismatch,
+    // No computed digest for the signature's algorithm.
+    Unsupported,
+}
+
+#[derive(Clone)]
+pub struct ImageSignature<'a> {
+    pub signed_data: SignedData<'a>,
+    pub indirect: SpcIndirectData<'a>,
+    pub timestamps: Vec<Timestamp>,
+    pub nested: Vec<ImageSignature<'a>>,
+}
+
+impl<'a> ImageSignature<'a> {
+    pub fn from_der(buffer: &'a [u8]) -> Result<Self, ImageSignatureError> {
+        Self::from_der_nested(buffer, 0)
+    }
+
+    fn from_der_nested(buffer: &'a [u8], depth: usize) -> Result<Self, ImageSignatureError> {
+        if depth > MAX_NESTING {
+            return Err(ImageSignatureError::NestedTooDeep);
+        }
+        let signed_data = SignedData::from_der(buffer)?;
+        if signed_data.content_type.0 != OID_SPC_INDIRECT_DATA || signed_data.signers.is_empty() {
+            return Err(ImageSignatureError::NotAuthenticode);
+        }
+        let content = signed_data
+            .content
+            .ok_or(ImageSignatureError::NotAuthenticode)?;
+        let indirect = SpcIndirectData::from_der_object(content)?;
+
+        let signer = &signed_data.signers[0];
+        let mut timestamps = Vec::new();
+        for value in signer.unauthenticated_attribute_values(OID_COUNTER_SIGNATURE)? {
+            let countersigner = SignerInfo::from_der(value)?;
+            if let Some(time) = countersigner.authenticated_attribute(OID_SIGNING_TIME)? {
+                timestamps.push(Timestamp {
+                    kind: TimestampKind::Countersignature,
+                    time: time.as_time()?,
+                    authority: signed_data
+                        .signer_certificate(&countersigner)
+                        .and_then(|certificate| certificate.subject.common_name()),
+                });
+            }
+        }
+        for value in signer.unauthenticated_attribute_values(OID_SPC_RFC3161_TIMESTAMP)? {
+            timestamps.push(Self::rfc3161_timestamp(value)?);
+        }
+
+        let mut nested = Vec::new();
+        for value in signer.unauthenticated_attribute_values(OID_SPC_NESTED_SIGNATURE)? {
+            nested.push(Self::from_der_nested(value.raw, depth + 1)?);
+        }
+
+        Ok(Self {
+            signed_data,
+            indirect,
+            timestamps,
+            nested,
+        })
+    }
+
+    fn rfc3161_timestamp(value: DerObject) -> Result<Timestamp, ImageSignatureError> {
+        let token = SignedData::from_der(value.raw)?;
+        let tst_info = TstInfo::from_token(&token)?;
+        let authority = token.signers.first().and_then(|signer| {
+            token
+                .signer_certificate(signer)
+                .and_then(|certificate| certificate.subject.common_name())
+        });
+        Ok(Timestamp {
+            kind: TimestampKind::Rfc3161,
+            time: tst_info.time,
+            authority,
+        })
+    }
+
+    // The time from an RFC 3161 timestamp that covers this signature, is
+    // correctly signed, and whose signing chain `trusted` accepts. This is
+    // the check firmware makes against dbt; countersignatures don't count.
+    pub fn verified_timestamp<F>(&self, trusted: F) -> Option<Time>
+    where
+        F: Fn(&[&Certificate]) -> bool,
+    {
+        let values = self
+            .signer()
+            .unauthenticated_attribute_values(OID_SPC_RFC3161_TIMESTAMP)
+            .ok()?;
+        values.into_iter().find_map(|value| {
+            let token = SignedData::from_der(value.raw).ok()?;
+            let tst_info = TstInfo::from_token(&token).ok()?;
+            // The token timestamps the signature, not the image.
+            let imprint = compute_digest(tst_info.imprint_algorithm.0, self.signer().signature)?;
+            if imprint != tst_info.imprint {
+                return None;
+            }
+            let signer = token.signers.first()?;
+            let certificate = token.verify_signer(signer, tst_info.raw).ok()?;
+            match trusted(&certificate_chain(&token, certificate)) {
+                true => Some(tst_info.time),
+                false => None,
+            }
+        })
+    }
+
+    pub fn signer(&self) -> &SignerInfo<'a> {
+        &self.signed_data.signers[0]
+    }
+
+    pub fn signer_certificate(&self) -> Option<&Certificate<'a>> {
+        self.signed_data.signer_certificate(self.signer())
+    }
+
+    // The signer's certificate followed by each issuer that's embedded.
+    pub fn chain(&self) -> Vec<&Certificate<'a>> {
+        match self.signer_certificate() {
+            Some(certificate) => certificate_chain(&self.signed_data, certificate),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn digest_algorithm(&self) -> Option<HashAlgorithm> {
+        HashAlgorithm::from_oid(self.indirect.digest_algorithm.0)
+    }
+
+    // Checks the signature over the SpcIndirectDataContent. That says
+    // nothing about whether the image still matches; see check_digest().
+    pub fn verify(&self) -> Result<&Certificate<'a>, VerifyError> {
+        self.signed_data
+            .verify_signer(self.signer(), self.indirect.contents)
+    }
+
+    // Compares the signed digest with the image's computed Authenticode
+    // digests, given as (algorithm, digest) pairs.
+    pub fn check_digest(&self, computed: &[(HashAlgorithm, Vec<u8>)]) -> DigestCheck {
+        let algorithm = match self.digest_algorithm() {
+            Some(algorithm) => algorithm,
+            None => return DigestCheck::Unsupported,
+        };
+        match computed.iter().find(|(a, _)| *a == algorithm) {
+            Some((_, digest)) if digest[..] == *self.indirect.digest => DigestCheck::Match,
+            Some(_) => DigestCheck::Mismatch,
+            None => DigestCheck::Unsupported,
+        }
+    }
+
+    // This signature and everything nested in it, outermost first.
+    pub fn all(&self) -> Vec<&ImageSignature<'a>> {
+        let mut result = vec![self];
+        for nested in self.nested.iter() {
+            result.extend(nested.all());
+        }
+        result
+    }
+}
+
+impl<'a> fmt::Debug for ImageSignature<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let name = |certificate: &Certificate| {
+            certificate
+                .subject
+                .common_name()
+                .unwrap_or_else(|| String::from("(no CN)"))
+        };
+        if fmtr.alternate() {
+            fmtr.write_str("ImageSignature:\n")?;
+            match self.signer_certificate() {
+                Some(certificate) => {
+                    fmtr.write_fmt(format_args!("\tSigner: {}\n", name(certificate)))?;
+                    fmtr.write_fmt(format_args!("\tIssuer: {}\n", certificate.issuer))?;
+                }
+                None => fmtr.write_str("\tSigner: certificate not included\n")?,
+            }
+            fmtr.write_str("\tChain:\n")?;
+            for certificate in self.chain() {
+                fmtr.write_fmt(format_args!(
+                    "\t\t{} (SHA-1 {:?})\n",
+                    name(certificate),
+                    DebugHex(&certificate.sha1_thumbprint())
+                ))?;
+            }
+            fmtr.write_fmt(format_args!(
+                "\tDigest Algorithm: {:?}\n",
+                self.indirect.digest_algorithm
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tSigned Image Digest: {:?}\n",
+                DebugHex(self.indirect.digest)
+            ))?;
+            for timestamp in self.timestamps.iter() {
+                fmtr.write_fmt(format_args!("\tTimestamp: {:?}\n", timestamp))?;
+            }
+            fmtr.write_fmt(format_args!("\tNested Signatures: {}\n", self.nested.len()))
+        } else {
+            fmtr.write_fmt(format_args!(
+                "ImageSignature {{ Signer: {}, Digest Algorithm: {:?} }}",
+                self.signer_certificate()
+                    .map(name)
+                    .unwrap_or_else(|| String::from("?")),
+                self.indirect.digest_algorithm
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::authenticode::{compute_digests, image_digest, read_headers};
+    use crate::der::{encode, encode_algorithm, encode_constructed, tag_context};
+    use crate::pe::tests::build_image;
+    use crate::pkcs7::{OID_CONTENT_TYPE, OID_MESSAGE_DIGEST, OID_PKCS7_SIGNED_DATA};
+    use crate::test_data::auth_files;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+    use sha2::{Digest, Sha256};
+
+    pub(crate) fn attribute(oid: &[u8], value: &[u8]) -> Vec<u8> {
+        encode_constructed(
+            der::TAG_SEQUENCE,
+            &[&encode(der::TAG_OID, oid), &encode(der::TAG_SET, value)],
+        )
+    }
+
+    // An Authenticode ContentInfo over `digest`, signed with the test KEK
+    // and carrying `unauthenticated` attributes.
+    pub(crate) fn sign(digest: &[u8], unauthenticated: &[u8]) -> Vec<u8> {
+        sign_with(digest, |_| Vec::from(unauthenticated))
+    }
+
+    // As sign(), with the unauthenticated attributes made from the signature.
+    pub(crate) fn sign_with<F>(digest: &[u8], unauthenticated: F) -> Vec<u8>
+    where
+        F: Fn(&[u8]) -> Vec<u8>,
+    {
+        let indirect = encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                &encode_constructed(
+                    der::TAG_SEQUENCE,
+                    &[
+                        &encode(der::TAG_OID, OID_SPC_PE_IMAGE_DATA),
+                        &encode(der::TAG_SEQUENCE, &[]),
+                    ],
+                ),
+                &encode_constructed(
+                    der::TAG_SEQUENCE,
+                    &[
+                        &encode_algorithm(der::OID_SHA256),
+                        &encode(der::TAG_OCTET_STRING, digest),
+                    ],
+                ),
+            ],
+        );
+        sign_content(OID_SPC_INDIRECT_DATA, &indirect, unauthenticated)
+    }
+
+    // A ContentInfo holding `content` (the encoded eContent) signed with the
+    // test KEK. `unauthenticated` gets the signature and returns the
+    // unauthenticated attributes, if any.
+    pub(crate) fn sign_content<F>(
+        content_type: &[u8],
+        content: &[u8],
+        unauthenticated: F,
+    ) -> Vec<u8>
+    where
+        F: Fn(&[u8]) -> Vec<u8>,
+    {
+        let certificate = Certificate::from_der(auth_files::TEST_KEK_CERT).unwrap();
+        let key = RsaPrivateKey::from_pkcs8_der(auth_files::TEST_KEK_KEY_PKCS8).unwrap();
+
+        let content_octets = DerReader::new(content).read_any().unwrap().contents;
+        let attributes = [
+            attribute(OID_CONTENT_TYPE, &encode(der::TAG_OID, content_type)),
+            attribute(
+                OID_MESSAGE_DIGEST,
+                &encode(der::TAG_OCTET_STRING, &Sha256::digest(content_octets)),
+            ),
+        ]
+        .concat();
+        let signature = key
+            .sign(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(encode(der::TAG_SET, &attributes)),
+            )
+            .unwrap();
+
+        let version = encode(der::TAG_INTEGER, &[0x01]);
+        let issuer_and_serial = encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                certificate.issuer.raw,
+                &der::encode_unsigned_integer(certificate.serial),
+            ],
+        );
+        let mut signer_fields = vec![
+            version.clone(),
+            issuer_and_serial,
+            encode_algorithm(der::OID_SHA256),
+            encode(tag_context(0), &attributes),
+            encode_algorithm(der::OID_RSA_ENCRYPTION),
+            encode(der::TAG_OCTET_STRING, &signature),
+        ];
+        let unauthenticated = unauthenticated(&signature);
+        if !unauthenticated.is_empty() {
+            signer_fields.push(encode(tag_context(1), &unauthenticated));
+        }
+        let signer_fields: Vec<&[u8]> = signer_fields.iter().map(Vec::as_slice).collect();
+        let signer_info = encode_constructed(der::TAG_SEQUENCE, &signer_fields);
+
+        let signed_data = encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                &version,
+                &encode(der::TAG_SET, &encode_algorithm(der::OID_SHA256)),
+                &encode_constructed(
+                    der::TAG_SEQUENCE,
+                    &[
+                        &encode(der::TAG_OID, content_type),
+                        &encode(tag_context(0), content),
+                    ],
+                ),
+                &encode(tag_context(0), auth_files::TEST_KEK_CERT),
+                &encode(der::TAG_SET, &signer_info),
+            ],
+        );
+        encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                &encode(der::TAG_OID, OID_PKCS7_SIGNED_DATA),
+                &encode(tag_context(0), &signed_data),
+            ],
+        )
+    }
+
+    // A PKCS#9 countersignature; only its signingTime matters here.
+    pub(crate) fn countersignature(time: &str) -> Vec<u8> {
+        let certificate = Certificate::from_der(auth_files::TEST_KEK_CERT).unwrap();
+        let countersigner = encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                &encode(der::TAG_INTEGER, &[0x01]),
+                &encode_constructed(
+                    der::TAG_SEQUENCE,
+                    &[
+                        certificate.issuer.raw,
+                        &der::encode_unsigned_integer(certificate.serial),
+                    ],
+                ),
+                &encode_algorithm(der::OID_SHA256),
+                &encode(
+                    tag_context(0),
+                    &attribute(
+                        OID_SIGNING_TIME,
+                        &encode(der::TAG_UTC_TIME, time.as_bytes()),
+                    ),
+                ),
+                &encode_algorithm(der::OID_RSA_ENCRYPTION),
+                &encode(der::TAG_OCTET_STRING, &[0u8; 16]),
+            ],
+        );
+        attribute(OID_COUNTER_SIGNATURE, &countersigner)
+    }
+
+    // An RFC 3161 timestamp attribute for `signature`, from a token the
+    // test KEK signs.
+    pub(crate) fn rfc3161_timestamp(signature: &[u8], time: &str) -> Vec<u8> {
+        let tst_info = encode_constructed(
+            der::TAG_SEQUENCE,
+            &[
+                &encode(der::TAG_INTEGER, &[0x01]),
+                // 1.2.3, a placeholder policy.
+                &encode(der::TAG_OID, &[0x2A, 0x03]),
+                &encode_constructed(
+                    der::TAG_SEQUENCE,
+                    &[
+                        &encode_algorithm(der::OID_SHA256),
+                        &encode(der::TAG_OCTET_STRING, &Sha256::digest(signature)),
+                    ],
+                ),
+                &encode(der::TAG_INTEGER, &[0x2A]),
+                &encode(der::TAG_GENERALIZED_TIME, time.as_bytes()),
+            ],
+        );
+        let token = sign_content(
+            OID_TST_INFO,
+            &encode(der::TAG_OCTET_STRING, &tst_info),
+            |_| Vec::new(),
+        );
+        attribute(OID_SPC_RFC3161_TIMESTAMP, &token)
+    }
+
+    pub(crate) fn win_certificate(signed_data: &[u8]) -> Vec<u8> {
+        let length = WIN_CERTIFICATE_HEADER_SIZE + signed_data.len();
+        let mut entry = Vec::new();
+        entry.extend_from_slice(&(length as u32).to_le_bytes());
+        entry.extend_from_slice(&0x0200u16.to_le_bytes());
+        entry.extend_from_slice(&WIN_CERT_TYPE_PKCS_SIGNED_DATA.to_le_bytes());
+        entry.extend_from_slice(signed_data);
+        entry.resize((length + 7) & !7, 0);
+        entry
+    }
+
+    // build_image() with `table` as its certificate table. The table isn't
+    // part of the digest, so it's the same as the unsigned image's.
+    pub(crate) fn signed_image(table: &[u8]) -> Vec<u8> {
+        let mut image = build_image(table.len());
+        image[0x400..].copy_from_slice(table);
+        image
+    }
+
+    #[test]
+    fn image_signature_should_decode_embedded_signature() {
+        let digest = image_digest(&mut build_image(0).as_slice(), HashAlgorithm::Sha256).unwrap();
+        let table = win_certificate(&sign(&digest, &countersignature("240501120000Z")));
+        let buffer = signed_image(&table);
+
+        let (image, headers) = read_headers(&mut buffer.as_slice()).unwrap();
+        let read = read_certificate_table(&image, &mut buffer.as_slice()).unwrap();
+        assert_eq!(read, table);
+        let entries = parse_certificate_table(&read).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        let signature = ImageSignature::from_der(entries[0].signed_data().unwrap()).unwrap();
+        assert_eq!(
+            signature
+                .signer_certificate()
+                .unwrap()
+                .subject
+                .common_name(),
+            Some(String::from("Test KEK 2024"))
+        );
+        assert_eq!(signature.chain().len(), 1);
+        assert_eq!(signature.digest_algorithm(), Some(HashAlgorithm::Sha256));
+        assert_eq!(signature.indirect.data_type.0, OID_SPC_PE_IMAGE_DATA);
+        assert_eq!(signature.indirect.digest, &digest[..]);
+        assert!(signature.verify().is_ok());
+        assert_eq!(signature.timestamps.len(), 1);
+        assert_eq!(
+            signature.timestamps[0].kind,
+            TimestampKind::Countersignature
+        );
+        assert_eq!(
+            signature.timestamps[0].time,
+            "2024-05-01T12:00:00Z".parse().unwrap()
+        );
+
+        let digests = compute_digests(
+            &image,
+            &headers,
+            &mut buffer.as_slice(),
+            &[HashAlgorithm::Sha256],
+        )
+        .unwrap();
+        let computed = [(HashAlgorithm::Sha256, digests[0].clone())];
+        assert_eq!(signature.check_digest(&computed), DigestCheck::Match);
+
+        // Patch the code after signing.
+        let mut patched = buffer.clone();
+        patched[0x210] ^= 0xFF;
+        let digest = image_digest(&mut patched.as_slice(), HashAlgorithm::Sha256).unwrap();
+        assert_eq!(
+            signature.check_digest(&[(HashAlgorithm::Sha256, digest)]),
+            DigestCheck::Mismatch
+        );
+        assert_eq!(signature.check_digest(&[]), DigestCheck::Unsupported);
+    }
+
+    #[test]
+    fn image_signature_should_find_nested_signatures() {
+        let inner = sign(&[0x11; 32], &[]);
+        let outer = sign(&[0x22; 32], &attribute(OID_SPC_NESTED_SIGNATURE, &inner));
+        let signature = ImageSignature::from_der(&outer).unwrap();
+        assert_eq!(signature.nested.len(), 1);
+        let all = signature.all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].indirect.digest, &[0x11; 32][..]);
+        assert!(all[1].verify().is_ok());
+    }
+
+    #[test]
+    fn parse_certificate_table_should_reject_bad_lengths() {
+        let entry = win_certificate(&[0x30, 0x00]);
+        let mut table = entry.clone();
+        table.extend_from_slice(&entry);
+        assert_eq!(parse_certificate_table(&table).unwrap().len(), 2);
+
+        let mut bad = table.clone();
+        bad[0..4].copy_from_slice(&0x1000u32.to_le_bytes());
+        assert_eq!(
+            parse_certificate_table(&bad),
+            Err(ImageSignatureError::BadCertificateTable(0))
+        );
+        let mut bad = table;
+        bad[8..12].copy_from_slice(&4u32.to_le_bytes());
+        assert_eq!(
+            parse_certificate_table(&bad[8..]),
+            Err(ImageSignatureError::BadCertificateTable(0))
+        );
+        assert!(matches!(
+            ImageSignature::from_der(&[0x30, 0x00]),
+            Err(ImageSignatureError::BadSignedData(_))
+        ));
+    }
+}
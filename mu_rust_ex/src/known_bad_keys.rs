@@ -0,0 +1,301 @@
+/* @file
+Detection of test and leaked keys in PK, KEK and db.
+
+Firmware sometimes ships with the sample platform key from a vendor's
+reference code ("PKfail"). Its private key is not secret, so anyone can sign
+a KEK or db update, or a boot loader, that the platform will accept. The
+same goes for any key whose private half has leaked. Certificates are
+matched by SHA-1 thumbprint against known-bad keys, and by subject or issuer
+against the names test keys are usually given.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::auth_variable::{
+    EFI_CERT_X509_GUID, EFI_IMAGE_SECURITY_DATABASE, EFI_KEY_EXCHANGE_KEY_NAME,
+    EFI_PLATFORM_KEY_NAME,
+};
+use crate::secure_boot_status::KeyDatabases;
+use crate::x509::{Certificate, DebugHex, Name};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyRisk {
+    // Generated for testing and never meant to leave the lab.
+    TestKey,
+    // A production key whose private half is public.
+    LeakedKey,
+}
+
+impl KeyRisk {
+    // What an attacker can do when `variable` trusts such a key.
+    pub fn explanation(&self, variable: &str) -> &'static str {
+        match (self, variable) {
+            (_, EFI_PLATFORM_KEY_NAME) => {
+                "anyone can replace KEK and db, so Secure Boot protects nothing"
+            }
+            (_, EFI_KEY_EXCHANGE_KEY_NAME) => "anyone can add their own keys to db or remove dbx",
+            (KeyRisk::TestKey, _) => "anyone can sign a boot loader the platform will run",
+            (KeyRisk::LeakedKey, _) => {
+                "anyone holding the leaked key can sign a boot loader the platform will run"
+            }
+        }
+    }
+}
+
+pub struct KnownBadKey {
+    pub description: &'static str,
+    pub sha1_thumbprint: [u8; 20],
+    pub risk: KeyRisk,
+}
+
+impl PartialEq for KnownBadKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.sha1_thumbprint == other.sha1_thumbprint
+    }
+}
+
+impl fmt::Debug for KnownBadKey {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "{} ({:?}, {:?})",
+            self.description,
+            self.risk,
+            DebugHex(&self.sha1_thumbprint)
+        ))
+    }
+}
+
+// Keys with a published certificate whose private half is known to be out:
+// PKfail sample PKs from the Binarly disclosure and leaked vendor keys. Only
+// add an entry after checking the thumbprint against the certificate itself.
+// Sample PKs that aren't listed are still caught by TEST_KEY_PATTERNS.
+pub const KNOWN_BAD_THUMBPRINTS: &[KnownBadKey] = &[];
+
+// Matched case-insensitively against every subject and issuer attribute.
+// "DO NOT TRUST" is how the AMI sample PK behind PKfail is labelled.
+pub const TEST_KEY_PATTERNS: &[&str] = &["DO NOT TRUST", "DO NOT SHIP", "TEST PK", "TEST KEK"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Detection {
+    // (entry) The thumbprint is in the known-bad table.
+    Thumbprint(&'static KnownBadKey),
+    // (pattern, value) The subject matches a test key pattern.
+    Subject(&'static str, String),
+    // (pattern, value) The issuer does; the certificate was signed by a test key.
+    Issuer(&'static str, String),
+}
+
+impl Detection {
+    pub fn risk(&self) -> KeyRisk {
+        match self {
+            Self::Thumbprint(entry) => entry.risk,
+            Self::Subject(..) | Self::Issuer(..) => KeyRisk::TestKey,
+        }
+    }
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Thumbprint(entry) => write!(f, "known bad key: {}", entry.description),
+            Self::Subject(pattern, value) => {
+                write!(f, "subject \"{}\" matches \"{}\"", value, pattern)
+            }
+            Self::Issuer(pattern, value) => {
+                write!(f, "issuer \"{}\" matches \"{}\"", value, pattern)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyWarning {
+    pub variable: &'static str,
+    pub subject: String,
+    pub sha1_thumbprint: [u8; 20],
+    pub detection: Detection,
+}
+
+impl fmt::Debug for KeyWarning {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_fmt(format_args!(
+                "WARNING: untrustworthy {:?} in {}\n",
+                self.detection.risk(),
+                self.variable
+            ))?;
+            fmtr.write_fmt(format_args!("\tSubject: {}\n", self.subject))?;
+            fmtr.write_fmt(format_args!(
+                "\tThumbprint: {:?}\n",
+                DebugHex(&self.sha1_thumbprint)
+            ))?;
+            fmtr.write_fmt(format_args!("\tDetected by {}\n", self.detection))?;
+            fmtr.write_fmt(format_args!(
+                "\tRisk: {}\n",
+                self.detection.risk().explanation(self.variable)
+            ))
+        } else {
+            fmtr.write_fmt(format_args!(
+                "KeyWarning {{ {}: \"{}\", {} }}",
+                self.variable, self.subject, self.detection
+            ))
+        }
+    }
+}
+
+fn find_pattern(name: &Name) -> Option<(&'static str, String)> {
+    name.attributes().into_iter().find_map(|(_, value)| {
+        let upper = value.to_ascii_uppercase();
+        TEST_KEY_PATTERNS
+            .iter()
+            .find(|pattern| upper.contains(**pattern))
+            .map(|pattern| (*pattern, value))
+    })
+}
+
+// Why `certificate` shouldn't be trusted, if it shouldn't.
+pub fn check_certificate(certificate: &Certificate) -> Option<Detection> {
+    check_certificate_against(certificate, KNOWN_BAD_THUMBPRINTS)
+}
+
+// Like check_certificate(), with `known_bad` in place of KNOWN_BAD_THUMBPRINTS.
+pub fn check_certificate_against(
+    certificate: &Certificate,
+    known_bad: &'static [KnownBadKey],
+) -> Option<Detection> {
+    let thumbprint = certificate.sha1_thumbprint();
+    if let Some(entry) = known_bad
+        .iter()
+        .find(|entry| entry.sha1_thumbprint == thumbprint)
+    {
+        return Some(Detection::Thumbprint(entry));
+    }
+    if let Some((pattern, value)) = find_pattern(&certificate.subject) {
+        return Some(Detection::Subject(pattern, value));
+    }
+    find_pattern(&certificate.issuer).map(|(pattern, value)| Detection::Issuer(pattern, value))
+}
+
+// Checks every X509 certificate in PK, KEK and db. Hash entries and
+// certificates that don't parse are skipped.
+pub fn scan(databases: &KeyDatabases) -> Vec<KeyWarning> {
+    scan_against(databases, KNOWN_BAD_THUMBPRINTS)
+}
+
+pub fn scan_against(
+    databases: &KeyDatabases,
+    known_bad: &'static [KnownBadKey],
+) -> Vec<KeyWarning> {
+    let mut warnings = Vec::new();
+    for (variable, snapshot) in IntoIterator::into_iter([
+        (EFI_PLATFORM_KEY_NAME, &databases.pk),
+        (EFI_KEY_EXCHANGE_KEY_NAME, &databases.kek),
+        (EFI_IMAGE_SECURITY_DATABASE, &databases.db),
+    ]) {
+        let database = match snapshot.as_ref().map(|snapshot| &snapshot.database) {
+            Some(Ok(database)) => database,
+            _ => continue,
+        };
+        for list in database
+            .entries
+            .iter()
+            .filter(|list| list.list_type == EFI_CERT_X509_GUID)
+        {
+            for element in list.elements.iter() {
+                let certificate = match Certificate::from_der(&element.data) {
+                    Ok(certificate) => certificate,
+                    Err(_) => continue,
+                };
+                if let Some(detection) = check_certificate_against(&certificate, known_bad) {
+                    warnings.push(KeyWarning {
+                        variable,
+                        subject: certificate.subject.common_name().unwrap_or_default(),
+                        sha1_thumbprint: certificate.sha1_thumbprint(),
+                        detection,
+                    });
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::{SignatureDatabase, SignatureList, SignatureListElement};
+    use crate::name_db::MICROSOFT_SIGNATURE_OWNER_GUID;
+    use crate::secure_boot_status::DatabaseSnapshot;
+    use crate::test_data::{auth_files, sig_lists};
+    use alloc::vec;
+
+    const TEST_KNOWN_BAD: &[KnownBadKey] = &[KnownBadKey {
+        // Its PKCS#8 key is in test_data, for signing test updates.
+        description: "mu_rust_ex Test KEK 2024",
+        sha1_thumbprint: [
+            0xAF, 0x59, 0x7F, 0x94, 0x94, 0x3D, 0xCC, 0xFF, 0x79, 0x58, 0x17, 0xF4, 0xBE, 0xE0,
+            0x17, 0x45, 0xFB, 0xE6, 0xB5, 0xAC,
+        ],
+        risk: KeyRisk::LeakedKey,
+    }];
+
+    #[test]
+    fn scan_should_flag_known_bad_keys() {
+        let test_kek = SignatureDatabase {
+            entries: vec![SignatureList {
+                list_type: EFI_CERT_X509_GUID,
+                header: None,
+                elements: vec![SignatureListElement {
+                    owner: MICROSOFT_SIGNATURE_OWNER_GUID,
+                    data: Vec::from(auth_files::TEST_KEK_CERT),
+                }],
+            }],
+        };
+        let databases = KeyDatabases {
+            pk: Some(DatabaseSnapshot::from_bytes(
+                "PK",
+                &test_kek.to_bytes().unwrap(),
+            )),
+            kek: Some(DatabaseSnapshot::from_bytes("KEK", sig_lists::KEK)),
+            db: Some(DatabaseSnapshot::from_bytes("db", sig_lists::DB)),
+            ..Default::default()
+        };
+
+        let warnings = scan_against(&databases, TEST_KNOWN_BAD);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].variable, "PK");
+        assert_eq!(warnings[0].subject, "Test KEK 2024");
+        assert_eq!(
+            warnings[0].detection,
+            Detection::Thumbprint(&TEST_KNOWN_BAD[0])
+        );
+        assert_eq!(warnings[0].detection.risk(), KeyRisk::LeakedKey);
+
+        // Without the table entry it's still caught by its name.
+        let warnings = scan(&databases);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].detection,
+            Detection::Subject("TEST KEK", String::from("Test KEK 2024"))
+        );
+    }
+
+    #[test]
+    fn check_certificate_should_match_test_key_names() {
+        let certificate = Certificate::from_der(auth_files::TEST_KEK_CERT).unwrap();
+        assert_eq!(
+            find_pattern(&certificate.subject),
+            Some(("TEST KEK", String::from("Test KEK 2024")))
+        );
+
+        let microsoft = SignatureList::from_bytes(sig_lists::KEK).unwrap();
+        let certificate = Certificate::from_der(&microsoft.elements[0].data).unwrap();
+        assert_eq!(check_certificate(&certificate), None);
+    }
+}
@@ -8,18 +8,35 @@ extern crate lazy_static;
 // TODO: Can any of this be gated behind an "alloc" feature flag?
 
 pub mod auth_variable;
+pub mod authenticode;
 pub mod boot;
+pub mod catalog;
 pub mod con_in;
+pub mod der;
 pub mod image_authentication;
+pub mod image_policy;
+pub mod image_signature;
+pub mod key_file;
+pub mod known_bad_keys;
+pub mod name_db;
+pub mod ownership;
+pub mod pe;
+pub mod pkcs7;
+pub mod preflight;
 pub mod protocol_utility;
+pub mod rng_protocol;
+pub mod rotation;
 pub mod runtime;
 pub mod rustified;
+pub mod secure_boot_status;
 pub mod shell_parameters_protocol;
 pub mod shell_protocol;
+pub mod signer;
 #[cfg(test)]
 mod test_data;
 pub mod util;
 pub mod variable;
+pub mod x509;
 
 use r_efi::efi;
 
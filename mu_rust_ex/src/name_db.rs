@@ -0,0 +1,454 @@
+/* @file
+Human-readable names for the GUIDs, certificates and hashes that show up
+in Secure Boot variables.
+
+There are two tables. The built-in one ships with the values we know about
+ahead of time. The user one is kept in a text file on the ESP and grows as
+the user enrolls new things; its names take precedence over the built-ins.
+
+File format is one entry per line: a key (a registry-format GUID or a hex
+digest), whitespace, then the name. Blank lines and lines starting with '#'
+are ignored.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use r_efi::efi;
+use spin::{Mutex, MutexGuard};
+
+use crate::auth_variable::*;
+use crate::protocol_utility::RustProtocolError as RPError;
+use crate::shell_protocol::Protocol as ShellProtocol;
+use crate::util::{guid_from_str, guid_to_string, hex_from_str, hex_to_string};
+use crate::variable::EFI_GLOBAL_VARIABLE_GUID;
+use crate::x509::Certificate;
+
+pub const DEFAULT_USER_DATABASE_PATH: &str = "\\EFI\\SecureBootManager\\names.txt";
+
+// SignatureOwner used by Microsoft for everything it publishes.
+pub const MICROSOFT_SIGNATURE_OWNER_GUID: efi::Guid = efi::Guid::from_fields(
+    0x77fa9abd,
+    0x0359,
+    0x4d32,
+    0xbd,
+    0x60,
+    &[0x28, 0xf4, 0xe7, 0x8f, 0x78, 0x4b],
+);
+// SHIM_LOCK_GUID. Owner of entries enrolled through shim's MokManager.
+pub const SHIM_LOCK_GUID: efi::Guid = efi::Guid::from_fields(
+    0x605dab50,
+    0xe046,
+    0x4300,
+    0xab,
+    0xb6,
+    &[0x3d, 0xd8, 0x10, 0xdd, 0x8b, 0x23],
+);
+pub const CANONICAL_SIGNATURE_OWNER_GUID: efi::Guid = efi::Guid::from_fields(
+    0x6dc40ae4,
+    0x2ee8,
+    0x9c4c,
+    0xa3,
+    0x14,
+    &[0x0f, 0xc7, 0xb2, 0x00, 0x87, 0x10],
+);
+// Red Hat's SMBIOS OEM string prefix. OVMF's EnrollDefaultKeys uses it as the
+// SignatureOwner of the PK and KEK it enrolls on Red Hat virtualization hosts.
+pub const RED_HAT_SIGNATURE_OWNER_GUID: efi::Guid = efi::Guid::from_fields(
+    0x4e32566d,
+    0x8e9e,
+    0x4f52,
+    0x81,
+    0xd3,
+    &[0x5b, 0xb9, 0x71, 0x5f, 0x97, 0x27],
+);
+
+const BUILTIN_GUIDS: &[(efi::Guid, &str)] = &[
+    (EFI_GLOBAL_VARIABLE_GUID, "EFI_GLOBAL_VARIABLE_GUID"),
+    (
+        EFI_IMAGE_SECURITY_DATABASE_GUID,
+        "EFI_IMAGE_SECURITY_DATABASE_GUID",
+    ),
+    (
+        EFI_SECURE_BOOT_ENABLE_DISABLE_GUID,
+        "EFI_SECURE_BOOT_ENABLE_DISABLE_GUID",
+    ),
+    (EFI_CERT_SHA1_GUID, "EFI_CERT_SHA1_GUID"),
+    (EFI_CERT_SHA224_GUID, "EFI_CERT_SHA224_GUID"),
+    (EFI_CERT_SHA256_GUID, "EFI_CERT_SHA256_GUID"),
+    (EFI_CERT_SHA384_GUID, "EFI_CERT_SHA384_GUID"),
+    (EFI_CERT_SHA512_GUID, "EFI_CERT_SHA512_GUID"),
+    (EFI_CERT_RSA2048_GUID, "EFI_CERT_RSA2048_GUID"),
+    (EFI_CERT_RSA2048_SHA1_GUID, "EFI_CERT_RSA2048_SHA1_GUID"),
+    (EFI_CERT_RSA2048_SHA256_GUID, "EFI_CERT_RSA2048_SHA256_GUID"),
+    (EFI_CERT_X509_GUID, "EFI_CERT_X509_GUID"),
+    (EFI_CERT_X509_SHA256_GUID, "EFI_CERT_X509_SHA256_GUID"),
+    (EFI_CERT_X509_SHA384_GUID, "EFI_CERT_X509_SHA384_GUID"),
+    (EFI_CERT_X509_SHA512_GUID, "EFI_CERT_X509_SHA512_GUID"),
+    (EFI_CERT_TYPE_PKCS7_GUID, "EFI_CERT_TYPE_PKCS7_GUID"),
+    (MICROSOFT_SIGNATURE_OWNER_GUID, "Microsoft Corporation"),
+    (SHIM_LOCK_GUID, "Shim (MOK)"),
+    (CANONICAL_SIGNATURE_OWNER_GUID, "Canonical Ltd."),
+    (RED_HAT_SIGNATURE_OWNER_GUID, "Red Hat, Inc."),
+];
+
+// Certificates are keyed by SHA-1 thumbprint.
+const BUILTIN_DIGESTS: &[(&[u8], &str)] = &[
+    (
+        &[
+            0x31, 0x59, 0x0B, 0xFD, 0x89, 0xC9, 0xD7, 0x4E, 0xD0, 0x87, 0xDF, 0xAC, 0x66, 0x33,
+            0x4B, 0x39, 0x31, 0x25, 0x4B, 0x30,
+        ],
+        "Microsoft Corporation KEK CA 2011",
+    ),
+    (
+        &[
+            0x58, 0x0A, 0x6F, 0x4C, 0xC4, 0xE4, 0xB6, 0x69, 0xB9, 0xEB, 0xDC, 0x1B, 0x2B, 0x3E,
+            0x08, 0x7B, 0x80, 0xD0, 0x67, 0x8D,
+        ],
+        "Microsoft Windows Production PCA 2011",
+    ),
+    (
+        &[
+            0x46, 0xDE, 0xF6, 0x3B, 0x5C, 0xE6, 0x1C, 0xF8, 0xBA, 0x0D, 0xE2, 0xE6, 0x63, 0x9C,
+            0x10, 0x19, 0xD0, 0xED, 0x14, 0xF3,
+        ],
+        "Microsoft Corporation UEFI CA 2011",
+    ),
+    (
+        &[
+            0xED, 0x8E, 0x40, 0x9F, 0x23, 0x58, 0x71, 0x8D, 0xB5, 0x4C, 0x62, 0xC5, 0x5A, 0xC4,
+            0xBE, 0xE7, 0xD4, 0x51, 0x1D, 0x02,
+        ],
+        "Microsoft Surface NFF UEFI PK CA 2015",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameKey {
+    Guid(efi::Guid),
+    // A hash, either of a certificate (thumbprint) or of an image.
+    Digest(Vec<u8>),
+}
+
+impl NameKey {
+    fn from_str(string: &str) -> Option<Self> {
+        guid_from_str(string)
+            .map(Self::Guid)
+            .or_else(|| hex_from_str(string).map(Self::Digest))
+    }
+}
+
+impl fmt::Display for NameKey {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Guid(guid) => fmtr.write_str(&guid_to_string(guid)),
+            Self::Digest(digest) => fmtr.write_str(&hex_to_string(digest)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameEntry {
+    pub key: NameKey,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum NameDatabaseError {
+    // (line) The line (1-based) couldn't be parsed.
+    BadLine(usize),
+    // The file isn't valid UTF-8.
+    BadEncoding,
+    Protocol(RPError),
+}
+impl fmt::Display for NameDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadLine(line) => write!(f, "Invalid name database entry on line {}", line),
+            Self::BadEncoding => write!(f, "Name database is not valid UTF-8"),
+            Self::Protocol(err) => write!(f, "Name database file access failed ({:?})", err),
+        }
+    }
+}
+impl From<NameDatabaseError> for efi::Status {
+    fn from(f: NameDatabaseError) -> Self {
+        match f {
+            NameDatabaseError::BadLine(_) => efi::Status::VOLUME_CORRUPTED,
+            NameDatabaseError::BadEncoding => efi::Status::VOLUME_CORRUPTED,
+            NameDatabaseError::Protocol(x) => x.into(),
+        }
+    }
+}
+impl From<RPError> for NameDatabaseError {
+    fn from(f: RPError) -> Self {
+        Self::Protocol(f)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameDatabase {
+    pub entries: Vec<NameEntry>,
+}
+
+impl NameDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lookup(&self, key: &NameKey) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == *key)
+            .map(|entry| entry.name.as_str())
+    }
+
+    // Adds or renames an entry. Returns the previous name, if any.
+    pub fn insert(&mut self, key: NameKey, name: &str) -> Option<String> {
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => Some(core::mem::replace(&mut entry.name, String::from(name))),
+            None => {
+                self.entries.push(NameEntry {
+                    key,
+                    name: String::from(name),
+                });
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &NameKey) -> Option<String> {
+        let index = self.entries.iter().position(|entry| entry.key == *key)?;
+        Some(self.entries.remove(index).name)
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, NameDatabaseError> {
+        let mut database = Self::new();
+        for (index, line) in text.trim_start_matches('\u{FEFF}').lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or(NameDatabaseError::BadLine(index + 1))?;
+            let key = NameKey::from_str(key).ok_or(NameDatabaseError::BadLine(index + 1))?;
+            database.insert(key, name.trim());
+        }
+        Ok(database)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for entry in self.entries.iter() {
+            // Writing to a String can't fail.
+            let _ = writeln!(text, "{} {}", entry.key, entry.name);
+        }
+        text
+    }
+
+    pub fn load(shell: &ShellProtocol, path: &str) -> Result<Self, NameDatabaseError> {
+        let file = match shell.open_file_by_name(path, r_efi::protocols::file::MODE_READ) {
+            // An interrupted save() may have left only the new copy behind.
+            Err(RPError::Efi(efi::Status::NOT_FOUND)) => {
+                shell.open_file_by_name(&temporary_path(path), r_efi::protocols::file::MODE_READ)?
+            }
+            file => file?,
+        };
+        let bytes = file.read_count(file.get_size()?)?;
+        let text = core::str::from_utf8(&bytes).map_err(|_| NameDatabaseError::BadEncoding)?;
+        Self::from_text(text)
+    }
+
+    // The new table is written out in full next to the old one before it
+    // replaces it, so a failed write leaves the old table intact. UEFI file
+    // systems can't rename over an existing file, so there's a brief window
+    // with only the temporary copy on disk; load() falls back to it.
+    pub fn save(&self, shell: &ShellProtocol, path: &str) -> Result<(), NameDatabaseError> {
+        let temp_path = temporary_path(path);
+        // Opening an existing file doesn't truncate it, so start over.
+        delete_if_present(shell, &temp_path)?;
+        let mut file = shell.create_file(
+            &temp_path,
+            r_efi::protocols::file::MODE_CREATE
+                | r_efi::protocols::file::MODE_READ
+                | r_efi::protocols::file::MODE_WRITE,
+        )?;
+        let text = self.to_text();
+        if file.write(text.as_bytes())? != text.len() {
+            return Err(NameDatabaseError::Protocol(RPError::Efi(
+                efi::Status::VOLUME_FULL,
+            )));
+        }
+        file.flush()?;
+
+        delete_if_present(shell, path)?;
+        let file_name = path.rsplit('\\').next().unwrap_or(path);
+        Ok(file.rename(file_name)?)
+    }
+}
+
+fn temporary_path(path: &str) -> String {
+    let mut temp_path = String::from(path);
+    temp_path.push_str(".tmp");
+    temp_path
+}
+
+fn delete_if_present(shell: &ShellProtocol, path: &str) -> Result<(), NameDatabaseError> {
+    match shell.delete_file_by_name(path) {
+        Ok(_) | Err(RPError::Efi(efi::Status::NOT_FOUND)) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+lazy_static! {
+    static ref USER_DATABASE: Mutex<NameDatabase> = Mutex::new(NameDatabase::new());
+}
+
+// Don't hold this across anything that formats a DebugGuid; lookups take the
+// same lock.
+pub fn user_database() -> MutexGuard<'static, NameDatabase> {
+    USER_DATABASE.lock()
+}
+
+pub fn load_user_database(shell: &ShellProtocol, path: &str) -> Result<(), NameDatabaseError> {
+    let database = NameDatabase::load(shell, path)?;
+    *user_database() = database;
+    Ok(())
+}
+
+pub fn save_user_database(shell: &ShellProtocol, path: &str) -> Result<(), NameDatabaseError> {
+    let database = user_database().clone();
+    database.save(shell, path)
+}
+
+pub fn lookup_guid(guid: &efi::Guid) -> Option<String> {
+    if let Some(name) = user_database().lookup(&NameKey::Guid(*guid)) {
+        return Some(String::from(name));
+    }
+    BUILTIN_GUIDS
+        .iter()
+        .find(|(builtin, _)| builtin == guid)
+        .map(|(_, name)| String::from(*name))
+}
+
+pub fn lookup_digest(digest: &[u8]) -> Option<String> {
+    if let Some(name) = user_database().lookup(&NameKey::Digest(Vec::from(digest))) {
+        return Some(String::from(name));
+    }
+    BUILTIN_DIGESTS
+        .iter()
+        .find(|(builtin, _)| *builtin == digest)
+        .map(|(_, name)| String::from(*name))
+}
+
+// Tries both of the thumbprints that certificates are commonly known by.
+pub fn lookup_certificate(certificate: &Certificate) -> Option<String> {
+    lookup_digest(&certificate.sha1_thumbprint())
+        .or_else(|| lookup_digest(&certificate.sha256_thumbprint()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::SignatureList;
+    use crate::test_data::sig_lists;
+    use crate::util::DebugGuid;
+
+    #[test]
+    fn name_db_should_round_trip_text() {
+        let text = "# Comment\n\
+                    \n\
+                    605DAB50-E046-4300-ABB6-3DD810DD8B23  Shim Owner\n\
+                    {8be4df61-93ca-11d2-aa0d-00e098032b8c} Global Variables\n\
+                    ED:8E:40:9F:23:58:71:8D:B5:4C:62:C5:5A:C4:BE:E7:D4:51:1D:02 Surface PK\n";
+        let database = NameDatabase::from_text(text).unwrap();
+        assert_eq!(database.entries.len(), 3);
+        assert_eq!(
+            database.lookup(&NameKey::Guid(SHIM_LOCK_GUID)),
+            Some("Shim Owner")
+        );
+        assert_eq!(
+            database.lookup(&NameKey::Guid(EFI_GLOBAL_VARIABLE_GUID)),
+            Some("Global Variables")
+        );
+
+        let text = database.to_text();
+        assert!(text.starts_with("605DAB50-E046-4300-ABB6-3DD810DD8B23 Shim Owner\n"));
+        assert!(text.contains("\nED8E409F2358718DB54C62C55AC4BEE7D4511D02 Surface PK\n"));
+        assert_eq!(NameDatabase::from_text(&text).unwrap(), database);
+    }
+
+    #[test]
+    fn name_db_should_reject_bad_lines() {
+        assert_eq!(
+            NameDatabase::from_text("\n605DAB50-E046-4300-ABB6-3DD810DD8B23\n")
+                .err()
+                .map(efi::Status::from),
+            Some(efi::Status::VOLUME_CORRUPTED)
+        );
+        assert!(matches!(
+            NameDatabase::from_text("# ok\nnot-a-key Name\n"),
+            Err(NameDatabaseError::BadLine(2))
+        ));
+        assert!(matches!(
+            NameDatabase::from_text("ABC Odd digits\n"),
+            Err(NameDatabaseError::BadLine(1))
+        ));
+    }
+
+    #[test]
+    fn name_db_insert_should_replace_existing_names() {
+        let mut database = NameDatabase::new();
+        let key = NameKey::Digest(vec![0x01, 0x02]);
+        assert_eq!(database.insert(key.clone(), "First"), None);
+        assert_eq!(
+            database.insert(key.clone(), "Second"),
+            Some(String::from("First"))
+        );
+        assert_eq!(database.entries.len(), 1);
+        assert_eq!(database.remove(&key), Some(String::from("Second")));
+        assert_eq!(database.lookup(&key), None);
+    }
+
+    #[test]
+    fn name_db_should_prefer_user_names() {
+        assert_eq!(
+            format!("{:?}", DebugGuid::from(MICROSOFT_SIGNATURE_OWNER_GUID)),
+            "Guid { Microsoft Corporation }"
+        );
+
+        // Only this test touches the user table for this GUID.
+        let guid = efi::Guid::from_fields(
+            0x12345678,
+            0x9abc,
+            0xdef0,
+            0x12,
+            0x34,
+            &[0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0],
+        );
+        assert_eq!(
+            format!("{:?}", DebugGuid::from(guid)),
+            "Guid { UNKNOWN(12345678-9ABC-DEF0-1234-56789ABCDEF0) }"
+        );
+        user_database().insert(NameKey::Guid(guid), "Test Owner");
+        assert_eq!(
+            format!("{:?}", DebugGuid::from(guid)),
+            "Guid { Test Owner }"
+        );
+        user_database().remove(&NameKey::Guid(guid));
+    }
+
+    #[test]
+    fn name_db_should_name_certificates_in_sig_lists() {
+        let output = format!("{:#?}", SignatureList::from_bytes(sig_lists::DB).unwrap());
+        assert!(output.contains("\tOwner: Guid { Microsoft Corporation }\n"));
+        assert!(output.contains("\tName: Microsoft Windows Production PCA 2011\n"));
+    }
+}
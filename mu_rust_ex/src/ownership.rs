@@ -0,0 +1,359 @@
+/* @file
+Taking ownership of a platform that's in Setup Mode (or Audit Mode, which
+also has SetupMode set).
+
+The PK is enrolled first, signed by its own key, which moves the platform to
+User Mode (Deployed Mode, from Audit). KEK goes second, signed by the new PK.
+Every write is checked with preflight() before anything touches firmware, and
+read back afterwards.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+
+use crate::auth_variable::*;
+use crate::der::DerError;
+use crate::image_authentication::{SignatureDatabase, SignatureList, SignatureListElement};
+use crate::preflight::{preflight, PreflightError, TrustState};
+use crate::runtime::SetVariableError;
+use crate::rustified;
+use crate::secure_boot_status::{SecureBootMode, SecureBootStatus};
+use crate::signer::Signer;
+use crate::variable::{EfiVariable, EFI_GLOBAL_VARIABLE_GUID};
+use crate::x509::Certificate;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OwnershipError {
+    // (mode) Ownership can only be taken from Setup or Audit Mode.
+    NotInSetupMode(SecureBootMode),
+    NoKekCertificates,
+    // (error) One of the supplied certificates failed to parse.
+    BadCertificate(DerError),
+    // (variable, error) Pre-flight says firmware would reject this write.
+    Preflight(&'static str, PreflightError),
+    // (variable, error) SetVariable() failed.
+    Write(&'static str, SetVariableError),
+    // (variable) What was read back doesn't match what was written.
+    ReadBack(&'static str),
+    Efi(efi::Status),
+}
+impl fmt::Display for OwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInSetupMode(mode) => {
+                write!(f, "Platform is in {:?} mode, not Setup or Audit", mode)
+            }
+            Self::NoKekCertificates => write!(f, "No KEK certificates supplied"),
+            Self::BadCertificate(err) => write!(f, "Invalid certificate ({})", err),
+            Self::Preflight(name, err) => write!(f, "{} would be rejected: {}", name, err),
+            Self::Write(name, err) => write!(f, "Writing {} failed: {}", name, err),
+            Self::ReadBack(name) => write!(f, "{} doesn't read back as written", name),
+            Self::Efi(status) => write!(f, "Taking ownership failed ({:?})", status),
+        }
+    }
+}
+impl From<OwnershipError> for efi::Status {
+    fn from(f: OwnershipError) -> Self {
+        match f {
+            OwnershipError::NotInSetupMode(_) => efi::Status::ACCESS_DENIED,
+            OwnershipError::NoKekCertificates => efi::Status::INVALID_PARAMETER,
+            OwnershipError::BadCertificate(x) => x.into(),
+            OwnershipError::Preflight(_, x) => x.into(),
+            OwnershipError::Write(_, x) => x.into(),
+            OwnershipError::ReadBack(_) => efi::Status::DEVICE_ERROR,
+            OwnershipError::Efi(x) => x,
+        }
+    }
+}
+impl From<efi::Status> for OwnershipError {
+    fn from(f: efi::Status) -> Self {
+        Self::Efi(f)
+    }
+}
+
+// One authenticated SetVariable() call, ready to go.
+#[derive(Clone)]
+pub struct PlannedWrite {
+    pub name: &'static str,
+    pub guid: efi::Guid,
+    pub attributes: u32,
+    // The signature lists the variable should hold afterwards.
+    pub contents: Vec<u8>,
+    // The full EFI_VARIABLE_AUTHENTICATION_2 payload.
+    pub payload: Vec<u8>,
+}
+
+impl fmt::Debug for PlannedWrite {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "PlannedWrite {{ {}: {} bytes of signature lists, {} byte payload }}",
+            self.name,
+            self.contents.len(),
+            self.payload.len()
+        ))
+    }
+}
+
+fn x509_database(certificates: &[Vec<u8>], owner: &efi::Guid) -> SignatureDatabase {
+    // Certificates differ in size, so each one gets its own list.
+    SignatureDatabase {
+        entries: certificates
+            .iter()
+            .map(|certificate| SignatureList {
+                list_type: EFI_CERT_X509_GUID,
+                header: None,
+                elements: vec![SignatureListElement {
+                    owner: *owner,
+                    data: certificate.clone(),
+                }],
+            })
+            .collect(),
+    }
+}
+
+// Builds and pre-flights the PK and KEK writes, in the order they have to
+// happen. `signer` must hold the PK's private key.
+pub fn plan_ownership<S: Signer + ?Sized>(
+    pk_certificate: &[u8],
+    kek_certificates: &[Vec<u8>],
+    owner: &efi::Guid,
+    timestamp: &rustified::Time,
+    signer: &mut S,
+) -> Result<Vec<PlannedWrite>, OwnershipError> {
+    if kek_certificates.is_empty() {
+        return Err(OwnershipError::NoKekCertificates);
+    }
+    for certificate in kek_certificates
+        .iter()
+        .map(Vec::as_slice)
+        .chain([pk_certificate])
+    {
+        Certificate::from_der(certificate).map_err(OwnershipError::BadCertificate)?;
+    }
+
+    let pk = x509_database(&[Vec::from(pk_certificate)], owner);
+    let kek = x509_database(kek_certificates, owner);
+    // What firmware will see before each write: PK goes in during Setup
+    // Mode, KEK once that PK has put the platform into User Mode.
+    let empty = || SignatureDatabase {
+        entries: Vec::new(),
+    };
+    let steps = [
+        (
+            EFI_PLATFORM_KEY_NAME,
+            &pk,
+            TrustState {
+                setup_mode: true,
+                pk: empty(),
+                kek: empty(),
+            },
+        ),
+        (
+            EFI_KEY_EXCHANGE_KEY_NAME,
+            &kek,
+            TrustState {
+                setup_mode: false,
+                pk: pk.clone(),
+                kek: empty(),
+            },
+        ),
+    ];
+
+    let mut plan = Vec::new();
+    for (name, database, state) in steps.iter() {
+        let name: &'static str = name;
+        let contents = database.to_bytes()?;
+        let variable = EfiAuthVariable2 {
+            variable: EfiVariable {
+                name: String::from(name),
+                guid: EFI_GLOBAL_VARIABLE_GUID,
+                data: contents.clone(),
+                attributes: EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+            },
+            time: *timestamp,
+        };
+        let payload = variable.get_signed_set_variable_data(signer)?;
+
+        let update = EfiAuthVariable2Ref::from_bytes(&payload).map_err(efi::Status::from)?;
+        preflight(
+            &update,
+            name,
+            EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+            state,
+            None,
+        )
+        .map_err(|err| OwnershipError::Preflight(name, err))?;
+
+        plan.push(PlannedWrite {
+            name,
+            guid: EFI_GLOBAL_VARIABLE_GUID,
+            attributes: EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+            contents,
+            payload,
+        });
+    }
+    Ok(plan)
+}
+
+#[derive(Clone)]
+pub struct OwnershipReport {
+    pub before: SecureBootMode,
+    pub after: SecureBootMode,
+    pub written: Vec<&'static str>,
+}
+
+impl fmt::Debug for OwnershipReport {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("OwnershipReport:\n")?;
+            fmtr.write_fmt(format_args!("\tWritten: {}\n", self.written.join(", ")))?;
+            fmtr.write_fmt(format_args!(
+                "\tMode: {:?} -> {:?}\n",
+                self.before, self.after
+            ))
+        } else {
+            fmtr.write_fmt(format_args!(
+                "OwnershipReport {{ Mode: {:?} -> {:?} }}",
+                self.before, self.after
+            ))
+        }
+    }
+}
+
+// Carries out a plan from plan_ownership(). Stops at the first write that
+// fails or doesn't read back, leaving the earlier ones in place.
+pub fn take_ownership(plan: &[PlannedWrite]) -> Result<OwnershipReport, OwnershipError> {
+    let before = SecureBootStatus::read_current()?.mode();
+    if !before.accepts_self_signed_pk() {
+        return Err(OwnershipError::NotInSetupMode(before));
+    }
+
+    let mut written = Vec::new();
+    for write in plan {
+        EfiVariable {
+            name: String::from(write.name),
+            guid: write.guid,
+            data: write.payload.clone(),
+            attributes: write.attributes,
+        }
+        .set()
+        .map_err(|err| OwnershipError::Write(write.name, err))?;
+
+        let stored = EfiVariable::get_variable(write.name, &write.guid)?;
+        if stored.data != write.contents {
+            return Err(OwnershipError::ReadBack(write.name));
+        }
+        written.push(write.name);
+    }
+
+    Ok(OwnershipReport {
+        before,
+        after: SecureBootStatus::read_current()?.mode(),
+        written,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::SignatureDatabaseRef;
+    use crate::signer::{MockSigner, RsaSigner};
+    use crate::test_data::{auth_files, sig_lists, TestRng};
+    use crate::x509::VerifyError;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    fn timestamp() -> rustified::Time {
+        "2024-05-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn microsoft_kek() -> Vec<u8> {
+        SignatureList::from_bytes(sig_lists::KEK).unwrap().elements[0]
+            .data
+            .clone()
+    }
+
+    #[test]
+    fn plan_ownership_should_enroll_pk_then_kek() {
+        let mut signer = RsaSigner::new(
+            RsaPrivateKey::from_pkcs8_der(auth_files::TEST_KEK_KEY_PKCS8).unwrap(),
+            vec![Vec::from(auth_files::TEST_KEK_CERT)],
+            TestRng::default(),
+        )
+        .unwrap();
+        let owner = crate::name_db::MICROSOFT_SIGNATURE_OWNER_GUID;
+        let keks = vec![microsoft_kek(), Vec::from(auth_files::TEST_KEK_CERT)];
+
+        let plan = plan_ownership(
+            auth_files::TEST_KEK_CERT,
+            &keks,
+            &owner,
+            &timestamp(),
+            &mut signer,
+        )
+        .unwrap();
+        assert_eq!(
+            plan.iter().map(|write| write.name).collect::<Vec<_>>(),
+            vec!["PK", "KEK"]
+        );
+
+        let kek = SignatureDatabase::from_bytes(&plan[1].contents).unwrap();
+        assert_eq!(kek.entries.len(), 2);
+        assert_eq!(kek.entries[0].elements[0].owner, owner);
+        assert_eq!(kek.entries[0].elements[0].data, keks[0]);
+
+        let update = EfiAuthVariable2Ref::from_bytes(&plan[0].payload).unwrap();
+        assert_eq!(update.time, timestamp());
+        assert!(SignatureDatabaseRef::from_bytes(update.data).is_ok());
+    }
+
+    #[test]
+    fn plan_ownership_should_reject_bad_inputs() {
+        let owner = crate::name_db::MICROSOFT_SIGNATURE_OWNER_GUID;
+        let mut signer = MockSigner::new(auth_files::TEST_KEK_CERT);
+        assert_eq!(
+            plan_ownership(
+                auth_files::TEST_KEK_CERT,
+                &[],
+                &owner,
+                &timestamp(),
+                &mut signer
+            )
+            .err(),
+            Some(OwnershipError::NoKekCertificates)
+        );
+        assert!(matches!(
+            plan_ownership(
+                &[0x30, 0x00],
+                &[microsoft_kek()],
+                &owner,
+                &timestamp(),
+                &mut signer
+            ),
+            Err(OwnershipError::BadCertificate(_))
+        ));
+        // The mock's signature is junk, so pre-flight catches it before firmware would.
+        assert_eq!(
+            plan_ownership(
+                auth_files::TEST_KEK_CERT,
+                &[microsoft_kek()],
+                &owner,
+                &timestamp(),
+                &mut signer
+            )
+            .err(),
+            Some(OwnershipError::Preflight(
+                "PK",
+                PreflightError::Signature(VerifyError::BadSignature)
+            ))
+        );
+    }
+}
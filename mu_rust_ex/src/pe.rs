@@ -0,0 +1,820 @@
+/* @file
+PE/COFF image parser, for EFI applications and drivers and the EFI images
+inside PCI option ROMs.
+
+Only the headers are decoded: DOS and PE headers, the optional header (PE32
+or PE32+), its data directories and the section table. Every field read is
+bounds-checked and every offset is validated against the file size, so a
+malformed image is an error rather than a panic. Headers can be parsed from
+the start of a file without reading the rest of it.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+
+use r_efi::efi;
+
+use crate::util::{read_u16_le, read_u32_le, read_u64_le};
+
+pub const DOS_SIGNATURE: u16 = 0x5A4D; // "MZ"
+pub const PE_SIGNATURE: u32 = 0x0000_4550; // "PE\0\0"
+pub const OPTIONAL_HEADER_MAGIC_PE32: u16 = 0x010B;
+pub const OPTIONAL_HEADER_MAGIC_PE32_PLUS: u16 = 0x020B;
+
+pub const DIRECTORY_ENTRY_EXPORT: usize = 0;
+pub const DIRECTORY_ENTRY_IMPORT: usize = 1;
+pub const DIRECTORY_ENTRY_RESOURCE: usize = 2;
+pub const DIRECTORY_ENTRY_EXCEPTION: usize = 3;
+// Unlike the others, its address is a file offset rather than an RVA.
+pub const DIRECTORY_ENTRY_SECURITY: usize = 4;
+pub const DIRECTORY_ENTRY_BASERELOC: usize = 5;
+pub const DIRECTORY_ENTRY_DEBUG: usize = 6;
+pub const MAX_DATA_DIRECTORIES: usize = 16;
+
+// The PE spec's limit; also keeps a corrupt count from allocating much.
+pub const MAX_SECTIONS: usize = 96;
+
+const DOS_LFANEW_OFFSET: usize = 0x3C;
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const DATA_DIRECTORY_SIZE: usize = 8;
+// Offsets within the optional header.
+const CHECKSUM_OFFSET: usize = 64;
+const PE32_DATA_DIRECTORIES_OFFSET: usize = 96;
+const PE32_PLUS_DATA_DIRECTORIES_OFFSET: usize = 112;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeError {
+    // (offset) Ran out of data while reading the header field at offset.
+    Truncated(usize),
+    BadDosSignature,
+    BadPeSignature,
+    // (magic) Neither PE32 nor PE32+.
+    UnknownOptionalHeader(u16),
+    // A header field contradicts another, or the file size.
+    BadHeader,
+    // (index) The section's raw data runs past the end of the file.
+    SectionOutOfBounds(usize),
+    // The certificate table runs past the end of the file.
+    BadSecurityDirectory,
+}
+impl fmt::Display for PeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(offset) => write!(f, "PE image truncated at 0x{:X}", offset),
+            Self::BadDosSignature => write!(f, "No MZ signature"),
+            Self::BadPeSignature => write!(f, "No PE signature"),
+            Self::UnknownOptionalHeader(magic) => {
+                write!(f, "Unknown optional header magic 0x{:04X}", magic)
+            }
+            Self::BadHeader => write!(f, "Inconsistent PE headers"),
+            Self::SectionOutOfBounds(index) => {
+                write!(f, "Section {} runs past the end of the image", index)
+            }
+            Self::BadSecurityDirectory => {
+                write!(f, "Certificate table runs past the end of the image")
+            }
+        }
+    }
+}
+impl From<PeError> for efi::Status {
+    fn from(f: PeError) -> Self {
+        match f {
+            PeError::Truncated(_) => efi::Status::BAD_BUFFER_SIZE,
+            PeError::BadDosSignature => efi::Status::UNSUPPORTED,
+            PeError::BadPeSignature => efi::Status::UNSUPPORTED,
+            PeError::UnknownOptionalHeader(_) => efi::Status::UNSUPPORTED,
+            PeError::BadHeader => efi::Status::LOAD_ERROR,
+            PeError::SectionOutOfBounds(_) => efi::Status::LOAD_ERROR,
+            PeError::BadSecurityDirectory => efi::Status::LOAD_ERROR,
+        }
+    }
+}
+
+type PeResult<T> = Result<T, PeError>;
+
+fn u16_at(buffer: &[u8], offset: usize) -> PeResult<u16> {
+    read_u16_le(buffer, offset).ok_or(PeError::Truncated(offset))
+}
+fn u32_at(buffer: &[u8], offset: usize) -> PeResult<u32> {
+    read_u32_le(buffer, offset).ok_or(PeError::Truncated(offset))
+}
+fn u64_at(buffer: &[u8], offset: usize) -> PeResult<u64> {
+    read_u64_le(buffer, offset).ok_or(PeError::Truncated(offset))
+}
+// `offset + length`, as long as it stays within `limit`.
+fn checked_end(offset: usize, length: usize, limit: usize) -> Option<usize> {
+    offset.checked_add(length).filter(|end| *end <= limit)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Machine {
+    I386,
+    X64,
+    Ia64,
+    Ebc,
+    Arm,
+    ArmThumb2,
+    Aarch64,
+    RiscV64,
+    LoongArch64,
+    Unknown(u16),
+}
+impl From<u16> for Machine {
+    fn from(f: u16) -> Self {
+        match f {
+            0x014C => Self::I386,
+            0x8664 => Self::X64,
+            0x0200 => Self::Ia64,
+            0x0EBC => Self::Ebc,
+            0x01C0 => Self::Arm,
+            0x01C4 => Self::ArmThumb2,
+            0xAA64 => Self::Aarch64,
+            0x5064 => Self::RiscV64,
+            0x6264 => Self::LoongArch64,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subsystem {
+    Native,
+    WindowsGui,
+    WindowsCui,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+    EfiRom,
+    Unknown(u16),
+}
+impl From<u16> for Subsystem {
+    fn from(f: u16) -> Self {
+        match f {
+            1 => Self::Native,
+            2 => Self::WindowsGui,
+            3 => Self::WindowsCui,
+            10 => Self::EfiApplication,
+            11 => Self::EfiBootServiceDriver,
+            12 => Self::EfiRuntimeDriver,
+            13 => Self::EfiRom,
+            other => Self::Unknown(other),
+        }
+    }
+}
+impl Subsystem {
+    pub fn is_efi(&self) -> bool {
+        matches!(
+            self,
+            Self::EfiApplication
+                | Self::EfiBootServiceDriver
+                | Self::EfiRuntimeDriver
+                | Self::EfiRom
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionalHeaderKind {
+    Pe32,
+    Pe32Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Section {
+    pub name: [u8; 8],
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub characteristics: u32,
+}
+
+impl Section {
+    // Names are NUL-padded ASCII; anything else is shown lossily.
+    pub fn name(&self) -> String {
+        let end = self.name.iter().position(|b| *b == 0).unwrap_or(8);
+        String::from_utf8_lossy(&self.name[..end]).into_owned()
+    }
+}
+
+impl fmt::Debug for Section {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "{:<8} RVA 0x{:08X} (0x{:X}), file 0x{:08X} (0x{:X}), flags 0x{:08X}",
+            self.name(),
+            self.virtual_address,
+            self.virtual_size,
+            self.pointer_to_raw_data,
+            self.size_of_raw_data,
+            self.characteristics
+        ))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct PeImage {
+    // Size of the whole file, which every offset has been checked against.
+    pub file_size: usize,
+    // e_lfanew: where the PE signature is.
+    pub pe_header_offset: usize,
+    pub machine: Machine,
+    pub time_date_stamp: u32,
+    pub characteristics: u16,
+    pub kind: OptionalHeaderKind,
+    pub optional_header_offset: usize,
+    pub address_of_entry_point: u32,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub checksum: u32,
+    pub subsystem: Subsystem,
+    pub dll_characteristics: u16,
+    pub data_directories: Vec<DataDirectory>,
+    pub section_table_offset: usize,
+    pub sections: Vec<Section>,
+}
+
+impl PeImage {
+    pub fn from_bytes(buffer: &[u8]) -> PeResult<Self> {
+        Self::parse_headers(buffer, buffer.len())
+    }
+
+    // Parses the headers at the start of a file that's `file_size` bytes
+    // long. `headers` needs to hold at least the section table; the rest
+    // of the file isn't looked at, only bounds-checked against `file_size`.
+    pub fn parse_headers(headers: &[u8], file_size: usize) -> PeResult<Self> {
+        if headers.len() > file_size {
+            return Err(PeError::BadHeader);
+        }
+        if u16_at(headers, 0)? != DOS_SIGNATURE {
+            return Err(PeError::BadDosSignature);
+        }
+        let pe_header_offset = u32_at(headers, DOS_LFANEW_OFFSET)? as usize;
+        if u32_at(headers, pe_header_offset)? != PE_SIGNATURE {
+            return Err(PeError::BadPeSignature);
+        }
+
+        let coff = pe_header_offset + 4;
+        let machine = Machine::from(u16_at(headers, coff)?);
+        let number_of_sections = u16_at(headers, coff + 2)? as usize;
+        let time_date_stamp = u32_at(headers, coff + 4)?;
+        let size_of_optional_header = u16_at(headers, coff + 16)? as usize;
+        let characteristics = u16_at(headers, coff + 18)?;
+        if number_of_sections > MAX_SECTIONS {
+            return Err(PeError::BadHeader);
+        }
+
+        let optional = coff + COFF_HEADER_SIZE;
+        let (kind, image_base, directory_count_offset) = match u16_at(headers, optional)? {
+            OPTIONAL_HEADER_MAGIC_PE32 => (
+                OptionalHeaderKind::Pe32,
+                u32_at(headers, optional + 28)? as u64,
+                PE32_DATA_DIRECTORIES_OFFSET - 4,
+            ),
+            OPTIONAL_HEADER_MAGIC_PE32_PLUS => (
+                OptionalHeaderKind::Pe32Plus,
+                u64_at(headers, optional + 24)?,
+                PE32_PLUS_DATA_DIRECTORIES_OFFSET - 4,
+            ),
+            magic => return Err(PeError::UnknownOptionalHeader(magic)),
+        };
+
+        // The directories have to fit in the optional header as declared.
+        let directory_count = u32_at(headers, optional + directory_count_offset)? as usize;
+        let directories_offset = directory_count_offset + 4;
+        if directory_count > MAX_DATA_DIRECTORIES
+            || directories_offset + directory_count * DATA_DIRECTORY_SIZE > size_of_optional_header
+        {
+            return Err(PeError::BadHeader);
+        }
+        let mut data_directories = Vec::with_capacity(directory_count);
+        for index in 0..directory_count {
+            let offset = optional + directories_offset + index * DATA_DIRECTORY_SIZE;
+            data_directories.push(DataDirectory {
+                virtual_address: u32_at(headers, offset)?,
+                size: u32_at(headers, offset + 4)?,
+            });
+        }
+
+        let section_table_offset = optional + size_of_optional_header;
+        let mut sections = Vec::with_capacity(number_of_sections);
+        for index in 0..number_of_sections {
+            let offset = section_table_offset + index * SECTION_HEADER_SIZE;
+            let raw = headers
+                .get(offset..offset + SECTION_HEADER_SIZE)
+                .ok_or(PeError::Truncated(offset))?;
+            let section = Section {
+                name: raw[..8].try_into().unwrap(),
+                virtual_size: u32_at(raw, 8)?,
+                virtual_address: u32_at(raw, 12)?,
+                size_of_raw_data: u32_at(raw, 16)?,
+                pointer_to_raw_data: u32_at(raw, 20)?,
+                characteristics: u32_at(raw, 36)?,
+            };
+            if section.size_of_raw_data != 0
+                && checked_end(
+                    section.pointer_to_raw_data as usize,
+                    section.size_of_raw_data as usize,
+                    file_size,
+                )
+                .is_none()
+            {
+                return Err(PeError::SectionOutOfBounds(index));
+            }
+            sections.push(section);
+        }
+
+        let image = Self {
+            file_size,
+            pe_header_offset,
+            machine,
+            time_date_stamp,
+            characteristics,
+            kind,
+            optional_header_offset: optional,
+            address_of_entry_point: u32_at(headers, optional + 16)?,
+            image_base,
+            section_alignment: u32_at(headers, optional + 32)?,
+            file_alignment: u32_at(headers, optional + 36)?,
+            size_of_image: u32_at(headers, optional + 56)?,
+            size_of_headers: u32_at(headers, optional + 60)?,
+            checksum: u32_at(headers, optional + CHECKSUM_OFFSET)?,
+            subsystem: Subsystem::from(u16_at(headers, optional + 68)?),
+            dll_characteristics: u16_at(headers, optional + 70)?,
+            data_directories,
+            section_table_offset,
+            sections,
+        };
+
+        if (image.size_of_headers as usize) < image.section_table_end()
+            || image.size_of_headers as usize > file_size
+        {
+            return Err(PeError::BadHeader);
+        }
+        if let Some(security) = image.security_directory() {
+            // The table comes after everything that's hashed, so it can't
+            // overlap the headers.
+            match checked_end(
+                security.virtual_address as usize,
+                security.size as usize,
+                file_size,
+            ) {
+                Some(_) if security.virtual_address >= image.size_of_headers => (),
+                _ => return Err(PeError::BadSecurityDirectory),
+            }
+        }
+        Ok(image)
+    }
+
+    pub fn data_directory(&self, index: usize) -> Option<DataDirectory> {
+        self.data_directories.get(index).copied()
+    }
+
+    // The certificate table, if the image has one.
+    pub fn security_directory(&self) -> Option<DataDirectory> {
+        self.data_directory(DIRECTORY_ENTRY_SECURITY)
+            .filter(|directory| directory.size != 0)
+    }
+
+    // File offset of the optional header's CheckSum field.
+    pub fn checksum_offset(&self) -> usize {
+        self.optional_header_offset + CHECKSUM_OFFSET
+    }
+
+    // File offset of the security data directory entry, if there's room
+    // for one.
+    pub fn security_directory_entry_offset(&self) -> Option<usize> {
+        if self.data_directories.len() <= DIRECTORY_ENTRY_SECURITY {
+            return None;
+        }
+        let directories = match self.kind {
+            OptionalHeaderKind::Pe32 => PE32_DATA_DIRECTORIES_OFFSET,
+            OptionalHeaderKind::Pe32Plus => PE32_PLUS_DATA_DIRECTORIES_OFFSET,
+        };
+        Some(
+            self.optional_header_offset
+                + directories
+                + DIRECTORY_ENTRY_SECURITY * DATA_DIRECTORY_SIZE,
+        )
+    }
+
+    pub fn section_table_end(&self) -> usize {
+        self.section_table_offset + self.sections.len() * SECTION_HEADER_SIZE
+    }
+
+    // Raw data of a section, from a buffer holding the whole file.
+    pub fn section_data<'a>(&self, buffer: &'a [u8], section: &Section) -> Option<&'a [u8]> {
+        let start = section.pointer_to_raw_data as usize;
+        buffer.get(start..start.checked_add(section.size_of_raw_data as usize)?)
+    }
+}
+
+impl fmt::Debug for PeImage {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("PeImage:\n")?;
+            fmtr.write_fmt(format_args!(
+                "\tFormat: {:?}, {:?}, {:?}\n",
+                self.kind, self.machine, self.subsystem
+            ))?;
+            fmtr.write_fmt(format_args!("\tFile Size: 0x{:X}\n", self.file_size))?;
+            fmtr.write_fmt(format_args!(
+                "\tEntry Point: 0x{:08X}, Image Base: 0x{:X}, Image Size: 0x{:X}\n",
+                self.address_of_entry_point, self.image_base, self.size_of_image
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tHeaders: 0x{:X}, Checksum: 0x{:08X}, Characteristics: 0x{:04X}/0x{:04X}\n",
+                self.size_of_headers, self.checksum, self.characteristics, self.dll_characteristics
+            ))?;
+            fmtr.write_str("\tData Directories:\n")?;
+            for (index, directory) in self.data_directories.iter().enumerate() {
+                if directory.size != 0 {
+                    fmtr.write_fmt(format_args!(
+                        "\t\t{:>2}: 0x{:08X} (0x{:X})\n",
+                        index, directory.virtual_address, directory.size
+                    ))?;
+                }
+            }
+            fmtr.write_str("\tSections:\n")?;
+            for section in self.sections.iter() {
+                fmtr.write_fmt(format_args!("\t\t{:?}\n", section))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "PeImage {{ {:?} {:?} {:?}, {} sections, {} }}",
+                self.kind,
+                self.machine,
+                self.subsystem,
+                self.sections.len(),
+                if self.security_directory().is_some() {
+                    "signed"
+                } else {
+                    "unsigned"
+                }
+            ))
+        }
+    }
+}
+
+// PCI expansion ROM images, as laid out in the PCI Firmware spec with the
+// UEFI spec's additions for EFI images.
+pub const OPTION_ROM_SIGNATURE: u16 = 0xAA55;
+pub const EFI_OPTION_ROM_SIGNATURE: u32 = 0x0EF1;
+pub const PCI_DATA_STRUCTURE_SIGNATURE: u32 = 0x5249_4350; // "PCIR"
+pub const CODE_TYPE_EFI: u8 = 0x03;
+pub const EFI_COMPRESSION_TYPE_NONE: u16 = 0;
+
+const OPTION_ROM_BLOCK_SIZE: usize = 512;
+// Offsets within the ROM header.
+const ROM_INITIALIZATION_SIZE_OFFSET: usize = 0x02;
+const ROM_EFI_SIGNATURE_OFFSET: usize = 0x04;
+const ROM_EFI_SUBSYSTEM_OFFSET: usize = 0x08;
+const ROM_EFI_MACHINE_OFFSET: usize = 0x0A;
+const ROM_COMPRESSION_TYPE_OFFSET: usize = 0x0C;
+const ROM_EFI_IMAGE_OFFSET: usize = 0x16;
+const ROM_PCIR_OFFSET: usize = 0x18;
+// Offsets within the PCI data structure.
+const PCIR_VENDOR_OFFSET: usize = 0x04;
+const PCIR_DEVICE_OFFSET: usize = 0x06;
+const PCIR_IMAGE_LENGTH_OFFSET: usize = 0x10;
+const PCIR_CODE_TYPE_OFFSET: usize = 0x14;
+const PCIR_INDICATOR_OFFSET: usize = 0x15;
+const PCIR_LAST_IMAGE: u8 = 0x80;
+
+// One image from a PCI option ROM. Legacy and other non-EFI images are
+// listed too, but only EFI images have a PE inside.
+#[derive(Clone, Copy, PartialEq)]
+pub struct OptionRomImage {
+    // Offset of this image within the ROM.
+    pub offset: usize,
+    pub length: usize,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub code_type: u8,
+    // EFI images only: (subsystem, machine, compression type).
+    pub efi: Option<(Subsystem, Machine, u16)>,
+    // EFI images only: offset of the PE image, relative to this image.
+    pub efi_image_offset: usize,
+    // EFI images only: where the PE image ends, relative to this image.
+    // Anything between here and `length` is padding.
+    pub initialization_size: usize,
+}
+
+impl OptionRomImage {
+    // The PE image bytes, if this is an uncompressed EFI image. Compressed
+    // ones would have to be decompressed first.
+    pub fn efi_image<'a>(&self, rom: &'a [u8]) -> Option<&'a [u8]> {
+        match self.efi {
+            Some((_, _, EFI_COMPRESSION_TYPE_NONE)) => {
+                rom.get(self.offset + self.efi_image_offset..self.offset + self.initialization_size)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for OptionRomImage {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "0x{:X} (0x{:X}) {:04X}:{:04X} ",
+            self.offset, self.length, self.vendor_id, self.device_id
+        ))?;
+        match self.efi {
+            Some((subsystem, machine, compression)) => fmtr.write_fmt(format_args!(
+                "EFI {:?} {:?}{}",
+                subsystem,
+                machine,
+                if compression == EFI_COMPRESSION_TYPE_NONE {
+                    ""
+                } else {
+                    ", compressed"
+                }
+            )),
+            None => fmtr.write_fmt(format_args!("code type 0x{:02X}", self.code_type)),
+        }
+    }
+}
+
+// Walks the images in a PCI option ROM, following each PCI data
+// structure's length until one is marked as the last.
+pub fn parse_option_rom(rom: &[u8]) -> PeResult<Vec<OptionRomImage>> {
+    let mut images = Vec::new();
+    let mut offset = 0;
+    loop {
+        let image = rom.get(offset..).ok_or(PeError::Truncated(offset))?;
+        if u16_at(image, 0)? != OPTION_ROM_SIGNATURE {
+            return Err(PeError::BadDosSignature);
+        }
+        let pcir = u16_at(image, ROM_PCIR_OFFSET)? as usize;
+        if u32_at(image, pcir)? != PCI_DATA_STRUCTURE_SIGNATURE {
+            return Err(PeError::BadHeader);
+        }
+        let length =
+            u16_at(image, pcir + PCIR_IMAGE_LENGTH_OFFSET)? as usize * OPTION_ROM_BLOCK_SIZE;
+        let code_type = *image
+            .get(pcir + PCIR_CODE_TYPE_OFFSET)
+            .ok_or(PeError::Truncated(offset + pcir + PCIR_CODE_TYPE_OFFSET))?;
+        let indicator = *image
+            .get(pcir + PCIR_INDICATOR_OFFSET)
+            .ok_or(PeError::Truncated(offset + pcir + PCIR_INDICATOR_OFFSET))?;
+        if length == 0 || length > image.len() {
+            return Err(PeError::BadHeader);
+        }
+
+        let is_efi = code_type == CODE_TYPE_EFI
+            && u32_at(image, ROM_EFI_SIGNATURE_OFFSET)? == EFI_OPTION_ROM_SIGNATURE;
+        let (efi, efi_image_offset, initialization_size) = if is_efi {
+            let efi_image_offset = u16_at(image, ROM_EFI_IMAGE_OFFSET)? as usize;
+            // InitializationSize covers the EFI image, and can't exceed the ROM image.
+            let initialization_size =
+                u16_at(image, ROM_INITIALIZATION_SIZE_OFFSET)? as usize * OPTION_ROM_BLOCK_SIZE;
+            if efi_image_offset >= initialization_size || initialization_size > length {
+                return Err(PeError::BadHeader);
+            }
+            (
+                Some((
+                    Subsystem::from(u16_at(image, ROM_EFI_SUBSYSTEM_OFFSET)?),
+                    Machine::from(u16_at(image, ROM_EFI_MACHINE_OFFSET)?),
+                    u16_at(image, ROM_COMPRESSION_TYPE_OFFSET)?,
+                )),
+                efi_image_offset,
+                initialization_size,
+            )
+        } else {
+            (None, 0, 0)
+        };
+
+        images.push(OptionRomImage {
+            offset,
+            length,
+            vendor_id: u16_at(image, pcir + PCIR_VENDOR_OFFSET)?,
+            device_id: u16_at(image, pcir + PCIR_DEVICE_OFFSET)?,
+            code_type,
+            efi,
+            efi_image_offset,
+            initialization_size,
+        });
+        if indicator & PCIR_LAST_IMAGE != 0 {
+            return Ok(images);
+        }
+        offset += length;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec;
+
+    // A minimal PE32+ EFI application: headers, one .text section and a
+    // certificate table of `certificate_size` bytes at the end.
+    pub(crate) fn build_image(certificate_size: usize) -> Vec<u8> {
+        let mut image = vec![0u8; 0x400];
+        image[0..2].copy_from_slice(&DOS_SIGNATURE.to_le_bytes());
+        image[DOS_LFANEW_OFFSET..DOS_LFANEW_OFFSET + 4].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(&PE_SIGNATURE.to_le_bytes());
+
+        let put16 = |image: &mut Vec<u8>, offset: usize, value: u16| {
+            image[offset..offset + 2].copy_from_slice(&value.to_le_bytes())
+        };
+        let put32 = |image: &mut Vec<u8>, offset: usize, value: u32| {
+            image[offset..offset + 4].copy_from_slice(&value.to_le_bytes())
+        };
+        let coff = 0x84;
+        put16(&mut image, coff, 0x8664);
+        put16(&mut image, coff + 2, 1);
+        put16(&mut image, coff + 16, 0xF0);
+        put16(&mut image, coff + 18, 0x0022);
+
+        let optional = coff + COFF_HEADER_SIZE;
+        put16(&mut image, optional, OPTIONAL_HEADER_MAGIC_PE32_PLUS);
+        put32(&mut image, optional + 16, 0x1000);
+        put32(&mut image, optional + 32, 0x1000);
+        put32(&mut image, optional + 36, 0x200);
+        put32(&mut image, optional + 56, 0x2000);
+        put32(&mut image, optional + 60, 0x200);
+        put32(&mut image, optional + CHECKSUM_OFFSET, 0x1234_5678);
+        put16(&mut image, optional + 68, 10);
+        put32(&mut image, optional + 108, 16);
+
+        let section = optional + 0xF0;
+        image[section..section + 5].copy_from_slice(b".text");
+        put32(&mut image, section + 8, 0x180);
+        put32(&mut image, section + 12, 0x1000);
+        put32(&mut image, section + 16, 0x200);
+        put32(&mut image, section + 20, 0x200);
+        put32(&mut image, section + 36, 0x6000_0020);
+        for (index, byte) in image[0x200..0x400].iter_mut().enumerate() {
+            *byte = index as u8;
+        }
+
+        if certificate_size != 0 {
+            let security = optional + PE32_PLUS_DATA_DIRECTORIES_OFFSET + 4 * 8;
+            put32(&mut image, security, 0x400);
+            put32(&mut image, security + 4, certificate_size as u32);
+            image.resize(0x400 + certificate_size, 0xCC);
+        }
+        image
+    }
+
+    #[test]
+    fn pe_image_should_parse_headers() {
+        let buffer = build_image(0x10);
+        let image = PeImage::from_bytes(&buffer).unwrap();
+        assert_eq!(image.kind, OptionalHeaderKind::Pe32Plus);
+        assert_eq!(image.machine, Machine::X64);
+        assert_eq!(image.subsystem, Subsystem::EfiApplication);
+        assert_eq!(image.checksum, 0x1234_5678);
+        assert_eq!(image.data_directories.len(), 16);
+        assert_eq!(
+            image.security_directory(),
+            Some(DataDirectory {
+                virtual_address: 0x400,
+                size: 0x10
+            })
+        );
+        assert_eq!(image.sections.len(), 1);
+        assert_eq!(image.sections[0].name(), ".text");
+        assert_eq!(
+            image.section_data(&buffer, &image.sections[0]).unwrap()[1],
+            1
+        );
+
+        // Parsing from just the headers gives the same result.
+        let headers = PeImage::parse_headers(&buffer[..0x200], buffer.len()).unwrap();
+        assert_eq!(headers, image);
+        assert_eq!(
+            read_u32_le(&buffer, image.checksum_offset()),
+            Some(0x1234_5678)
+        );
+        assert_eq!(
+            read_u32_le(&buffer, image.security_directory_entry_offset().unwrap()),
+            Some(0x400)
+        );
+    }
+
+    #[test]
+    fn pe_image_should_reject_malformed_images() {
+        let buffer = build_image(0x10);
+        // Every truncation either fails cleanly or, once the headers are
+        // complete, trips over the section or certificate bounds.
+        for length in 0..buffer.len() {
+            assert!(PeImage::from_bytes(&buffer[..length]).is_err());
+        }
+
+        let mut bad = buffer.clone();
+        bad[0] = b'Z';
+        assert_eq!(PeImage::from_bytes(&bad), Err(PeError::BadDosSignature));
+
+        let mut bad = buffer.clone();
+        bad[DOS_LFANEW_OFFSET..DOS_LFANEW_OFFSET + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            PeImage::from_bytes(&bad),
+            Err(PeError::Truncated(u32::MAX as usize))
+        );
+
+        let mut bad = buffer.clone();
+        bad[0x98] = 0x07;
+        assert_eq!(
+            PeImage::from_bytes(&bad),
+            Err(PeError::UnknownOptionalHeader(0x0207))
+        );
+
+        // Section raw data pointing past the end of the file.
+        let mut bad = buffer.clone();
+        let section = 0x98 + 0xF0;
+        bad[section + 20..section + 24].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+        assert_eq!(
+            PeImage::from_bytes(&bad),
+            Err(PeError::SectionOutOfBounds(0))
+        );
+
+        // A certificate table that's bigger than the file.
+        let mut bad = buffer;
+        let security = 0x98 + PE32_PLUS_DATA_DIRECTORIES_OFFSET + 4 * 8;
+        bad[security + 4..security + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            PeImage::from_bytes(&bad),
+            Err(PeError::BadSecurityDirectory)
+        );
+    }
+
+    // A single-image EFI option ROM: a one-block header, `pe`, then
+    // `padding` blocks past the end of the PE image.
+    fn build_option_rom(pe: &[u8], padding: u16) -> Vec<u8> {
+        let initialization_blocks = (0x200 + pe.len()).div_ceil(0x200) as u16;
+        let mut rom = vec![0u8; 0x200];
+        rom[0..2].copy_from_slice(&OPTION_ROM_SIGNATURE.to_le_bytes());
+        rom[ROM_INITIALIZATION_SIZE_OFFSET..ROM_INITIALIZATION_SIZE_OFFSET + 2]
+            .copy_from_slice(&initialization_blocks.to_le_bytes());
+        rom[ROM_EFI_SIGNATURE_OFFSET..ROM_EFI_SIGNATURE_OFFSET + 4]
+            .copy_from_slice(&EFI_OPTION_ROM_SIGNATURE.to_le_bytes());
+        rom[ROM_EFI_SUBSYSTEM_OFFSET..ROM_EFI_SUBSYSTEM_OFFSET + 2]
+            .copy_from_slice(&11u16.to_le_bytes());
+        rom[ROM_EFI_MACHINE_OFFSET..ROM_EFI_MACHINE_OFFSET + 2]
+            .copy_from_slice(&0x8664u16.to_le_bytes());
+        rom[ROM_EFI_IMAGE_OFFSET..ROM_EFI_IMAGE_OFFSET + 2]
+            .copy_from_slice(&0x200u16.to_le_bytes());
+        rom[ROM_PCIR_OFFSET..ROM_PCIR_OFFSET + 2].copy_from_slice(&0x1Cu16.to_le_bytes());
+        rom[0x1C..0x20].copy_from_slice(&PCI_DATA_STRUCTURE_SIGNATURE.to_le_bytes());
+        rom[0x1C + PCIR_VENDOR_OFFSET..0x1C + PCIR_VENDOR_OFFSET + 2]
+            .copy_from_slice(&0x8086u16.to_le_bytes());
+        rom[0x1C + PCIR_IMAGE_LENGTH_OFFSET..0x1C + PCIR_IMAGE_LENGTH_OFFSET + 2]
+            .copy_from_slice(&(initialization_blocks + padding).to_le_bytes());
+        rom[0x1C + PCIR_CODE_TYPE_OFFSET] = CODE_TYPE_EFI;
+        rom[0x1C + PCIR_INDICATOR_OFFSET] = PCIR_LAST_IMAGE;
+        rom.extend_from_slice(pe);
+        rom.resize((initialization_blocks + padding) as usize * 0x200, 0xFF);
+        rom
+    }
+
+    #[test]
+    fn parse_option_rom_should_find_efi_images() {
+        let pe = build_image(0);
+        let mut rom = build_option_rom(&pe, 0);
+
+        let images = parse_option_rom(&rom).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            images[0].efi,
+            Some((Subsystem::EfiBootServiceDriver, Machine::X64, 0))
+        );
+        let efi = images[0].efi_image(&rom).unwrap();
+        assert_eq!(PeImage::from_bytes(efi).unwrap().sections.len(), 1);
+
+        // An image length running past the end of the ROM.
+        rom.truncate(0x400);
+        assert_eq!(parse_option_rom(&rom), Err(PeError::BadHeader));
+    }
+
+    #[test]
+    fn option_rom_efi_image_should_stop_at_initialization_size() {
+        let pe = build_image(0x10);
+        let rom = build_option_rom(&pe, 2);
+
+        let images = parse_option_rom(&rom).unwrap();
+        assert_eq!(images[0].length, rom.len());
+        let efi = images[0].efi_image(&rom).unwrap();
+        // The PE ends short of a block boundary, so only the fill up to
+        // InitializationSize comes along, not the padding blocks after it.
+        assert_eq!(efi.len(), pe.len().next_multiple_of(0x200));
+        assert_eq!(&efi[..pe.len()], &pe[..]);
+        assert_eq!(images[0].initialization_size, 0x200 + efi.len());
+    }
+}
@@ -0,0 +1,489 @@
+/* @file
+PKCS#7 (CMS) SignedData decoding, as used in EFI_VARIABLE_AUTHENTICATION_2
+descriptors and Authenticode signatures.
+
+Both the bare SignedData and the ContentInfo-wrapped form are accepted,
+since firmware and tools disagree on which one belongs in CertData.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::der::{self, tag_context, tag_context_primitive, DerObject, DerReader, DerResult, Oid};
+use crate::x509::{
+    compute_digest, signature_digest_algorithm, Certificate, DebugHex, Name, VerifyError,
+};
+
+pub const OID_PKCS7_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+pub const OID_PKCS7_SIGNED_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+pub const OID_CONTENT_TYPE: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x03];
+pub const OID_MESSAGE_DIGEST: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x04];
+pub const OID_SIGNING_TIME: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x05];
+pub const OID_COUNTER_SIGNATURE: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x06];
+pub const OID_TST_INFO: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignerIdentifier<'a> {
+    IssuerAndSerial { issuer: Name<'a>, serial: &'a [u8] },
+    SubjectKeyId(&'a [u8]),
+}
+
+#[derive(Clone, Copy)]
+pub struct SignerInfo<'a> {
+    pub version: u64,
+    pub identifier: SignerIdentifier<'a>,
+    pub digest_algorithm: Oid<'a>,
+    // The [0] IMPLICIT SET, exactly as encoded. The digest is taken over
+    // the same contents re-tagged as a SET (0x31).
+    pub authenticated_attributes: Option<DerObject<'a>>,
+    pub signature_algorithm: Oid<'a>,
+    pub signature: &'a [u8],
+    // The [1] IMPLICIT SET: countersignatures, timestamps and the like.
+    pub unauthenticated_attributes: Option<DerObject<'a>>,
+}
+
+impl<'a> SignerInfo<'a> {
+    pub(crate) fn from_der(object: DerObject<'a>) -> DerResult<Self> {
+        let mut fields = object.expect(der::TAG_SEQUENCE)?.reader();
+        let version = fields.read(der::TAG_INTEGER)?.as_small_integer()?;
+        let identifier = match fields.read_optional(tag_context_primitive(0))? {
+            Some(key_id) => SignerIdentifier::SubjectKeyId(key_id.contents),
+            None => {
+                let mut issuer_and_serial = fields.read(der::TAG_SEQUENCE)?.reader();
+                let issuer = Name::from_der(issuer_and_serial.read_any()?)?;
+                let serial = issuer_and_serial
+                    .read_last(der::TAG_INTEGER)?
+                    .as_unsigned_integer()?;
+                SignerIdentifier::IssuerAndSerial { issuer, serial }
+            }
+        };
+        let digest_algorithm = read_algorithm(&mut fields)?;
+        let authenticated_attributes = fields.read_optional(tag_context(0))?;
+        let signature_algorithm = read_algorithm(&mut fields)?;
+        let signature = fields.read(der::TAG_OCTET_STRING)?.contents;
+        let unauthenticated_attributes = fields.read_optional(tag_context(1))?;
+        fields.finish()?;
+
+        Ok(Self {
+            version,
+            identifier,
+            digest_algorithm,
+            authenticated_attributes,
+            signature_algorithm,
+            signature,
+            unauthenticated_attributes,
+        })
+    }
+
+    // Finds the value of a single-valued authenticated attribute.
+    pub fn authenticated_attribute(&self, oid: &[u8]) -> DerResult<Option<DerObject<'a>>> {
+        Ok(attribute_values(self.authenticated_attributes, oid)?
+            .into_iter()
+            .next())
+    }
+
+    // Every value of every unauthenticated attribute with this type.
+    pub fn unauthenticated_attribute_values(&self, oid: &[u8]) -> DerResult<Vec<DerObject<'a>>> {
+        attribute_values(self.unauthenticated_attributes, oid)
+    }
+
+    pub fn message_digest(&self) -> DerResult<Option<&'a [u8]>> {
+        Ok(self
+            .authenticated_attribute(OID_MESSAGE_DIGEST)?
+            .map(|digest| digest.contents))
+    }
+
+    pub fn matches(&self, certificate: &Certificate) -> bool {
+        match self.identifier {
+            SignerIdentifier::IssuerAndSerial { issuer, serial } => {
+                issuer.raw == certificate.issuer.raw && serial == certificate.serial
+            }
+            SignerIdentifier::SubjectKeyId(key_id) => {
+                certificate.subject_key_identifier() == Ok(Some(key_id))
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for SignerInfo<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("SignerInfo:\n")?;
+            match self.identifier {
+                SignerIdentifier::IssuerAndSerial { issuer, serial } => {
+                    fmtr.write_fmt(format_args!("\tIssuer: {}\n", issuer))?;
+                    fmtr.write_fmt(format_args!("\tSerial: {:?}\n", DebugHex(serial)))?;
+                }
+                SignerIdentifier::SubjectKeyId(key_id) => {
+                    fmtr.write_fmt(format_args!("\tSubject Key Id: {:?}\n", DebugHex(key_id)))?;
+                }
+            }
+            fmtr.write_fmt(format_args!(
+                "\tDigest Algorithm: {:?}\n",
+                self.digest_algorithm
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tSignature Algorithm: {:?}\n",
+                self.signature_algorithm
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tAuthenticated Attributes: {}\n",
+                self.authenticated_attributes.is_some()
+            ))?;
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "SignerInfo {{ Digest Algorithm: {:?} }}",
+                self.digest_algorithm
+            ))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SignedData<'a> {
+    pub version: u64,
+    pub digest_algorithms: Vec<Oid<'a>>,
+    pub content_type: Oid<'a>,
+    // None for detached signatures, which is what variable updates use.
+    pub content: Option<DerObject<'a>>,
+    pub certificates: Vec<Certificate<'a>>,
+    pub signers: Vec<SignerInfo<'a>>,
+}
+
+impl<'a> SignedData<'a> {
+    pub fn from_der(buffer: &'a [u8]) -> DerResult<Self> {
+        let object = DerReader::new(buffer).read_last(der::TAG_SEQUENCE)?;
+
+        // A ContentInfo starts with an OID; a bare SignedData with its version.
+        let mut probe = object.reader();
+        let signed_data = match probe.peek_tag() {
+            Some(der::TAG_OID) => {
+                let content_type = probe.read_any()?.as_oid()?;
+                if content_type.0 != OID_PKCS7_SIGNED_DATA {
+                    return Err(der::DerError::BadValue);
+                }
+                let mut wrapper = probe.read_last(tag_context(0))?.reader();
+                wrapper.read_last(der::TAG_SEQUENCE)?
+            }
+            _ => object,
+        };
+        Self::from_signed_data(signed_data)
+    }
+
+    fn from_signed_data(object: DerObject<'a>) -> DerResult<Self> {
+        let mut fields = object.reader();
+        let version = fields.read(der::TAG_INTEGER)?.as_small_integer()?;
+
+        let mut digest_algorithms = Vec::new();
+        let mut algorithms = fields.read(der::TAG_SET)?.reader();
+        while !algorithms.is_empty() {
+            digest_algorithms.push(read_algorithm(&mut algorithms)?);
+        }
+
+        let mut content_info = fields.read(der::TAG_SEQUENCE)?.reader();
+        let content_type = content_info.read_any()?.as_oid()?;
+        let content = match content_info.read_optional(tag_context(0))? {
+            Some(wrapper) => Some(wrapper.reader().read_any()?),
+            None => None,
+        };
+        content_info.finish()?;
+
+        let mut certificates = Vec::new();
+        if let Some(certificate_set) = fields.read_optional(tag_context(0))? {
+            let mut reader = certificate_set.reader();
+            while !reader.is_empty() {
+                // Only plain certificates are expected here; anything else
+                // (attribute certificates, etc.) is a context-tagged choice.
+                match reader.peek_tag() {
+                    Some(der::TAG_SEQUENCE) => {
+                        certificates.push(Certificate::from_der_object(reader.read_any()?)?)
+                    }
+                    _ => {
+                        reader.read_any()?;
+                    }
+                }
+            }
+        }
+        // CRLs aren't used by anything we deal with.
+        fields.read_optional(tag_context(1))?;
+
+        let mut signers = Vec::new();
+        let mut signer_infos = fields.read_last(der::TAG_SET)?.reader();
+        while !signer_infos.is_empty() {
+            signers.push(SignerInfo::from_der(signer_infos.read_any()?)?);
+        }
+
+        Ok(Self {
+            version,
+            digest_algorithms,
+            content_type,
+            content,
+            certificates,
+            signers,
+        })
+    }
+
+    // The embedded certificate that produced the given signature, if it was included.
+    pub fn signer_certificate(&self, signer: &SignerInfo) -> Option<&Certificate<'a>> {
+        self.certificates
+            .iter()
+            .find(|certificate| signer.matches(certificate))
+    }
+
+    // Checks `signer`'s signature over `content` (the detached data, or the
+    // embedded content's octets) and returns the certificate that made it.
+    // Whether that certificate should be trusted is up to the caller.
+    pub fn verify_signer(
+        &self,
+        signer: &SignerInfo,
+        content: &[u8],
+    ) -> Result<&Certificate<'a>, VerifyError> {
+        let certificate = self
+            .signer_certificate(signer)
+            .ok_or(VerifyError::MissingCertificate)?;
+        if signer.signature_algorithm.0 != der::OID_RSA_ENCRYPTION
+            && signature_digest_algorithm(signer.signature_algorithm.0).is_none()
+        {
+            return Err(VerifyError::UnsupportedAlgorithm);
+        }
+
+        let digest_algorithm = signer.digest_algorithm.0;
+        match signer.authenticated_attributes {
+            None => certificate
+                .public_key
+                .verify(digest_algorithm, content, signer.signature)?,
+            Some(attributes) => {
+                let digest = compute_digest(digest_algorithm, content)
+                    .ok_or(VerifyError::UnsupportedAlgorithm)?;
+                if signer.message_digest() != Ok(Some(&digest[..])) {
+                    return Err(VerifyError::DigestMismatch);
+                }
+                // The signature covers the attributes re-tagged as a SET OF.
+                let mut signed_attributes = Vec::from(attributes.raw);
+                signed_attributes[0] = der::TAG_SET;
+                certificate.public_key.verify(
+                    digest_algorithm,
+                    &signed_attributes,
+                    signer.signature,
+                )?
+            }
+        }
+        Ok(certificate)
+    }
+}
+
+impl<'a> fmt::Debug for SignedData<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("SignedData:\n")?;
+            fmtr.write_fmt(format_args!("\tContent Type: {:?}\n", self.content_type))?;
+            fmtr.write_fmt(format_args!("\tDetached: {}\n", self.content.is_none()))?;
+            fmtr.write_fmt(format_args!("\tSigners: {}\n", self.signers.len()))?;
+            for signer in self.signers.iter() {
+                fmtr.write_fmt(format_args!("{:#?}", signer))?;
+            }
+            fmtr.write_fmt(format_args!(
+                "\tCertificates: {}\n",
+                self.certificates.len()
+            ))?;
+            for certificate in self.certificates.iter() {
+                fmtr.write_fmt(format_args!("{:#?}", certificate))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "SignedData {{ Signers: {}, Certificates: {} }}",
+                self.signers.len(),
+                self.certificates.len()
+            ))
+        }
+    }
+}
+
+// Builds a detached, ContentInfo-wrapped SignedData with a single signer and
+// no authenticated attributes, which is the form firmware expects for
+// authenticated variables. `certificates` are embedded in the order given.
+pub fn encode_signed_data(
+    certificates: &[&[u8]],
+    signer: &Certificate,
+    digest_algorithm: &[u8],
+    signature_algorithm: &[u8],
+    signature: &[u8],
+) -> Vec<u8> {
+    let version = der::encode(der::TAG_INTEGER, &[0x01]);
+    let digest_algorithm = der::encode_algorithm(digest_algorithm);
+
+    let issuer_and_serial = der::encode_constructed(
+        der::TAG_SEQUENCE,
+        &[
+            signer.issuer.raw,
+            &der::encode_unsigned_integer(signer.serial),
+        ],
+    );
+    let signer_info = der::encode_constructed(
+        der::TAG_SEQUENCE,
+        &[
+            &version,
+            &issuer_and_serial,
+            &digest_algorithm,
+            &der::encode_algorithm(signature_algorithm),
+            &der::encode(der::TAG_OCTET_STRING, signature),
+        ],
+    );
+
+    let signed_data = der::encode_constructed(
+        der::TAG_SEQUENCE,
+        &[
+            &version,
+            &der::encode(der::TAG_SET, &digest_algorithm),
+            &der::encode_constructed(
+                der::TAG_SEQUENCE,
+                &[&der::encode(der::TAG_OID, OID_PKCS7_DATA)],
+            ),
+            &der::encode(tag_context(0), &certificates.concat()),
+            &der::encode(der::TAG_SET, &signer_info),
+        ],
+    );
+
+    der::encode_constructed(
+        der::TAG_SEQUENCE,
+        &[
+            &der::encode(der::TAG_OID, OID_PKCS7_SIGNED_DATA),
+            &der::encode(tag_context(0), &signed_data),
+        ],
+    )
+}
+
+// The values of the attributes of type `oid` in a SET OF Attribute.
+fn attribute_values<'a>(
+    attributes: Option<DerObject<'a>>,
+    oid: &[u8],
+) -> DerResult<Vec<DerObject<'a>>> {
+    let mut result = Vec::new();
+    let mut reader = match attributes {
+        Some(attributes) => attributes.reader(),
+        None => return Ok(result),
+    };
+    while !reader.is_empty() {
+        let mut attribute = reader.read(der::TAG_SEQUENCE)?.reader();
+        if attribute.read_any()?.as_oid()?.0 == oid {
+            let mut values = attribute.read_last(der::TAG_SET)?.reader();
+            while !values.is_empty() {
+                result.push(values.read_any()?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+// Reads an AlgorithmIdentifier and returns the OID. Parameters are ignored.
+fn read_algorithm<'a>(reader: &mut DerReader<'a>) -> DerResult<Oid<'a>> {
+    reader
+        .read(der::TAG_SEQUENCE)?
+        .reader()
+        .read_any()?
+        .as_oid()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_variable::EfiAuthVariable2Ref;
+    use crate::test_data::auth_files;
+
+    #[test]
+    fn pkcs7_should_decode_content_info_wrapped_signed_data() {
+        let auth = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let signed_data = SignedData::from_der(auth.signature).unwrap();
+        assert_eq!(signed_data.version, 1);
+        assert_eq!(signed_data.digest_algorithms.len(), 1);
+        assert_eq!(signed_data.digest_algorithms[0].0, der::OID_SHA256);
+        assert_eq!(signed_data.content_type.0, OID_PKCS7_DATA);
+        assert!(signed_data.content.is_none());
+        assert_eq!(signed_data.certificates.len(), 1);
+        assert_eq!(signed_data.signers.len(), 1);
+
+        let signer = &signed_data.signers[0];
+        assert_eq!(signer.signature.len(), 256);
+        assert_eq!(signer.message_digest(), Ok(None));
+        let certificate = signed_data.signer_certificate(signer).unwrap();
+        assert_eq!(certificate.subject.common_name().unwrap(), "Test KEK 2024");
+        assert_eq!(certificate.as_bytes(), auth_files::TEST_KEK_CERT);
+    }
+
+    #[test]
+    fn pkcs7_should_decode_bare_signed_data() {
+        let auth = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let mut wrapper = DerReader::new(auth.signature)
+            .read_last(der::TAG_SEQUENCE)
+            .unwrap()
+            .reader();
+        wrapper.read(der::TAG_OID).unwrap();
+        let bare = wrapper.read(tag_context(0)).unwrap().contents;
+
+        let signed_data = SignedData::from_der(bare).unwrap();
+        assert_eq!(signed_data.signers.len(), 1);
+        assert_eq!(signed_data.certificates.len(), 1);
+    }
+
+    #[test]
+    fn pkcs7_should_reject_other_content_types() {
+        let mut data = Vec::from(
+            EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND)
+                .unwrap()
+                .signature,
+        );
+        // Turn pkcs7-signedData into pkcs7-data.
+        data[14] = 0x01;
+        assert_eq!(
+            SignedData::from_der(&data).err(),
+            Some(der::DerError::BadValue)
+        );
+    }
+
+    #[test]
+    fn pkcs7_should_verify_signers() {
+        let auth = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let tbs = auth
+            .to_auth_variable(
+                "db",
+                &crate::auth_variable::EFI_IMAGE_SECURITY_DATABASE_GUID,
+                0x67,
+            )
+            .get_tbs_data()
+            .unwrap();
+        let signed_data = auth.signed_data().unwrap();
+        let certificate = signed_data
+            .verify_signer(&signed_data.signers[0], &tbs)
+            .unwrap();
+        assert_eq!(certificate.as_bytes(), auth_files::TEST_KEK_CERT);
+        assert_eq!(
+            signed_data
+                .verify_signer(&signed_data.signers[0], &tbs[1..])
+                .err(),
+            Some(VerifyError::BadSignature)
+        );
+
+        let signed_data = SignedData::from_der(auth_files::SIGNED_ATTRIBUTES_P7).unwrap();
+        assert!(signed_data.signers[0].authenticated_attributes.is_some());
+        assert!(signed_data
+            .verify_signer(
+                &signed_data.signers[0],
+                auth_files::SIGNED_ATTRIBUTES_CONTENT
+            )
+            .is_ok());
+        assert_eq!(
+            signed_data
+                .verify_signer(&signed_data.signers[0], b"Something else")
+                .err(),
+            Some(VerifyError::DigestMismatch)
+        );
+    }
+}
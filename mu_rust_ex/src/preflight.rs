@@ -0,0 +1,487 @@
+/* @file
+Offline pre-flight checks for authenticated Secure Boot variable updates.
+
+Firmware answers a bad update with nothing but SECURITY_VIOLATION. These
+checks follow what EDK2's AuthVariableLib does with an
+EFI_VARIABLE_AUTHENTICATION_2 payload, so the tool can say why an update
+would be rejected before calling SetVariable():
+
+- the TimeStamp is well-formed and later than the stored one (appends excepted)
+- the new contents are valid signature lists of types firmware knows
+- the PKCS#7 signature verifies over the TBS data
+- the signer chains to a certificate trusted for that variable: PK for PK
+  and KEK, KEK or PK for db/dbx/dbt/dbr
+
+In setup mode, a PK update only needs to be signed by the certificate it
+enrolls; KEK, db, dbx, dbt and dbr updates aren't checked at all. Like
+EDK2, certificate validity periods are ignored.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+
+use crate::auth_variable::*;
+use crate::der::DerError;
+use crate::image_authentication::{signature_data_size, SignatureDatabase, SignatureDatabaseError};
+use crate::rustified;
+use crate::util::DebugGuid;
+use crate::variable::{EfiVariable, EFI_GLOBAL_VARIABLE_GUID};
+use crate::x509::{Certificate, DebugTime, VerifyError};
+use crate::UefiResult;
+
+// Longest signer -> anchor path we'll follow through embedded intermediates.
+const MAX_CHAIN_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreflightError {
+    // The variable isn't PK, KEK, db, dbx, dbt or dbr.
+    UnknownVariable,
+    // The attributes don't include EFI_VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS.
+    NotTimeBased,
+    // TimeStamp has a nonzero Nanosecond, TimeZone or Daylight.
+    BadTimestamp,
+    // (stored) TimeStamp isn't later than the variable's current one.
+    StaleTimestamp(rustified::Time),
+    // (error) The new contents aren't valid signature lists.
+    BadPayload(SignatureDatabaseError),
+    // PK must hold exactly one X.509 certificate.
+    BadPlatformKey,
+    // (type) The list type isn't allowed in this variable.
+    UnsupportedSignatureType(efi::Guid),
+    // (error) CertData isn't a valid SignedData.
+    BadSignedData(DerError),
+    // (count) Firmware expects exactly one SignerInfo.
+    SignerCount(usize),
+    // (attributes) The signature only verifies with these attributes in the TBS data.
+    WrongAttributes(u32),
+    // (error) The signature itself didn't verify.
+    Signature(VerifyError),
+    // The signer doesn't chain to any certificate trusted for this variable.
+    Untrusted,
+    Efi(efi::Status),
+}
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownVariable => write!(f, "Not a Secure Boot key database"),
+            Self::NotTimeBased => write!(
+                f,
+                "Attributes lack EFI_VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS"
+            ),
+            Self::BadTimestamp => write!(
+                f,
+                "TimeStamp Nanosecond, TimeZone and Daylight must all be zero"
+            ),
+            Self::StaleTimestamp(stored) => write!(
+                f,
+                "TimeStamp isn't later than the stored one ({:?})",
+                DebugTime(stored)
+            ),
+            Self::BadPayload(err) => write!(f, "Invalid variable contents ({})", err),
+            Self::BadPlatformKey => write!(f, "PK must hold exactly one X.509 certificate"),
+            Self::UnsupportedSignatureType(guid) => write!(
+                f,
+                "Signature type {:?} isn't allowed in this variable",
+                DebugGuid::from(*guid)
+            ),
+            Self::BadSignedData(err) => write!(f, "Invalid PKCS#7 SignedData ({})", err),
+            Self::SignerCount(count) => write!(f, "Expected one signer, found {}", count),
+            Self::WrongAttributes(attributes) => write!(
+                f,
+                "Signed for attributes 0x{:X}; check the append/replace mode",
+                attributes
+            ),
+            Self::Signature(err) => write!(f, "{}", err),
+            Self::Untrusted => write!(
+                f,
+                "Signer doesn't chain to a certificate trusted for this variable"
+            ),
+            Self::Efi(status) => write!(f, "Pre-flight check failed ({:?})", status),
+        }
+    }
+}
+impl From<PreflightError> for efi::Status {
+    fn from(f: PreflightError) -> Self {
+        match f {
+            PreflightError::UnknownVariable => efi::Status::INVALID_PARAMETER,
+            PreflightError::NotTimeBased => efi::Status::INVALID_PARAMETER,
+            PreflightError::BadPayload(_) => efi::Status::INVALID_PARAMETER,
+            PreflightError::BadPlatformKey => efi::Status::INVALID_PARAMETER,
+            PreflightError::UnsupportedSignatureType(_) => efi::Status::INVALID_PARAMETER,
+            PreflightError::Efi(x) => x,
+            _ => efi::Status::SECURITY_VIOLATION,
+        }
+    }
+}
+
+// Who vouches for an update that passed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Authorization {
+    // (variable, certificate) The signer chains to this certificate in the named variable.
+    Trusted(&'static str, Vec<u8>),
+    // (certificate) Setup mode; signed by this certificate from the new contents.
+    SelfSigned(Vec<u8>),
+    // Setup mode; firmware won't check the signature.
+    NotRequired,
+}
+
+#[derive(Clone)]
+pub struct PreflightReport {
+    pub authorization: Authorization,
+    // False when there was no stored TimeStamp to compare against.
+    pub timestamp_checked: bool,
+}
+
+impl fmt::Debug for PreflightReport {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let subject = |certificate: &[u8]| {
+            Certificate::from_der(certificate)
+                .map(|certificate| alloc::format!("{}", certificate.subject))
+                .unwrap_or_default()
+        };
+        if fmtr.alternate() {
+            fmtr.write_str("PreflightReport:\n")?;
+            match &self.authorization {
+                Authorization::Trusted(variable, certificate) => fmtr.write_fmt(format_args!(
+                    "\tAuthorized By: {} ({})\n",
+                    subject(certificate),
+                    variable
+                ))?,
+                Authorization::SelfSigned(certificate) => fmtr.write_fmt(format_args!(
+                    "\tAuthorized By: {} (new contents, setup mode)\n",
+                    subject(certificate)
+                ))?,
+                Authorization::NotRequired => {
+                    fmtr.write_str("\tAuthorized By: nobody needed (setup mode)\n")?
+                }
+            }
+            match self.timestamp_checked {
+                true => fmtr.write_str("\tTimeStamp: later than stored\n"),
+                false => fmtr.write_str("\tTimeStamp: not compared\n"),
+            }
+        } else {
+            fmtr.write_fmt(format_args!(
+                "PreflightReport {{ Authorization: {:?}, TimeStamp Checked: {} }}",
+                self.authorization, self.timestamp_checked
+            ))
+        }
+    }
+}
+
+// The parts of the current Secure Boot state that decide who may sign what.
+#[derive(Clone)]
+pub struct TrustState {
+    pub setup_mode: bool,
+    pub pk: SignatureDatabase,
+    pub kek: SignatureDatabase,
+}
+
+impl TrustState {
+    pub fn read_current() -> UefiResult<Self> {
+        let setup_mode = EfiVariable::get_variable(EFI_SETUP_MODE_NAME, &EFI_GLOBAL_VARIABLE_GUID)?;
+        let read_database = |name| match EfiVariable::get_variable_as::<SignatureDatabase>(
+            name,
+            &EFI_GLOBAL_VARIABLE_GUID,
+        ) {
+            Ok(database) => Ok(database),
+            Err(efi::Status::NOT_FOUND) => Ok(SignatureDatabase {
+                entries: Vec::new(),
+            }),
+            Err(err) => Err(err),
+        };
+
+        Ok(Self {
+            setup_mode: setup_mode.data.first() == Some(&SETUP_MODE),
+            pk: read_database(EFI_PLATFORM_KEY_NAME)?,
+            kek: read_database(EFI_KEY_EXCHANGE_KEY_NAME)?,
+        })
+    }
+}
+
+// Checks `update` as a write to `name` with `attributes`, the same way
+// firmware would. `stored_time` is the variable's current TimeStamp if the
+// caller knows it; firmware offers no way to read it back.
+pub fn preflight(
+    update: &EfiAuthVariable2Ref,
+    name: &str,
+    attributes: u32,
+    state: &TrustState,
+    stored_time: Option<&rustified::Time>,
+) -> Result<PreflightReport, PreflightError> {
+    let guid = get_secure_boot_variable_guid(name).ok_or(PreflightError::UnknownVariable)?;
+    if attributes & efi::VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS == 0 {
+        return Err(PreflightError::NotTimeBased);
+    }
+
+    let time = &update.time;
+    if time.nanosecond != 0 || time.timezone != 0 || time.daylight != 0 {
+        return Err(PreflightError::BadTimestamp);
+    }
+    let append = attributes & efi::VARIABLE_APPEND_WRITE != 0;
+    let timestamp_checked = !append && stored_time.is_some();
+    if let Some(stored) = stored_time.filter(|_| !append) {
+        if time <= stored {
+            return Err(PreflightError::StaleTimestamp(*stored));
+        }
+    }
+
+    let payload = update.payload().map_err(PreflightError::BadPayload)?;
+    check_payload(name, &payload)?;
+
+    // Which certificates firmware will accept a signature from.
+    let anchor_databases: Vec<(&'static str, &SignatureDatabase)> = match (name, state.setup_mode) {
+        (EFI_PLATFORM_KEY_NAME, false) | (EFI_KEY_EXCHANGE_KEY_NAME, false) => {
+            vec![(EFI_PLATFORM_KEY_NAME, &state.pk)]
+        }
+        (EFI_PLATFORM_KEY_NAME, true) => Vec::new(),
+        (_, false) => vec![
+            (EFI_KEY_EXCHANGE_KEY_NAME, &state.kek),
+            (EFI_PLATFORM_KEY_NAME, &state.pk),
+        ],
+        (_, true) => {
+            return Ok(PreflightReport {
+                authorization: Authorization::NotRequired,
+                timestamp_checked,
+            })
+        }
+    };
+    let mut anchors: Vec<(&'static str, Certificate)> = anchor_databases
+        .iter()
+        .flat_map(|(variable, database)| {
+            x509_certificates(database)
+                .into_iter()
+                .map(move |certificate| (*variable, certificate))
+        })
+        .collect();
+    // Only a PK write gets this far in setup mode.
+    if state.setup_mode {
+        anchors.extend(
+            x509_certificates(&payload)
+                .into_iter()
+                .take(1)
+                .map(|certificate| ("", certificate)),
+        );
+    }
+
+    let signed_data = update
+        .signed_data()
+        .map_err(PreflightError::BadSignedData)?;
+    let signer = match signed_data.signers.as_slice() {
+        [signer] => signer,
+        signers => return Err(PreflightError::SignerCount(signers.len())),
+    };
+    let tbs_data = |attributes| {
+        update
+            .to_auth_variable(name, &guid, attributes)
+            .get_tbs_data()
+            .map_err(PreflightError::Efi)
+    };
+    let signer_certificate = match signed_data.verify_signer(signer, &tbs_data(attributes)?) {
+        Ok(certificate) => certificate,
+        // The TBS data includes the attributes, so an update signed as an
+        // append doesn't verify as a replacement and vice versa.
+        Err(VerifyError::BadSignature) => {
+            let other = attributes ^ efi::VARIABLE_APPEND_WRITE;
+            return match signed_data.verify_signer(signer, &tbs_data(other)?) {
+                Ok(_) => Err(PreflightError::WrongAttributes(other)),
+                Err(err) => Err(PreflightError::Signature(err)),
+            };
+        }
+        Err(err) => return Err(PreflightError::Signature(err)),
+    };
+
+    let (variable, anchor) = find_anchor(signer_certificate, &signed_data.certificates, &anchors)
+        .ok_or(PreflightError::Untrusted)?;
+    let anchor = Vec::from(anchor.as_bytes());
+    Ok(PreflightReport {
+        authorization: match state.setup_mode {
+            true => Authorization::SelfSigned(anchor),
+            false => Authorization::Trusted(variable, anchor),
+        },
+        timestamp_checked,
+    })
+}
+
+fn check_payload(name: &str, payload: &SignatureDatabase) -> Result<(), PreflightError> {
+    match name {
+        EFI_PLATFORM_KEY_NAME => {
+            let entries = payload.signature_entries();
+            // An empty payload deletes the PK.
+            match entries.as_slice() {
+                [] => Ok(()),
+                [entry] if entry.signature_type == EFI_CERT_X509_GUID => Ok(()),
+                _ => Err(PreflightError::BadPlatformKey),
+            }
+        }
+        // EDK2 holds KEK to the same list types as db; the spec's
+        // X.509/RSA-2048 expectation isn't enforced.
+        _ => match payload.entries.iter().find(|list| {
+            list.list_type != EFI_CERT_X509_GUID && signature_data_size(&list.list_type).is_none()
+        }) {
+            Some(list) => Err(PreflightError::UnsupportedSignatureType(list.list_type)),
+            None => Ok(()),
+        },
+    }
+}
+
+fn x509_certificates(database: &SignatureDatabase) -> Vec<Certificate<'_>> {
+    database
+        .entries
+        .iter()
+        .filter(|list| list.list_type == EFI_CERT_X509_GUID)
+        .flat_map(|list| list.elements.iter())
+        .filter_map(|element| Certificate::from_der(&element.data).ok())
+        .collect()
+}
+
+// Walks from the signer through the certificates embedded in the SignedData
+// until it reaches one that an anchor issued (or that is an anchor).
+fn find_anchor<'a, 'b>(
+    signer: &Certificate,
+    embedded: &[Certificate],
+    anchors: &'b [(&'static str, Certificate<'a>)],
+) -> Option<(&'static str, &'b Certificate<'a>)> {
+    let mut current = signer;
+    for _ in 0..MAX_CHAIN_DEPTH {
+        let trusted = anchors.iter().find(|(_, anchor)| {
+            anchor.as_bytes() == current.as_bytes()
+                || (anchor.subject.raw == current.issuer.raw
+                    && current.verify_issued_by(anchor).is_ok())
+        });
+        if let Some((variable, anchor)) = trusted {
+            return Some((variable, anchor));
+        }
+
+        current = embedded.iter().find(|issuer| {
+            issuer.as_bytes() != current.as_bytes()
+                && issuer.subject.raw == current.issuer.raw
+                && current.verify_issued_by(issuer).is_ok()
+        })?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::{SignatureList, SignatureListElement};
+    use crate::name_db::CANONICAL_SIGNATURE_OWNER_GUID;
+    use crate::test_data::{auth_files, sig_lists};
+
+    fn database(certificate: &[u8]) -> SignatureDatabase {
+        SignatureDatabase {
+            entries: vec![SignatureList {
+                list_type: EFI_CERT_X509_GUID,
+                header: None,
+                elements: vec![SignatureListElement {
+                    owner: CANONICAL_SIGNATURE_OWNER_GUID,
+                    data: Vec::from(certificate),
+                }],
+            }],
+        }
+    }
+
+    fn user_mode(kek: SignatureDatabase) -> TrustState {
+        TrustState {
+            setup_mode: false,
+            pk: SignatureDatabase::from_bytes(sig_lists::PK).unwrap(),
+            kek,
+        }
+    }
+
+    const APPEND: u32 = EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES | efi::VARIABLE_APPEND_WRITE;
+
+    #[test]
+    fn preflight_should_accept_update_signed_by_kek() {
+        let update = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let state = user_mode(database(auth_files::TEST_KEK_CERT));
+        let report = preflight(&update, "db", APPEND, &state, None).unwrap();
+        assert_eq!(
+            report.authorization,
+            Authorization::Trusted("KEK", Vec::from(auth_files::TEST_KEK_CERT))
+        );
+        assert!(!report.timestamp_checked);
+    }
+
+    #[test]
+    fn preflight_should_explain_rejections() {
+        let update = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let trusted = user_mode(database(auth_files::TEST_KEK_CERT));
+
+        let untrusted = user_mode(SignatureDatabase::from_bytes(sig_lists::KEK).unwrap());
+        assert_eq!(
+            preflight(&update, "db", APPEND, &untrusted, None).err(),
+            Some(PreflightError::Untrusted)
+        );
+        assert_eq!(
+            preflight(
+                &update,
+                "db",
+                EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+                &trusted,
+                None
+            )
+            .err(),
+            Some(PreflightError::WrongAttributes(APPEND))
+        );
+        assert_eq!(
+            preflight(&update, "dbx", APPEND, &trusted, None).err(),
+            Some(PreflightError::Signature(VerifyError::BadSignature))
+        );
+
+        let stored = update.time;
+        let replace = EfiAuthVariable2Ref {
+            data: &[],
+            ..update
+        };
+        assert_eq!(
+            preflight(
+                &replace,
+                "db",
+                EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+                &trusted,
+                Some(&stored)
+            )
+            .err(),
+            Some(PreflightError::StaleTimestamp(stored))
+        );
+    }
+
+    #[test]
+    fn preflight_should_follow_setup_mode_rules() {
+        let update = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let setup = TrustState {
+            setup_mode: true,
+            pk: SignatureDatabase {
+                entries: Vec::new(),
+            },
+            kek: SignatureDatabase {
+                entries: Vec::new(),
+            },
+        };
+        let report = preflight(&update, "db", APPEND, &setup, None).unwrap();
+        assert_eq!(report.authorization, Authorization::NotRequired);
+
+        // Firmware only checks PK writes in setup mode, so even a KEK of
+        // hashes signed for another variable goes through.
+        let report = preflight(&update, "KEK", APPEND, &setup, None).unwrap();
+        assert_eq!(report.authorization, Authorization::NotRequired);
+
+        // PK writes are still checked; this signature was made for db.
+        let pk = EfiAuthVariable2Ref {
+            data: sig_lists::PK,
+            ..update
+        };
+        assert_eq!(
+            preflight(&pk, "PK", EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES, &setup, None).err(),
+            Some(PreflightError::Signature(VerifyError::BadSignature))
+        );
+    }
+}
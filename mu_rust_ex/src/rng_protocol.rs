@@ -0,0 +1,106 @@
+// Copyright (c) Microsoft Corporation
+//
+// SPDX-License-Identifier: BSD-2-Clause-Patent
+
+use crate::protocol_utility::{
+    ManagedProtocol, RustProtocol, RustProtocolError as RPError, RustProtocolResult as RPResult,
+};
+use crate::UefiResult;
+
+use alloc::sync::Arc;
+use core::num::NonZeroU32;
+
+use r_efi::{efi, eficall, eficall_abi};
+use rsa::rand_core::{self, CryptoRng, RngCore};
+use spin::Mutex;
+
+pub const PROTOCOL_NAME: &str = "EfiRngProtocol";
+pub const PROTOCOL_GUID: efi::Guid = efi::Guid::from_fields(
+    0x3152bca5,
+    0xeade,
+    0x433d,
+    0x86,
+    0x2e,
+    &[0xc0, 0x1c, 0xdc, 0x29, 0x1f, 0x44],
+);
+
+#[repr(C)]
+pub struct RawProtocol {
+    pub get_info: eficall! {fn(
+        *mut RawProtocol,
+        *mut usize,
+        *mut efi::Guid,
+    ) -> efi::Status},
+    pub get_rng: eficall! {fn(
+        *mut RawProtocol,
+        *const efi::Guid,   // RNGAlgorithm, NULL for the platform default
+        usize,              // RNGValueLength
+        *mut u8,            // RNGValue
+    ) -> efi::Status},
+}
+
+#[derive(Clone)]
+pub struct Protocol {
+    inner: Arc<Mutex<Option<ManagedProtocol<RawProtocol>>>>,
+}
+
+impl Protocol {
+    // Fills `buffer` from the platform's default RNG algorithm.
+    pub fn get_rng(&self, buffer: &mut [u8]) -> RPResult<()> {
+        let mut prot_guard = self.inner.lock();
+        let prot = prot_guard.as_mut().ok_or(RPError::Unregistered)?;
+
+        let status = (prot.get_rng)(
+            &mut **prot as *mut _,
+            core::ptr::null(),
+            buffer.len(),
+            buffer.as_mut_ptr(),
+        );
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(RPError::Efi(status))
+        }
+    }
+}
+
+// Lets the protocol feed RSA blinding directly.
+impl RngCore for Protocol {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // RngCore gives us no way to report this, and signing without fresh
+        // randomness isn't an option.
+        self.try_fill_bytes(dest)
+            .expect("EFI_RNG_PROTOCOL failed to produce random bytes");
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.get_rng(dest).map_err(|err| {
+            let status: efi::Status = err.into();
+            // Keep the low bits of the status so the failure can be told apart.
+            let code = rand_core::Error::CUSTOM_START | (status.as_usize() as u32 & 0xFFFF);
+            NonZeroU32::new(code).unwrap().into()
+        })
+    }
+}
+impl CryptoRng for Protocol {}
+
+// TODO: Make this into a macro or a derive.
+impl RustProtocol for Protocol {
+    type RawProtocol = RawProtocol;
+    fn get_name() -> &'static str {
+        PROTOCOL_NAME
+    }
+    fn get_guid() -> &'static efi::Guid {
+        &PROTOCOL_GUID
+    }
+
+    fn init_protocol(mp: Arc<Mutex<Option<ManagedProtocol<RawProtocol>>>>) -> UefiResult<Self> {
+        Ok(Self { inner: mp })
+    }
+}
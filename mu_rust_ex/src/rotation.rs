@@ -0,0 +1,387 @@
+/* @file
+Certificate expiry and CA rotation advice for PK, KEK and db.
+
+Firmware doesn't check validity dates when it verifies an image, so an
+expired CA in db keeps working for what it has already signed. What stops
+working is the signing side: nothing new gets issued under it. The advisor
+flags certificates that have expired, or will within a window, and checks
+whether the catalog's successor for each retiring CA is already enrolled.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use sha1::{Digest, Sha1};
+
+use crate::auth_variable::{
+    EFI_CERT_X509_GUID, EFI_IMAGE_SECURITY_DATABASE, EFI_KEY_EXCHANGE_KEY_NAME,
+    EFI_PLATFORM_KEY_NAME,
+};
+use crate::catalog::{self, CatalogEntry};
+use crate::rustified::Time;
+use crate::secure_boot_status::KeyDatabases;
+use crate::x509::{Certificate, DebugHex};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// Long enough to get an update through a fleet before the date arrives.
+pub const DEFAULT_ROTATION_WINDOW_DAYS: u32 = 180;
+
+// (retiring, successor) catalog entry names. One CA can have several
+// successors; the 2011 third-party CA was split into UEFI and option ROM CAs.
+pub const ROTATIONS: &[(&str, &str)] = &[
+    ("ms-kek-2011", "ms-kek-2023"),
+    ("ms-windows-pca-2011", "ms-windows-uefi-2023"),
+    ("ms-uefi-ca-2011", "ms-uefi-ca-2023"),
+    ("ms-uefi-ca-2011", "ms-option-rom-2023"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpiryStatus {
+    Valid,
+    // (days) Expires within the window, this many whole days from now.
+    ExpiresSoon(i64),
+    Expired,
+    // The list says X509, but the contents don't parse.
+    Unparseable,
+}
+
+#[derive(Clone)]
+pub struct CertificateFinding {
+    pub variable: &'static str,
+    // Common name if there is one, otherwise empty.
+    pub subject: String,
+    pub sha1_thumbprint: [u8; 20],
+    pub not_after: Option<Time>,
+    pub status: ExpiryStatus,
+    pub catalog: Option<&'static CatalogEntry>,
+}
+
+impl fmt::Debug for CertificateFinding {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!("{}: ", self.variable))?;
+        match &self.catalog {
+            Some(entry) => fmtr.write_fmt(format_args!("{} ", entry.name))?,
+            None if !self.subject.is_empty() => {
+                fmtr.write_fmt(format_args!("\"{}\" ", self.subject))?
+            }
+            None => fmtr.write_fmt(format_args!("{:?} ", DebugHex(&self.sha1_thumbprint)))?,
+        }
+        match (self.status, &self.not_after) {
+            (ExpiryStatus::Unparseable, _) | (_, None) => fmtr.write_str("(unparseable)"),
+            (ExpiryStatus::Valid, Some(time)) => {
+                fmtr.write_fmt(format_args!("valid until {}", time))
+            }
+            (ExpiryStatus::ExpiresSoon(days), Some(time)) => {
+                fmtr.write_fmt(format_args!("EXPIRES {} ({} days)", time, days))
+            }
+            (ExpiryStatus::Expired, Some(time)) => fmtr.write_fmt(format_args!("EXPIRED {}", time)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationFinding {
+    pub retiring: &'static CatalogEntry,
+    pub successor: &'static CatalogEntry,
+    pub retiring_present: bool,
+    pub successor_present: bool,
+}
+
+impl RotationFinding {
+    // Only platforms that trust the retiring CA need its successor.
+    pub fn is_needed(&self) -> bool {
+        self.retiring_present && !self.successor_present
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Remediation {
+    // (entry) Append a successor CA from the catalog.
+    InstallSuccessor(&'static CatalogEntry),
+    // (variable, subject) Expired or expiring, and the catalog has no
+    // successor for it; its owner has to supply one.
+    ReplaceCertificate(&'static str, String),
+}
+
+impl fmt::Display for Remediation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InstallSuccessor(entry) => write!(
+                f,
+                "Install {} into {}: install-catalog {} {} <{} key>",
+                entry.description,
+                entry.variable,
+                entry.name,
+                entry.variable,
+                if entry.variable == EFI_KEY_EXCHANGE_KEY_NAME {
+                    EFI_PLATFORM_KEY_NAME
+                } else {
+                    EFI_KEY_EXCHANGE_KEY_NAME
+                }
+            ),
+            Self::ReplaceCertificate(variable, subject) => {
+                write!(
+                    f,
+                    "Replace \"{}\" in {} with a new certificate",
+                    subject, variable
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RotationReport {
+    pub checked_at: Time,
+    pub window_days: u32,
+    pub certificates: Vec<CertificateFinding>,
+    pub rotations: Vec<RotationFinding>,
+}
+
+impl RotationReport {
+    // What this machine needs, successors first. Empty when nothing does.
+    pub fn remediation(&self) -> Vec<Remediation> {
+        let mut steps: Vec<Remediation> = self
+            .rotations
+            .iter()
+            .filter(|rotation| rotation.is_needed())
+            .map(|rotation| Remediation::InstallSuccessor(rotation.successor))
+            .collect();
+
+        for finding in self.certificates.iter() {
+            if !matches!(
+                finding.status,
+                ExpiryStatus::Expired | ExpiryStatus::ExpiresSoon(_)
+            ) {
+                continue;
+            }
+            let has_successor = finding.catalog.is_some_and(|entry| {
+                ROTATIONS
+                    .iter()
+                    .any(|(retiring, _)| *retiring == entry.name)
+            });
+            if !has_successor {
+                steps.push(Remediation::ReplaceCertificate(
+                    finding.variable,
+                    finding.subject.clone(),
+                ));
+            }
+        }
+        steps
+    }
+
+    pub fn is_affected(&self) -> bool {
+        !self.remediation().is_empty()
+    }
+}
+
+impl fmt::Debug for RotationReport {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let remediation = self.remediation();
+        if fmtr.alternate() {
+            fmtr.write_fmt(format_args!(
+                "RotationReport ({}, {} day window):\n",
+                self.checked_at, self.window_days
+            ))?;
+            fmtr.write_str("\tCertificates:\n")?;
+            for finding in self.certificates.iter() {
+                fmtr.write_fmt(format_args!("\t\t{:?}\n", finding))?;
+            }
+            fmtr.write_str("\tRotations:\n")?;
+            for rotation in self.rotations.iter().filter(|r| r.retiring_present) {
+                fmtr.write_fmt(format_args!(
+                    "\t\t{} -> {}: {}\n",
+                    rotation.retiring.name,
+                    rotation.successor.name,
+                    if rotation.successor_present {
+                        "done"
+                    } else {
+                        "NEEDED"
+                    }
+                ))?;
+            }
+            fmtr.write_str("\tRemediation:\n")?;
+            if remediation.is_empty() {
+                fmtr.write_str("\t\tNone needed\n")?;
+            }
+            for step in remediation.iter() {
+                fmtr.write_fmt(format_args!("\t\t{}\n", step))?;
+            }
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "RotationReport {{ Certificates: {}, Remediation steps: {} }}",
+                self.certificates.len(),
+                remediation.len()
+            ))
+        }
+    }
+}
+
+fn examine(
+    variable: &'static str,
+    data: &[u8],
+    now: &Time,
+    window_days: u32,
+) -> CertificateFinding {
+    let certificate = match Certificate::from_der(data) {
+        Ok(certificate) => certificate,
+        Err(_) => {
+            return CertificateFinding {
+                variable,
+                subject: String::new(),
+                sha1_thumbprint: Sha1::digest(data).into(),
+                not_after: None,
+                status: ExpiryStatus::Unparseable,
+                catalog: None,
+            }
+        }
+    };
+
+    let thumbprint = certificate.sha1_thumbprint();
+    let remaining = certificate.not_after.to_unix_seconds() - now.to_unix_seconds();
+    let status = if remaining < 0 {
+        ExpiryStatus::Expired
+    } else if remaining <= i64::from(window_days) * SECONDS_PER_DAY {
+        ExpiryStatus::ExpiresSoon(remaining / SECONDS_PER_DAY)
+    } else {
+        ExpiryStatus::Valid
+    };
+    CertificateFinding {
+        variable,
+        subject: certificate.subject.common_name().unwrap_or_default(),
+        sha1_thumbprint: thumbprint,
+        not_after: Some(certificate.not_after),
+        status,
+        catalog: catalog::CATALOG
+            .iter()
+            .find(|entry| entry.sha1_thumbprint == thumbprint),
+    }
+}
+
+// Checks every X509 certificate in PK, KEK and db against `now`, flagging
+// any that expire within `window_days`. Hash entries have no validity and
+// are skipped, as are databases that are missing or don't parse.
+pub fn analyze(databases: &KeyDatabases, now: &Time, window_days: u32) -> RotationReport {
+    let mut certificates = Vec::new();
+    for (variable, snapshot) in IntoIterator::into_iter([
+        (EFI_PLATFORM_KEY_NAME, &databases.pk),
+        (EFI_KEY_EXCHANGE_KEY_NAME, &databases.kek),
+        (EFI_IMAGE_SECURITY_DATABASE, &databases.db),
+    ]) {
+        let database = match snapshot.as_ref().map(|snapshot| &snapshot.database) {
+            Some(Ok(database)) => database,
+            _ => continue,
+        };
+        for list in database
+            .entries
+            .iter()
+            .filter(|list| list.list_type == EFI_CERT_X509_GUID)
+        {
+            for element in list.elements.iter() {
+                certificates.push(examine(variable, &element.data, now, window_days));
+            }
+        }
+    }
+
+    let present = |name: &str| {
+        certificates.iter().any(|finding| {
+            finding
+                .catalog
+                .is_some_and(|entry| entry.name == name && entry.variable == finding.variable)
+        })
+    };
+    let rotations = ROTATIONS
+        .iter()
+        .filter_map(|(retiring, successor)| {
+            Some(RotationFinding {
+                retiring: catalog::find_entry(retiring)?,
+                successor: catalog::find_entry(successor)?,
+                retiring_present: present(retiring),
+                successor_present: present(successor),
+            })
+        })
+        .collect();
+
+    RotationReport {
+        checked_at: *now,
+        window_days,
+        certificates,
+        rotations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secure_boot_status::DatabaseSnapshot;
+    use crate::test_data::sig_lists;
+
+    fn databases() -> KeyDatabases {
+        KeyDatabases {
+            pk: Some(DatabaseSnapshot::from_bytes("PK", sig_lists::PK)),
+            kek: Some(DatabaseSnapshot::from_bytes("KEK", sig_lists::KEK)),
+            db: Some(DatabaseSnapshot::from_bytes("db", sig_lists::DB)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn analyze_should_flag_expiring_2011_cas() {
+        let now: Time = "2026-05-01T00:00:00Z".parse().unwrap();
+        let report = analyze(&databases(), &now, 90);
+
+        let kek = report
+            .certificates
+            .iter()
+            .find(|finding| finding.variable == "KEK")
+            .unwrap();
+        assert_eq!(kek.catalog.unwrap().name, "ms-kek-2011");
+        assert!(matches!(kek.status, ExpiryStatus::ExpiresSoon(54)));
+
+        let remediation = report.remediation();
+        assert!(remediation.iter().any(|step| *step
+            == Remediation::InstallSuccessor(catalog::find_entry("ms-kek-2023").unwrap())));
+        assert!(report.is_affected());
+    }
+
+    #[test]
+    fn remediation_should_name_the_install_catalog_command() {
+        let kek = Remediation::InstallSuccessor(catalog::find_entry("ms-kek-2023").unwrap());
+        assert_eq!(
+            format!("{}", kek),
+            "Install Microsoft Corporation KEK 2K CA 2023 into KEK: \
+             install-catalog ms-kek-2023 KEK <PK key>"
+        );
+        let db = Remediation::InstallSuccessor(catalog::find_entry("ms-uefi-ca-2023").unwrap());
+        assert!(format!("{}", db).ends_with("install-catalog ms-uefi-ca-2023 db <KEK key>"));
+    }
+
+    #[test]
+    fn analyze_should_respect_the_window() {
+        let now: Time = "2020-01-01T00:00:00Z".parse().unwrap();
+        let report = analyze(&databases(), &now, 30);
+        assert!(report
+            .certificates
+            .iter()
+            .all(|finding| finding.status == ExpiryStatus::Valid));
+        // Nothing is expiring yet, but the successors are still missing.
+        assert!(report
+            .remediation()
+            .iter()
+            .all(|step| matches!(step, Remediation::InstallSuccessor(_))));
+
+        let later: Time = "2030-01-01T00:00:00Z".parse().unwrap();
+        let report = analyze(&databases(), &later, 30);
+        assert!(report
+            .certificates
+            .iter()
+            .any(|finding| finding.status == ExpiryStatus::Expired));
+    }
+}
@@ -2,14 +2,20 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::convert::TryFrom;
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 use r_efi::efi;
 
 use efi::RuntimeServices as EfiRuntimeServices;
 
-use crate::UefiResult;
+use crate::{rustified, UefiResult};
 
 #[derive(Debug, Clone, Copy)]
 pub enum RuntimeServicesError {
@@ -32,6 +38,138 @@ impl From<RuntimeServicesError> for efi::Status {
 
 pub type UefiRsResult<T> = Result<T, RuntimeServicesError>;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetVariableError {
+    // No room left in the store, or the data is too large for one variable.
+    OutOfResources,
+    // The variable is read-only, or is boot-services only and boot services are gone.
+    WriteProtected,
+    // An authenticated write failed verification.
+    SecurityViolation,
+    // Deleting or appending to a variable that doesn't exist.
+    NotFound,
+    // Bad name, attributes or size.
+    InvalidParameter,
+    Other(efi::Status),
+}
+impl core::fmt::Display for SetVariableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfResources => write!(f, "Not enough variable storage"),
+            Self::WriteProtected => write!(f, "Variable is write-protected"),
+            Self::SecurityViolation => write!(f, "Authenticated write was rejected"),
+            Self::NotFound => write!(f, "Variable not found"),
+            Self::InvalidParameter => write!(f, "Invalid variable name, attributes or size"),
+            Self::Other(status) => write!(f, "SetVariable failed ({:?})", status),
+        }
+    }
+}
+impl From<efi::Status> for SetVariableError {
+    fn from(f: efi::Status) -> Self {
+        match f {
+            efi::Status::OUT_OF_RESOURCES => Self::OutOfResources,
+            efi::Status::WRITE_PROTECTED => Self::WriteProtected,
+            efi::Status::SECURITY_VIOLATION => Self::SecurityViolation,
+            efi::Status::NOT_FOUND => Self::NotFound,
+            efi::Status::INVALID_PARAMETER => Self::InvalidParameter,
+            x => Self::Other(x),
+        }
+    }
+}
+impl From<SetVariableError> for efi::Status {
+    fn from(f: SetVariableError) -> Self {
+        match f {
+            SetVariableError::OutOfResources => efi::Status::OUT_OF_RESOURCES,
+            SetVariableError::WriteProtected => efi::Status::WRITE_PROTECTED,
+            SetVariableError::SecurityViolation => efi::Status::SECURITY_VIOLATION,
+            SetVariableError::NotFound => efi::Status::NOT_FOUND,
+            SetVariableError::InvalidParameter => efi::Status::INVALID_PARAMETER,
+            SetVariableError::Other(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariableEnumerationError {
+    // The previous variable disappeared, so the walk can't continue.
+    Interrupted,
+    // Firmware returned a name with no terminating NUL.
+    MalformedName,
+    Other(efi::Status),
+}
+impl core::fmt::Display for VariableEnumerationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Interrupted => write!(f, "Variable store changed during enumeration"),
+            Self::MalformedName => write!(f, "Firmware returned an unterminated variable name"),
+            Self::Other(status) => write!(f, "GetNextVariableName failed ({:?})", status),
+        }
+    }
+}
+impl From<VariableEnumerationError> for efi::Status {
+    fn from(f: VariableEnumerationError) -> Self {
+        match f {
+            VariableEnumerationError::Interrupted => efi::Status::INVALID_PARAMETER,
+            VariableEnumerationError::MalformedName => efi::Status::VOLUME_CORRUPTED,
+            VariableEnumerationError::Other(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryVariableInfoError {
+    // (attributes) Not a valid combination of attributes.
+    InvalidAttributes(u32),
+    // (attributes) Valid, but this platform doesn't support them.
+    Unsupported(u32),
+    Other(efi::Status),
+}
+impl core::fmt::Display for QueryVariableInfoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidAttributes(x) => write!(f, "Invalid variable attributes 0x{:X}", x),
+            Self::Unsupported(x) => write!(f, "Unsupported variable attributes 0x{:X}", x),
+            Self::Other(status) => write!(f, "QueryVariableInfo failed ({:?})", status),
+        }
+    }
+}
+impl From<QueryVariableInfoError> for efi::Status {
+    fn from(f: QueryVariableInfoError) -> Self {
+        match f {
+            QueryVariableInfoError::InvalidAttributes(_) => efi::Status::INVALID_PARAMETER,
+            QueryVariableInfoError::Unsupported(_) => efi::Status::UNSUPPORTED,
+            QueryVariableInfoError::Other(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetType {
+    Cold,
+    Warm,
+    Shutdown,
+    // (type) A reset the platform defines itself, identified by GUID.
+    PlatformSpecific(efi::Guid),
+}
+impl From<ResetType> for efi::ResetType {
+    fn from(f: ResetType) -> Self {
+        match f {
+            ResetType::Cold => efi::RESET_COLD,
+            ResetType::Warm => efi::RESET_WARM,
+            ResetType::Shutdown => efi::RESET_SHUTDOWN,
+            ResetType::PlatformSpecific(_) => efi::RESET_PLATFORM_SPECIFIC,
+        }
+    }
+}
+
+// Storage figures for variables with a given set of attributes, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariableStorageInfo {
+    pub maximum_storage_size: u64,
+    pub remaining_storage_size: u64,
+    pub maximum_variable_size: u64,
+}
+
 pub struct RuntimeServices {
     inner: NonNull<EfiRuntimeServices>,
 }
@@ -86,7 +224,8 @@ impl RuntimeServices {
             core::ptr::null_mut(),
         );
 
-        if !status.is_error() {
+        // With no buffer, an existing variable reports BUFFER_TOO_SMALL and its size.
+        if !status.is_error() || status == efi::Status::BUFFER_TOO_SMALL {
             Ok(data_size)
         } else {
             Err(status)
@@ -119,4 +258,320 @@ impl RuntimeServices {
             Err(RuntimeServicesError::Other(status))
         }
     }
+
+    pub fn get_time(&self) -> UefiResult<(rustified::Time, rustified::TimeCapabilities)> {
+        let rs = unsafe { self.inner.as_ref() };
+        let mut time = efi::Time::from(rustified::Time::default());
+        let mut capabilities = efi::TimeCapabilities {
+            resolution: 0,
+            accuracy: 0,
+            sets_to_zero: false.into(),
+        };
+        let status = (rs.get_time)(&mut time as *mut _, &mut capabilities as *mut _);
+
+        if !status.is_error() {
+            Ok((rustified::Time::try_from(time)?, capabilities.into()))
+        } else {
+            Err(status)
+        }
+    }
+
+    // For expiry times and the like. The result keeps the RTC's timezone.
+    pub fn time_from_now(&self, seconds: i64) -> UefiResult<rustified::Time> {
+        let (now, _) = self.get_time()?;
+        Ok(now.plus_seconds(seconds)?)
+    }
+
+    pub fn set_time(&self, time: &rustified::Time) -> UefiResult<()> {
+        let rs = unsafe { self.inner.as_ref() };
+        let mut time = efi::Time::from(*time);
+        let status = (rs.set_time)(&mut time as *mut _);
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
+
+    // `reason` is recorded by firmware that logs resets. A platform-specific
+    // reset always passes ResetData, since its GUID follows the string.
+    pub fn reset_system(
+        &self,
+        reset_type: ResetType,
+        status: efi::Status,
+        reason: Option<&str>,
+    ) -> ! {
+        let rs = unsafe { self.inner.as_ref() };
+
+        let mut data = Vec::<u8>::new();
+        if reason.is_some() || matches!(reset_type, ResetType::PlatformSpecific(_)) {
+            for c in reason
+                .unwrap_or("")
+                .encode_utf16()
+                .chain(core::iter::once(0))
+            {
+                data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        if let ResetType::PlatformSpecific(guid) = reset_type {
+            data.extend_from_slice(guid.as_bytes());
+        }
+        let data_ptr = match data.is_empty() {
+            true => core::ptr::null_mut(),
+            false => data.as_mut_ptr() as *mut _,
+        };
+
+        (rs.reset_system)(reset_type.into(), status, data.len(), data_ptr);
+        // ResetSystem() doesn't return.
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    // An empty `data` with no EFI_VARIABLE_APPEND_WRITE deletes the variable.
+    pub fn set_variable(
+        &self,
+        name: *mut efi::Char16,
+        guid: *mut efi::Guid,
+        attributes: u32,
+        data: &[u8],
+    ) -> Result<(), SetVariableError> {
+        let rs = unsafe { self.inner.as_ref() };
+        let status = (rs.set_variable)(name, guid, attributes, data.len(), data.as_ptr() as *mut _);
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(status.into())
+        }
+    }
+
+    // Walks the whole variable store as (name, vendor GUID) pairs.
+    pub fn variables(&self) -> Variables<'_> {
+        let get_next_variable_name = unsafe { self.inner.as_ref() }.get_next_variable_name;
+        Variables::new(Box::new(move |name_size, name, guid| {
+            (get_next_variable_name)(name_size as *mut _, name.as_mut_ptr(), guid as *mut _)
+        }))
+    }
+
+    pub fn query_variable_info(
+        &self,
+        attributes: u32,
+    ) -> Result<VariableStorageInfo, QueryVariableInfoError> {
+        let rs = unsafe { self.inner.as_ref() };
+        let mut info = VariableStorageInfo {
+            maximum_storage_size: 0,
+            remaining_storage_size: 0,
+            maximum_variable_size: 0,
+        };
+        let status = (rs.query_variable_info)(
+            attributes,
+            &mut info.maximum_storage_size as *mut _,
+            &mut info.remaining_storage_size as *mut _,
+            &mut info.maximum_variable_size as *mut _,
+        );
+
+        match status {
+            efi::Status::SUCCESS => Ok(info),
+            efi::Status::INVALID_PARAMETER => {
+                Err(QueryVariableInfoError::InvalidAttributes(attributes))
+            }
+            efi::Status::UNSUPPORTED => Err(QueryVariableInfoError::Unsupported(attributes)),
+            x => Err(QueryVariableInfoError::Other(x)),
+        }
+    }
+}
+
+// In Char16s. Most names fit; longer ones make the buffer grow.
+const INITIAL_NAME_LENGTH: usize = 64;
+
+// GetNextVariableName(), minus the raw pointers.
+type GetNextVariableName<'a> =
+    Box<dyn FnMut(&mut usize, &mut [efi::Char16], &mut efi::Guid) -> efi::Status + 'a>;
+
+pub struct Variables<'a> {
+    get_next_variable_name: GetNextVariableName<'a>,
+    // GetNextVariableName() continues from whatever name is in here, so it
+    // holds the previous result between calls.
+    name: Vec<efi::Char16>,
+    guid: efi::Guid,
+    done: bool,
+}
+
+impl<'a> Variables<'a> {
+    fn new(get_next_variable_name: GetNextVariableName<'a>) -> Self {
+        Self {
+            get_next_variable_name,
+            name: vec![0; INITIAL_NAME_LENGTH],
+            guid: efi::Guid::from_fields(0, 0, 0, 0, 0, &[0; 6]),
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Variables<'a> {
+    type Item = Result<(String, efi::Guid), VariableEnumerationError>;
+
+    // Once this returns None or an error, it keeps returning None.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let mut name_size = self.name.len() * core::mem::size_of::<efi::Char16>();
+            let status =
+                (self.get_next_variable_name)(&mut name_size, &mut self.name, &mut self.guid);
+
+            let result = match status {
+                efi::Status::SUCCESS => match self.name.iter().position(|c| *c == 0) {
+                    Some(length) => {
+                        let name = core::char::decode_utf16(self.name[..length].iter().copied())
+                            .map(|c| c.unwrap_or(core::char::REPLACEMENT_CHARACTER))
+                            .collect();
+                        return Some(Ok((name, self.guid)));
+                    }
+                    // Without the terminator there's nothing to continue from.
+                    None => Some(Err(VariableEnumerationError::MalformedName)),
+                },
+                // Grow without disturbing the previous name; it's still the input.
+                efi::Status::BUFFER_TOO_SMALL => {
+                    let length = name_size.div_ceil(core::mem::size_of::<efi::Char16>());
+                    self.name.resize(length.max(self.name.len() + 1), 0);
+                    continue;
+                }
+                efi::Status::NOT_FOUND => None,
+                efi::Status::INVALID_PARAMETER => Some(Err(VariableEnumerationError::Interrupted)),
+                x => Some(Err(VariableEnumerationError::Other(x))),
+            };
+            self.done = true;
+            return result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guid(index: u8) -> efi::Guid {
+        efi::Guid::from_fields(0, 0, 0, 0, 0, &[0, 0, 0, 0, 0, index])
+    }
+
+    // Plays back `store` the way GetNextVariableName() would, recording the
+    // buffer size (in bytes) offered on every call.
+    fn fake_store<'a>(
+        store: &'a [(&'a str, efi::Guid)],
+        offered: &'a RefCell<Vec<usize>>,
+    ) -> Variables<'a> {
+        Variables::new(Box::new(move |name_size, name, vendor| {
+            offered.borrow_mut().push(*name_size);
+            let length = name.iter().position(|c| *c == 0).unwrap();
+            let previous: String = core::char::decode_utf16(name[..length].iter().copied())
+                .map(Result::unwrap)
+                .collect();
+            let index = match previous.is_empty() {
+                true => 0,
+                false => match store
+                    .iter()
+                    .position(|(n, g)| *n == previous && *g == *vendor)
+                {
+                    Some(index) => index + 1,
+                    None => return efi::Status::INVALID_PARAMETER,
+                },
+            };
+            let (next_name, next_guid) = match store.get(index) {
+                Some(entry) => entry,
+                None => return efi::Status::NOT_FOUND,
+            };
+
+            let encoded: Vec<u16> = next_name.encode_utf16().chain([0]).collect();
+            let required = encoded.len() * core::mem::size_of::<efi::Char16>();
+            if *name_size < required {
+                *name_size = required;
+                return efi::Status::BUFFER_TOO_SMALL;
+            }
+            name[..encoded.len()].copy_from_slice(&encoded);
+            *name_size = required;
+            *vendor = *next_guid;
+            efi::Status::SUCCESS
+        }))
+    }
+
+    #[test]
+    fn variables_should_grow_name_buffer() {
+        let long_name = "L".repeat(INITIAL_NAME_LENGTH * 2);
+        let store = [
+            ("PK", guid(1)),
+            (long_name.as_str(), guid(2)),
+            ("db", guid(3)),
+        ];
+        let offered = RefCell::new(Vec::new());
+
+        let names: Vec<_> = fake_store(&store, &offered).map(Result::unwrap).collect();
+        assert_eq!(
+            names,
+            vec![
+                (String::from("PK"), guid(1)),
+                (long_name.clone(), guid(2)),
+                (String::from("db"), guid(3)),
+            ]
+        );
+        // The long name took a retry with exactly the size firmware asked
+        // for, and the bigger buffer stuck around afterwards.
+        let grown = (INITIAL_NAME_LENGTH * 2 + 1) * 2;
+        assert_eq!(
+            *offered.borrow(),
+            vec![
+                INITIAL_NAME_LENGTH * 2,
+                INITIAL_NAME_LENGTH * 2,
+                grown,
+                grown,
+                grown
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_should_stay_finished() {
+        let store = [("PK", guid(1))];
+        let offered = RefCell::new(Vec::new());
+        let mut variables = fake_store(&store, &offered);
+        assert!(matches!(variables.next(), Some(Ok(_))));
+        assert_eq!(variables.next(), None);
+        assert_eq!(variables.next(), None);
+        // Nothing is asked of firmware once NOT_FOUND has been seen.
+        assert_eq!(offered.borrow().len(), 2);
+    }
+
+    #[test]
+    fn variables_should_stop_after_errors() {
+        let mut calls = 0;
+        let mut variables = Variables::new(Box::new(
+            |_: &mut usize, name: &mut [efi::Char16], _: &mut efi::Guid| {
+                calls += 1;
+                name.fill(0x41);
+                efi::Status::SUCCESS
+            },
+        ));
+        assert_eq!(
+            variables.next(),
+            Some(Err(VariableEnumerationError::MalformedName))
+        );
+        assert_eq!(variables.next(), None);
+        drop(variables);
+        assert_eq!(calls, 1);
+
+        let mut variables = Variables::new(Box::new(
+            |_: &mut usize, _: &mut [efi::Char16], _: &mut efi::Guid| efi::Status::DEVICE_ERROR,
+        ));
+        assert_eq!(
+            variables.next(),
+            Some(Err(VariableEnumerationError::Other(
+                efi::Status::DEVICE_ERROR
+            )))
+        );
+        assert_eq!(variables.next(), None);
+    }
 }
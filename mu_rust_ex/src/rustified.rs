@@ -8,11 +8,101 @@ SPDX-License-Identifier: BSD-2-Clause-Patent
 
 */
 
+use core::cmp::Ordering;
 use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
 
 use r_efi::efi;
-use r_efi::efi::{TIME_ADJUST_DAYLIGHT, TIME_IN_DAYLIGHT};
+use r_efi::efi::{TIME_ADJUST_DAYLIGHT, TIME_IN_DAYLIGHT, UNSPECIFIED_TIMEZONE};
 
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const NANOSECONDS_PER_SECOND: u32 = 1_000_000_000;
+// EFI_TIME limits, straight from the spec.
+const MIN_YEAR: u16 = 1900;
+const MAX_YEAR: u16 = 9999;
+const MAX_TIMEZONE: i16 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeError {
+    // Month or day out of range, including Feb 29 outside a leap year.
+    BadDate,
+    // Hour, minute, second or nanosecond out of range.
+    BadTime,
+    // (timezone) Neither EFI_UNSPECIFIED_TIMEZONE nor within +/-1440 minutes.
+    BadTimezone(i16),
+    // (daylight) Unknown bits, or EFI_TIME_IN_DAYLIGHT without EFI_TIME_ADJUST_DAYLIGHT.
+    BadDaylight(u8),
+    // The result falls outside the years EFI_TIME can hold.
+    OutOfRange,
+    // The string isn't an ISO-8601 date and time.
+    BadFormat,
+}
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadDate => write!(f, "Invalid date"),
+            Self::BadTime => write!(f, "Invalid time of day"),
+            Self::BadTimezone(x) => write!(f, "Invalid timezone {}", x),
+            Self::BadDaylight(x) => write!(f, "Invalid daylight flags 0x{:X}", x),
+            Self::OutOfRange => write!(f, "Year outside {}-{}", MIN_YEAR, MAX_YEAR),
+            Self::BadFormat => write!(f, "Expected YYYY-MM-DDTHH:MM:SS[.fraction][Z|+HH:MM]"),
+        }
+    }
+}
+impl From<TimeError> for efi::Status {
+    fn from(_f: TimeError) -> Self {
+        efi::Status::INVALID_PARAMETER
+    }
+}
+
+pub fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: u16, month: u8, day: u8) -> i64 {
+    let year = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Inverse of days_from_civil().
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u8;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Comparisons and conversions go through UTC: TimeZone is the local offset
+// in minutes (Localtime = UTC + TimeZone), and EFI_UNSPECIFIED_TIMEZONE is
+// treated as UTC since there's nothing better to go on. Daylight is only
+// informational and doesn't move the time.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Time {
     pub year: u16,
@@ -28,6 +118,252 @@ pub struct Time {
     // pub pad2: u8,
 }
 
+impl Time {
+    // Size of the packed EFI_TIME structure as it appears in variables and signature data.
+    pub const SERIALIZED_SIZE: usize = core::mem::size_of::<efi::Time>();
+
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, efi::Status> {
+        if buffer.len() < Self::SERIALIZED_SIZE {
+            return Err(efi::Status::BAD_BUFFER_SIZE);
+        }
+
+        Self::try_from(efi::Time {
+            year: u16::from_le_bytes([buffer[0], buffer[1]]),
+            month: buffer[2],
+            day: buffer[3],
+            hour: buffer[4],
+            minute: buffer[5],
+            second: buffer[6],
+            pad1: 0,
+            nanosecond: u32::from_le_bytes([buffer[8], buffer[9], buffer[10], buffer[11]]),
+            timezone: i16::from_le_bytes([buffer[12], buffer[13]]),
+            daylight: buffer[14],
+            pad2: 0,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE] {
+        let mut buffer = [0u8; Self::SERIALIZED_SIZE];
+        buffer[0..2].copy_from_slice(&self.year.to_le_bytes());
+        buffer[2] = self.month;
+        buffer[3] = self.day;
+        buffer[4] = self.hour;
+        buffer[5] = self.minute;
+        buffer[6] = self.second;
+        buffer[8..12].copy_from_slice(&self.nanosecond.to_le_bytes());
+        buffer[12..14].copy_from_slice(&self.timezone.to_le_bytes());
+        buffer[14] = self.daylight;
+        buffer
+    }
+
+    pub fn validate(&self) -> Result<(), TimeError> {
+        if !(MIN_YEAR..=MAX_YEAR).contains(&self.year) {
+            return Err(TimeError::OutOfRange);
+        }
+        if !(1..=12).contains(&self.month)
+            || !(1..=days_in_month(self.year, self.month)).contains(&self.day)
+        {
+            return Err(TimeError::BadDate);
+        }
+        if self.hour > 23
+            || self.minute > 59
+            || self.second > 59
+            || self.nanosecond >= NANOSECONDS_PER_SECOND
+        {
+            return Err(TimeError::BadTime);
+        }
+        if self.timezone != UNSPECIFIED_TIMEZONE
+            && !(-MAX_TIMEZONE..=MAX_TIMEZONE).contains(&self.timezone)
+        {
+            return Err(TimeError::BadTimezone(self.timezone));
+        }
+        let tad = (self.daylight & TIME_ADJUST_DAYLIGHT) == TIME_ADJUST_DAYLIGHT;
+        let tid = (self.daylight & TIME_IN_DAYLIGHT) == TIME_IN_DAYLIGHT;
+        if ((self.daylight & 0xFC) != 0) || (tid && !tad) {
+            return Err(TimeError::BadDaylight(self.daylight));
+        }
+        Ok(())
+    }
+
+    // Minutes to add to UTC to get this time's local time.
+    fn offset_minutes(&self) -> i64 {
+        match self.timezone {
+            UNSPECIFIED_TIMEZONE => 0,
+            x => x as i64,
+        }
+    }
+
+    // Whole seconds since 1970-01-01T00:00:00Z; nanosecond is dropped.
+    pub fn to_unix_seconds(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.offset_minutes() * 60
+    }
+
+    pub fn from_unix_seconds(seconds: i64) -> Result<Self, TimeError> {
+        Self::from_unix_seconds_in(seconds, &Self::default())
+    }
+
+    // Same instant as `seconds`, expressed in `zone`'s timezone and daylight
+    // setting, keeping its nanosecond.
+    fn from_unix_seconds_in(seconds: i64, zone: &Self) -> Result<Self, TimeError> {
+        let local = seconds
+            .checked_add(zone.offset_minutes() * 60)
+            .ok_or(TimeError::OutOfRange)?;
+        let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+        let time_of_day = local.rem_euclid(SECONDS_PER_DAY);
+        if !(MIN_YEAR as i64..=MAX_YEAR as i64).contains(&year) {
+            return Err(TimeError::OutOfRange);
+        }
+
+        Ok(Self {
+            year: year as u16,
+            month,
+            day,
+            hour: (time_of_day / 3600) as u8,
+            minute: (time_of_day / 60 % 60) as u8,
+            second: (time_of_day % 60) as u8,
+            nanosecond: zone.nanosecond,
+            timezone: zone.timezone,
+            daylight: zone.daylight,
+        })
+    }
+
+    // The same instant `seconds` later (or earlier), in the same timezone.
+    pub fn plus_seconds(&self, seconds: i64) -> Result<Self, TimeError> {
+        let target = self
+            .to_unix_seconds()
+            .checked_add(seconds)
+            .ok_or(TimeError::OutOfRange)?;
+        Self::from_unix_seconds_in(target, self)
+    }
+
+    // The same instant with TimeZone 0 and no Daylight flags. Authenticated
+    // variable TimeStamps also need nanosecond cleared.
+    pub fn to_utc(&self) -> Result<Self, TimeError> {
+        Self::from_unix_seconds_in(
+            self.to_unix_seconds(),
+            &Self {
+                nanosecond: self.nanosecond,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+impl Ord for Time {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.to_unix_seconds(), self.nanosecond).cmp(&(other.to_unix_seconds(), other.nanosecond))
+    }
+}
+impl PartialOrd for Time {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+// Equal means the same instant, so 10:00+01:00 == 09:00Z.
+impl PartialEq for Time {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Time {}
+
+// ISO-8601 / RFC 3339. An unspecified timezone is written without an offset.
+impl fmt::Display for Time {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        ))?;
+        if self.nanosecond != 0 {
+            let mut fraction = self.nanosecond;
+            let mut digits = 9;
+            while fraction.is_multiple_of(10) {
+                fraction /= 10;
+                digits -= 1;
+            }
+            fmtr.write_fmt(format_args!(".{:0width$}", fraction, width = digits))?;
+        }
+        match self.timezone {
+            UNSPECIFIED_TIMEZONE => Ok(()),
+            0 => fmtr.write_str("Z"),
+            x => fmtr.write_fmt(format_args!(
+                "{}{:02}:{:02}",
+                if x < 0 { '-' } else { '+' },
+                x.unsigned_abs() / 60,
+                x.unsigned_abs() % 60
+            )),
+        }
+    }
+}
+
+fn parse_digits(digits: Option<&[u8]>) -> Result<u32, TimeError> {
+    match digits {
+        Some(digits) if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) => Ok(digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + (digit - b'0') as u32)),
+        _ => Err(TimeError::BadFormat),
+    }
+}
+
+// Accepts YYYY-MM-DDTHH:MM:SS with an optional fraction of up to nine digits
+// and an optional Z or +/-HH:MM. 'T' may also be a space.
+impl FromStr for Time {
+    type Err = TimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        let field = |start: usize, end: usize| parse_digits(bytes.get(start..end));
+        for (index, separators) in [(4, "-"), (7, "-"), (10, "Tt "), (13, ":"), (16, ":")] {
+            match bytes.get(index) {
+                Some(byte) if separators.as_bytes().contains(byte) => (),
+                _ => return Err(TimeError::BadFormat),
+            }
+        }
+
+        let mut time = Self {
+            year: field(0, 4)? as u16,
+            month: field(5, 7)? as u8,
+            day: field(8, 10)? as u8,
+            hour: field(11, 13)? as u8,
+            minute: field(14, 16)? as u8,
+            second: field(17, 19)? as u8,
+            ..Default::default()
+        };
+
+        let mut rest = &bytes[19..];
+        if let [b'.', fraction @ ..] = rest {
+            let digits = fraction.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 9 {
+                return Err(TimeError::BadFormat);
+            }
+            time.nanosecond =
+                parse_digits(Some(&fraction[..digits]))? * 10u32.pow(9 - digits as u32);
+            rest = &fraction[digits..];
+        }
+        time.timezone = match rest {
+            [] => UNSPECIFIED_TIMEZONE,
+            [b'Z'] | [b'z'] => 0,
+            [sign @ (b'+' | b'-'), hours @ .., b':', _, _] if hours.len() == 2 => {
+                let minutes = parse_digits(Some(hours))? * 60 + parse_digits(rest.get(4..6))?;
+                let minutes = i16::try_from(minutes).map_err(|_| TimeError::BadFormat)?;
+                if *sign == b'-' {
+                    -minutes
+                } else {
+                    minutes
+                }
+            }
+            _ => return Err(TimeError::BadFormat),
+        };
+
+        time.validate()?;
+        Ok(time)
+    }
+}
+
 impl From<Time> for efi::Time {
     fn from(f: Time) -> Self {
         Self {
@@ -54,6 +390,11 @@ impl TryFrom<efi::Time> for Time {
         if ((tf.daylight & 0xFC) != 0) || (tid && !tad) {
             return Err(efi::Status::INVALID_PARAMETER);
         }
+        if tf.timezone != UNSPECIFIED_TIMEZONE
+            && !(-MAX_TIMEZONE..=MAX_TIMEZONE).contains(&tf.timezone)
+        {
+            return Err(efi::Status::INVALID_PARAMETER);
+        }
 
         Ok(Self {
             year: tf.year,
@@ -68,3 +409,165 @@ impl TryFrom<efi::Time> for Time {
         })
     }
 }
+
+// What GetTime() reports about the platform's real-time clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeCapabilities {
+    // Counts per second; 1 for a normal PC-AT CMOS RTC.
+    pub resolution: u32,
+    // Error rate in parts per million, times 1e6.
+    pub accuracy: u32,
+    // Whether SetTime() clears the time below `resolution`.
+    pub sets_to_zero: bool,
+}
+
+impl From<efi::TimeCapabilities> for TimeCapabilities {
+    fn from(f: efi::TimeCapabilities) -> Self {
+        Self {
+            resolution: f.resolution,
+            accuracy: f.accuracy,
+            sets_to_zero: f.sets_to_zero.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> Time {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn time_should_validate_fields() {
+        assert!(time("2024-02-29T00:00:00Z").validate().is_ok());
+        assert_eq!(
+            "2023-02-29T00:00:00Z".parse::<Time>(),
+            Err(TimeError::BadDate)
+        );
+        assert_eq!(
+            "1900-02-29T00:00:00Z".parse::<Time>(),
+            Err(TimeError::BadDate)
+        );
+        assert!(time("2000-02-29T00:00:00Z").validate().is_ok());
+        assert_eq!(
+            "2023-04-31T00:00:00Z".parse::<Time>(),
+            Err(TimeError::BadDate)
+        );
+        assert_eq!(
+            "2023-01-01T24:00:00Z".parse::<Time>(),
+            Err(TimeError::BadTime)
+        );
+        assert_eq!(
+            "2023-01-01T00:00:00+25:00".parse::<Time>(),
+            Err(TimeError::BadTimezone(1500))
+        );
+
+        let mut bad = time("2023-01-01T00:00:00Z");
+        bad.daylight = TIME_IN_DAYLIGHT;
+        assert_eq!(
+            bad.validate(),
+            Err(TimeError::BadDaylight(TIME_IN_DAYLIGHT))
+        );
+        bad.daylight = 0;
+        bad.year = 1899;
+        assert_eq!(bad.validate(), Err(TimeError::OutOfRange));
+
+        // Firmware-supplied times get their timezone checked on the way in.
+        let mut raw = efi::Time::from(time("2023-01-01T00:00:00-24:00"));
+        assert!(Time::try_from(raw).is_ok());
+        raw.timezone = i16::MIN;
+        assert_eq!(Time::try_from(raw), Err(efi::Status::INVALID_PARAMETER));
+        raw.timezone = UNSPECIFIED_TIMEZONE;
+        assert!(Time::try_from(raw).is_ok());
+    }
+
+    #[test]
+    fn time_should_convert_to_and_from_unix_seconds() {
+        assert_eq!(time("1970-01-01T00:00:00Z").to_unix_seconds(), 0);
+        assert_eq!(time("2000-02-29T12:00:00Z").to_unix_seconds(), 951825600);
+        assert_eq!(time("1900-01-01T00:00:00Z").to_unix_seconds(), -2208988800);
+        assert_eq!(
+            time("2000-02-29T13:30:00+01:30").to_unix_seconds(),
+            951825600
+        );
+
+        let converted = Time::from_unix_seconds(951825600).unwrap();
+        assert_eq!(converted.to_string(), "2000-02-29T12:00:00Z");
+        assert_eq!(
+            Time::from_unix_seconds(i64::MAX / 2),
+            Err(TimeError::OutOfRange)
+        );
+        let ahead = time("2000-01-01T00:00:00+01:00");
+        assert_eq!(
+            ahead.plus_seconds(i64::MAX - ahead.to_unix_seconds()),
+            Err(TimeError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn time_should_order_by_instant() {
+        let utc = time("2022-04-18T09:00:00Z");
+        let plus_one = time("2022-04-18T10:00:00+01:00");
+        assert_eq!(utc, plus_one);
+        assert!(utc < time("2022-04-18T09:00:01Z"));
+        assert!(plus_one < time("2022-04-18T09:00:00.000000001Z"));
+        assert!(time("2022-04-18T08:30:00-01:00") > utc);
+        assert_eq!(time("2022-04-18T09:00:00"), utc);
+    }
+
+    #[test]
+    fn time_should_format_and_parse_iso_8601() {
+        for text in [
+            "2022-04-18T12:34:56Z",
+            "2022-04-18T12:34:56.5-07:30",
+            "2022-04-18T12:34:56.000000001+14:00",
+            "2022-04-18T12:34:56",
+        ] {
+            assert_eq!(time(text).to_string(), text);
+        }
+        assert_eq!(
+            time("2022-04-18 12:34:56z").to_string(),
+            "2022-04-18T12:34:56Z"
+        );
+        assert_eq!(time("2022-04-18T12:34:56.25Z").nanosecond, 250_000_000);
+        assert_eq!(time("2022-04-18T12:34:56").timezone, UNSPECIFIED_TIMEZONE);
+        // Out-of-range timezones set by hand still format without panicking.
+        let odd = Time {
+            timezone: i16::MIN,
+            ..time("2022-04-18T12:34:56Z")
+        };
+        assert_eq!(odd.to_string(), "2022-04-18T12:34:56-546:08");
+
+        for bad in [
+            "",
+            "2022-04-18",
+            "2022/04/18T12:34:56Z",
+            "2022-04-18T12:34:56.Z",
+            "2022-04-18T12:34:56.1234567890Z",
+            "2022-04-18T12:34:56+0100",
+            "2022-04-18T12:34:56Zjunk",
+        ] {
+            assert_eq!(bad.parse::<Time>(), Err(TimeError::BadFormat), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn time_should_add_seconds_in_its_own_timezone() {
+        let start = time("2023-12-31T23:59:30-05:00");
+        assert_eq!(
+            start.plus_seconds(45).unwrap().to_string(),
+            "2024-01-01T00:00:15-05:00"
+        );
+        assert_eq!(
+            start.plus_seconds(-86400).unwrap().to_string(),
+            "2023-12-30T23:59:30-05:00"
+        );
+        assert_eq!(start.to_utc().unwrap().to_string(), "2024-01-01T04:59:30Z");
+        assert_eq!(
+            time("9999-12-31T23:59:59Z").plus_seconds(1),
+            Err(TimeError::OutOfRange)
+        );
+    }
+}
@@ -0,0 +1,342 @@
+/* @file
+A snapshot of every platform Secure Boot variable.
+
+SecureBootStatus::read_current() pulls the mode variables, the key databases
+and their vendor defaults in one go. Missing variables are None rather than
+errors, and a database that doesn't parse keeps its raw contents so it can
+still be backed up or reported.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+
+use crate::auth_variable::*;
+use crate::image_authentication::{SignatureDatabase, SignatureDatabaseError};
+use crate::variable::{EfiVariable, EFI_GLOBAL_VARIABLE_GUID};
+use crate::UefiResult;
+
+// The platform mode, from the SetupMode/AuditMode/DeployedMode combination
+// described in the UEFI spec's Secure Boot mode transitions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecureBootMode {
+    Setup,
+    User,
+    Audit,
+    Deployed,
+    // A combination the spec doesn't allow, or SetupMode missing.
+    Unknown,
+}
+
+impl SecureBootMode {
+    // Setup and Audit both have SetupMode set, so firmware takes a PK signed
+    // only by itself. Enrolling it moves Setup to User and Audit to Deployed.
+    pub fn accepts_self_signed_pk(&self) -> bool {
+        matches!(self, Self::Setup | Self::Audit)
+    }
+}
+
+#[derive(Clone)]
+pub struct DatabaseSnapshot {
+    // Raw contents and attributes, exactly as read.
+    pub variable: EfiVariable,
+    pub database: Result<SignatureDatabase, SignatureDatabaseError>,
+}
+
+impl DatabaseSnapshot {
+    fn new(variable: EfiVariable) -> Self {
+        Self {
+            database: SignatureDatabase::from_bytes(&variable.data),
+            variable,
+        }
+    }
+
+    // For contents that came from somewhere other than firmware, such as a
+    // file. The GUID is the one `name` lives under; attributes are zero.
+    pub fn from_bytes(name: &str, data: &[u8]) -> Self {
+        Self::new(EfiVariable {
+            name: String::from(name),
+            guid: get_secure_boot_variable_guid(name).unwrap_or(EFI_GLOBAL_VARIABLE_GUID),
+            data: Vec::from(data),
+            attributes: 0,
+        })
+    }
+}
+
+impl fmt::Debug for DatabaseSnapshot {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match &self.database {
+            Ok(database) if fmtr.alternate() => fmtr.write_fmt(format_args!("{:#?}", database)),
+            Ok(database) => fmtr.write_fmt(format_args!(
+                "{} ({} lists, {} entries)",
+                self.variable.name,
+                database.entries.len(),
+                database.signature_entries().len()
+            )),
+            Err(err) => fmtr.write_fmt(format_args!(
+                "{} (unparseable, {} bytes: {})",
+                self.variable.name,
+                self.variable.data.len(),
+                err
+            )),
+        }
+    }
+}
+
+// One set of key databases: either the live ones or the vendor defaults.
+#[derive(Clone, Default)]
+pub struct KeyDatabases {
+    pub pk: Option<DatabaseSnapshot>,
+    pub kek: Option<DatabaseSnapshot>,
+    pub db: Option<DatabaseSnapshot>,
+    pub dbx: Option<DatabaseSnapshot>,
+    pub dbt: Option<DatabaseSnapshot>,
+    pub dbr: Option<DatabaseSnapshot>,
+}
+
+impl KeyDatabases {
+    // (label, snapshot) pairs in the order they're usually listed.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Option<&DatabaseSnapshot>)> {
+        IntoIterator::into_iter([
+            (EFI_PLATFORM_KEY_NAME, self.pk.as_ref()),
+            (EFI_KEY_EXCHANGE_KEY_NAME, self.kek.as_ref()),
+            (EFI_IMAGE_SECURITY_DATABASE, self.db.as_ref()),
+            (EFI_IMAGE_SECURITY_DATABASE1, self.dbx.as_ref()),
+            (EFI_IMAGE_SECURITY_DATABASE2, self.dbt.as_ref()),
+            (EFI_IMAGE_SECURITY_DATABASE3, self.dbr.as_ref()),
+        ])
+    }
+}
+
+impl fmt::Debug for KeyDatabases {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        for (label, snapshot) in self.iter() {
+            match snapshot {
+                Some(snapshot) => fmtr.write_fmt(format_args!("\t{}: {:?}\n", label, snapshot))?,
+                None => fmtr.write_fmt(format_args!("\t{}: not present\n", label))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct SecureBootStatus {
+    // Each mode variable's first byte, or None if it isn't there.
+    pub secure_boot: Option<u8>,
+    pub setup_mode: Option<u8>,
+    pub audit_mode: Option<u8>,
+    pub deployed_mode: Option<u8>,
+    pub vendor_keys: Option<u8>,
+    pub current: KeyDatabases,
+    pub defaults: KeyDatabases,
+}
+
+impl SecureBootStatus {
+    pub fn read_current() -> UefiResult<Self> {
+        Self::read_with(|name, guid| match EfiVariable::get_variable(name, guid) {
+            Ok(variable) => Ok(Some(variable)),
+            Err(efi::Status::NOT_FOUND) => Ok(None),
+            Err(err) => Err(err),
+        })
+    }
+
+    // `read` returns Ok(None) for a variable that doesn't exist. Split out of
+    // read_current() so the snapshot can be built from something other than
+    // live firmware.
+    pub fn read_with<F>(mut read: F) -> UefiResult<Self>
+    where
+        F: FnMut(&str, &efi::Guid) -> UefiResult<Option<EfiVariable>>,
+    {
+        let mut mode = |name| -> UefiResult<Option<u8>> {
+            Ok(read(name, &EFI_GLOBAL_VARIABLE_GUID)?.and_then(|v| v.data.first().copied()))
+        };
+        let secure_boot = mode(EFI_SECURE_BOOT_MODE_NAME)?;
+        let setup_mode = mode(EFI_SETUP_MODE_NAME)?;
+        let audit_mode = mode(EFI_AUDIT_MODE_NAME)?;
+        let deployed_mode = mode(EFI_DEPLOYED_MODE_NAME)?;
+        let vendor_keys = mode(EFI_VENDOR_KEYS_NAME)?;
+
+        let mut database = |name, guid| -> UefiResult<Option<DatabaseSnapshot>> {
+            Ok(read(name, &guid)?.map(DatabaseSnapshot::new))
+        };
+        let current = KeyDatabases {
+            pk: database(EFI_PLATFORM_KEY_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            kek: database(EFI_KEY_EXCHANGE_KEY_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            db: database(
+                EFI_IMAGE_SECURITY_DATABASE,
+                EFI_IMAGE_SECURITY_DATABASE_GUID,
+            )?,
+            dbx: database(
+                EFI_IMAGE_SECURITY_DATABASE1,
+                EFI_IMAGE_SECURITY_DATABASE_GUID,
+            )?,
+            dbt: database(
+                EFI_IMAGE_SECURITY_DATABASE2,
+                EFI_IMAGE_SECURITY_DATABASE_GUID,
+            )?,
+            dbr: database(
+                EFI_IMAGE_SECURITY_DATABASE3,
+                EFI_IMAGE_SECURITY_DATABASE_GUID,
+            )?,
+        };
+        let defaults = KeyDatabases {
+            pk: database(EFI_PLATFORM_KEY_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            kek: database(EFI_KEY_EXCHANGE_KEY_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            db: database(EFI_DB_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            dbx: database(EFI_DBX_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            dbt: database(EFI_DBT_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+            dbr: database(EFI_DBR_DEFAULT_NAME, EFI_GLOBAL_VARIABLE_GUID)?,
+        };
+
+        Ok(Self {
+            secure_boot,
+            setup_mode,
+            audit_mode,
+            deployed_mode,
+            vendor_keys,
+            current,
+            defaults,
+        })
+    }
+
+    pub fn is_secure_boot_enabled(&self) -> bool {
+        self.secure_boot == Some(SECURE_BOOT_MODE_ENABLE)
+    }
+
+    pub fn mode(&self) -> SecureBootMode {
+        // Firmware without the UEFI 2.5 mode variables is never in Audit or Deployed.
+        let audit = self.audit_mode.unwrap_or(0) != 0;
+        let deployed = self.deployed_mode.unwrap_or(0) != 0;
+        match (self.setup_mode, audit, deployed) {
+            (Some(SETUP_MODE), false, false) => SecureBootMode::Setup,
+            (Some(USER_MODE), false, false) => SecureBootMode::User,
+            (Some(SETUP_MODE), true, false) => SecureBootMode::Audit,
+            (Some(USER_MODE), false, true) => SecureBootMode::Deployed,
+            _ => SecureBootMode::Unknown,
+        }
+    }
+}
+
+impl fmt::Debug for SecureBootStatus {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |value: Option<u8>| match value {
+            Some(value) => alloc::format!("{}", value),
+            None => alloc::string::String::from("not present"),
+        };
+        if fmtr.alternate() {
+            fmtr.write_str("SecureBootStatus:\n")?;
+            fmtr.write_fmt(format_args!("\tMode: {:?}\n", self.mode()))?;
+            fmtr.write_fmt(format_args!("\tSecureBoot: {}\n", flag(self.secure_boot)))?;
+            fmtr.write_fmt(format_args!("\tSetupMode: {}\n", flag(self.setup_mode)))?;
+            fmtr.write_fmt(format_args!("\tAuditMode: {}\n", flag(self.audit_mode)))?;
+            fmtr.write_fmt(format_args!(
+                "\tDeployedMode: {}\n",
+                flag(self.deployed_mode)
+            ))?;
+            fmtr.write_fmt(format_args!("\tVendorKeys: {}\n", flag(self.vendor_keys)))?;
+            fmtr.write_str("Current Keys:\n")?;
+            fmtr.write_fmt(format_args!("{:?}", self.current))?;
+            fmtr.write_str("Vendor Defaults:\n")?;
+            fmtr.write_fmt(format_args!("{:?}", self.defaults))
+        } else {
+            fmtr.write_fmt(format_args!(
+                "SecureBootStatus {{ Mode: {:?}, SecureBoot: {} }}",
+                self.mode(),
+                flag(self.secure_boot)
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::sig_lists;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    fn variable(name: &str, guid: &efi::Guid, data: &[u8]) -> EfiVariable {
+        EfiVariable {
+            name: String::from(name),
+            guid: *guid,
+            data: Vec::from(data),
+            attributes: EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+        }
+    }
+
+    fn snapshot(variables: &[EfiVariable]) -> SecureBootStatus {
+        SecureBootStatus::read_with(|name, guid| {
+            Ok(variables
+                .iter()
+                .find(|v| v.name == name && v.guid == *guid)
+                .cloned())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn secure_boot_status_should_collect_variables() {
+        let global = EFI_GLOBAL_VARIABLE_GUID;
+        let status = snapshot(&[
+            variable(
+                EFI_SECURE_BOOT_MODE_NAME,
+                &global,
+                &[SECURE_BOOT_MODE_ENABLE],
+            ),
+            variable(EFI_SETUP_MODE_NAME, &global, &[USER_MODE]),
+            variable(EFI_PLATFORM_KEY_NAME, &global, sig_lists::PK),
+            variable(EFI_KEY_EXCHANGE_KEY_DEFAULT_NAME, &global, sig_lists::KEK),
+            // db lives under the image security GUID, so this one is ignored.
+            variable(EFI_IMAGE_SECURITY_DATABASE, &global, sig_lists::DB),
+            variable(
+                EFI_IMAGE_SECURITY_DATABASE1,
+                &EFI_IMAGE_SECURITY_DATABASE_GUID,
+                &[0xFF; 8],
+            ),
+        ]);
+
+        assert!(status.is_secure_boot_enabled());
+        assert_eq!(status.mode(), SecureBootMode::User);
+        assert_eq!(status.vendor_keys, None);
+        assert_eq!(
+            status.current.pk.unwrap().database.unwrap().entries.len(),
+            1
+        );
+        assert!(status.defaults.kek.unwrap().database.is_ok());
+        assert!(status.current.db.is_none());
+        let dbx = status.current.dbx.unwrap();
+        assert!(dbx.database.is_err());
+        assert_eq!(dbx.variable.data, &[0xFF; 8]);
+    }
+
+    #[test]
+    fn secure_boot_status_should_derive_mode() {
+        let global = EFI_GLOBAL_VARIABLE_GUID;
+        let mode = |setup: u8, audit: u8, deployed: u8| {
+            snapshot(&[
+                variable(EFI_SETUP_MODE_NAME, &global, &[setup]),
+                variable(EFI_AUDIT_MODE_NAME, &global, &[audit]),
+                variable(EFI_DEPLOYED_MODE_NAME, &global, &[deployed]),
+            ])
+            .mode()
+        };
+        assert_eq!(mode(1, 0, 0), SecureBootMode::Setup);
+        assert_eq!(mode(0, 0, 0), SecureBootMode::User);
+        assert_eq!(mode(1, 1, 0), SecureBootMode::Audit);
+        assert_eq!(mode(0, 0, 1), SecureBootMode::Deployed);
+        assert_eq!(mode(1, 0, 1), SecureBootMode::Unknown);
+        assert_eq!(snapshot(&[]).mode(), SecureBootMode::Unknown);
+
+        assert!(SecureBootMode::Setup.accepts_self_signed_pk());
+        assert!(SecureBootMode::Audit.accepts_self_signed_pk());
+        assert!(!SecureBootMode::User.accepts_self_signed_pk());
+        assert!(!SecureBootMode::Deployed.accepts_self_signed_pk());
+    }
+}
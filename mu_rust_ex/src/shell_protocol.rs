@@ -2,12 +2,14 @@
 //
 // SPDX-License-Identifier: BSD-2-Clause-Patent
 
+use crate::boot::uefi_bs;
 use crate::protocol_utility::{
     ManagedProtocol, RustProtocol, RustProtocolError as RPError, RustProtocolResult as RPResult,
 };
 use crate::UefiResult;
 
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::convert::TryInto;
 
@@ -238,6 +240,20 @@ impl Protocol {
         }
     }
 
+    pub fn delete_file_by_name(&self, name: &str) -> RPResult<()> {
+        let prot_guard = self.inner.lock();
+        let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
+        let efi_name = OsString::from(name);
+
+        let status = (prot.delete_file_by_name)(efi_name.as_ptr());
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(RPError::Efi(status))
+        }
+    }
+
     fn read_file(&self, handle: FileHandle, buffer: &mut [u8]) -> RPResult<usize> {
         let prot_guard = self.inner.lock();
         let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
@@ -274,6 +290,19 @@ impl Protocol {
         }
     }
 
+    fn set_file_position(&self, handle: FileHandle, position: u64) -> RPResult<()> {
+        let prot_guard = self.inner.lock();
+        let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
+
+        let status = (prot.set_file_position)(handle, position);
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(RPError::Efi(status))
+        }
+    }
+
     fn flush_file(&self, handle: FileHandle) -> RPResult<()> {
         let prot_guard = self.inner.lock();
         let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
@@ -287,6 +316,44 @@ impl Protocol {
         }
     }
 
+    // Renames the file in place by writing an EFI_FILE_INFO that only differs
+    // in FileName. `name` is relative to the directory holding the file.
+    fn rename_file(&self, handle: FileHandle, name: &str) -> RPResult<()> {
+        let prot_guard = self.inner.lock();
+        let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
+
+        let old_info = (prot.get_file_info)(handle);
+        if old_info.is_null() {
+            return Err(RPError::Efi(efi::Status::DEVICE_ERROR));
+        }
+
+        let header_size = core::mem::size_of::<efi::protocols::file::Info>();
+        let mut file_name: Vec<u16> = name.encode_utf16().collect();
+        file_name.push(0);
+        let info_size = header_size + file_name.len() * core::mem::size_of::<u16>();
+        // Backed by u64s so the new EFI_FILE_INFO is suitably aligned.
+        let mut buffer = vec![0u64; info_size.div_ceil(8)];
+        let new_info = buffer.as_mut_ptr() as *mut efi::protocols::file::Info;
+        unsafe {
+            core::ptr::copy_nonoverlapping(old_info, new_info, 1);
+            (*new_info).size = info_size as u64;
+            core::ptr::copy_nonoverlapping(
+                file_name.as_ptr(),
+                (new_info as *mut u8).add(header_size) as *mut u16,
+                file_name.len(),
+            );
+        }
+        _ = uefi_bs().free_pool(old_info as *mut _);
+
+        let status = (prot.set_file_info)(handle, new_info);
+
+        if !status.is_error() {
+            Ok(())
+        } else {
+            Err(RPError::Efi(status))
+        }
+    }
+
     fn close_file(&self, handle: FileHandle) -> RPResult<()> {
         let prot_guard = self.inner.lock();
         let prot = prot_guard.as_ref().ok_or(RPError::Unregistered)?;
@@ -345,6 +412,25 @@ impl ShellFile {
     pub fn get_size(&self) -> RPResult<usize> {
         self.protocol.get_file_size(self.handle)
     }
+    // Gives the file a new name within the same directory. Fails if a file
+    // with that name already exists.
+    pub fn rename(&self, name: &str) -> RPResult<()> {
+        self.protocol.rename_file(self.handle, name)
+    }
+    // The next read() or write() starts at `position` bytes into the file.
+    pub fn set_position(&self, position: usize) -> RPResult<()> {
+        self.protocol
+            .set_file_position(self.handle, position as u64)
+    }
+}
+// Allows formatted output (e.g. `{:#?}` renderings) to be written straight to a file.
+impl core::fmt::Write for ShellFile {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        match self.write(s.as_bytes()) {
+            Ok(size) if size == s.len() => Ok(()),
+            _ => Err(core::fmt::Error),
+        }
+    }
 }
 impl Drop for ShellFile {
     fn drop(&mut self) {
@@ -0,0 +1,253 @@
+/* @file
+Signing backends for authenticated variable updates.
+
+A Signer takes the TBS data from EfiAuthVariable2::get_tbs_data() and hands
+back a PKCS#7 SignedData along with the certificate chain it embedded. The
+payload builder doesn't care where the signature came from, so new backends
+(smart cards, TPMs, ...) only need to implement the trait.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use r_efi::efi;
+use rsa::rand_core::CryptoRngCore;
+use rsa::traits::PublicKeyParts;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+
+use crate::der::{self, DerError};
+use crate::pkcs7;
+use crate::x509::{Certificate, PublicKey};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignerError {
+    // No signing certificate was provided.
+    EmptyChain,
+    // (error) A certificate in the chain failed to parse.
+    BadCertificate(DerError),
+    // The private key doesn't belong to the signing certificate.
+    KeyMismatch,
+    // The backend couldn't produce a signature.
+    SigningFailed,
+    Efi(efi::Status),
+}
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyChain => write!(f, "No signing certificate"),
+            Self::BadCertificate(err) => write!(f, "Invalid signing certificate ({})", err),
+            Self::KeyMismatch => write!(f, "Private key doesn't match the signing certificate"),
+            Self::SigningFailed => write!(f, "Signing failed"),
+            Self::Efi(status) => write!(f, "Signing failed ({:?})", status),
+        }
+    }
+}
+impl From<SignerError> for efi::Status {
+    fn from(f: SignerError) -> Self {
+        match f {
+            SignerError::EmptyChain => efi::Status::NOT_READY,
+            SignerError::BadCertificate(x) => x.into(),
+            SignerError::KeyMismatch => efi::Status::INVALID_PARAMETER,
+            SignerError::SigningFailed => efi::Status::DEVICE_ERROR,
+            SignerError::Efi(x) => x,
+        }
+    }
+}
+
+pub struct SignerOutput {
+    // DER ContentInfo-wrapped SignedData, ready to go into CertData.
+    pub signed_data: Vec<u8>,
+    // DER certificates embedded in signed_data, signing certificate first.
+    pub chain: Vec<Vec<u8>>,
+}
+
+pub trait Signer {
+    fn sign(&mut self, tbs: &[u8]) -> Result<SignerOutput, SignerError>;
+}
+
+// Wraps a raw signature from any backend in the SignedData that firmware expects.
+pub fn build_signer_output(
+    chain: &[Vec<u8>],
+    digest_algorithm: &[u8],
+    signature_algorithm: &[u8],
+    signature: &[u8],
+) -> Result<SignerOutput, SignerError> {
+    let signer = Certificate::from_der(chain.first().ok_or(SignerError::EmptyChain)?)
+        .map_err(SignerError::BadCertificate)?;
+    let certificates: Vec<&[u8]> = chain.iter().map(|certificate| &certificate[..]).collect();
+
+    Ok(SignerOutput {
+        signed_data: pkcs7::encode_signed_data(
+            &certificates,
+            &signer,
+            digest_algorithm,
+            signature_algorithm,
+            signature,
+        ),
+        chain: Vec::from(chain),
+    })
+}
+
+// In-process RSA PKCS#1 v1.5 with SHA-256. The key lives in memory for as
+// long as the signer does; RsaPrivateKey zeroizes itself on drop. `rng`
+// blinds the private-key operation so its timing doesn't depend on the key.
+pub struct RsaSigner<R: CryptoRngCore> {
+    key: RsaPrivateKey,
+    chain: Vec<Vec<u8>>,
+    rng: R,
+}
+
+impl<R: CryptoRngCore> RsaSigner<R> {
+    // `chain` is the signing certificate followed by any intermediates
+    // that should travel with the signature.
+    pub fn new(key: RsaPrivateKey, chain: Vec<Vec<u8>>, rng: R) -> Result<Self, SignerError> {
+        let certificate = Certificate::from_der(chain.first().ok_or(SignerError::EmptyChain)?)
+            .map_err(SignerError::BadCertificate)?;
+        for intermediate in chain.iter().skip(1) {
+            Certificate::from_der(intermediate).map_err(SignerError::BadCertificate)?;
+        }
+
+        if !rsa_key_matches(&certificate, &key) {
+            return Err(SignerError::KeyMismatch);
+        }
+        Ok(Self { key, chain, rng })
+    }
+}
+
+pub(crate) fn rsa_key_matches(certificate: &Certificate, key: &RsaPrivateKey) -> bool {
+    match certificate.public_key {
+        PublicKey::Rsa { modulus, exponent } => {
+            modulus == &key.n().to_bytes_be()[..] && exponent == &key.e().to_bytes_be()[..]
+        }
+        _ => false,
+    }
+}
+
+impl<R: CryptoRngCore> Signer for RsaSigner<R> {
+    fn sign(&mut self, tbs: &[u8]) -> Result<SignerOutput, SignerError> {
+        let digest = Sha256::digest(tbs);
+        let signature = self
+            .key
+            .sign_with_rng(&mut self.rng, Pkcs1v15Sign::new::<Sha256>(), &digest)
+            .map_err(|_| SignerError::SigningFailed)?;
+        build_signer_output(
+            &self.chain,
+            der::OID_SHA256,
+            der::OID_RSA_ENCRYPTION,
+            &signature,
+        )
+    }
+}
+
+// Stands in for a real backend. Produces well-formed SignedData around a
+// fixed signature value and remembers everything it was asked to sign.
+pub struct MockSigner {
+    pub chain: Vec<Vec<u8>>,
+    pub signature: Vec<u8>,
+    pub requests: Vec<Vec<u8>>,
+}
+
+impl MockSigner {
+    pub fn new(certificate: &[u8]) -> Self {
+        Self {
+            chain: vec![Vec::from(certificate)],
+            signature: vec![0xA5; 256],
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl Signer for MockSigner {
+    fn sign(&mut self, tbs: &[u8]) -> Result<SignerOutput, SignerError> {
+        self.requests.push(Vec::from(tbs));
+        build_signer_output(
+            &self.chain,
+            der::OID_SHA256,
+            der::OID_RSA_ENCRYPTION,
+            &self.signature,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_variable::{EfiAuthVariable2Ref, EFI_IMAGE_SECURITY_DATABASE_GUID};
+    use crate::pkcs7::SignedData;
+    use crate::test_data::{auth_files, sig_lists, TestRng};
+    use rsa::pkcs8::DecodePrivateKey;
+
+    fn test_signer() -> RsaSigner<TestRng> {
+        RsaSigner::new(
+            RsaPrivateKey::from_pkcs8_der(auth_files::TEST_KEK_KEY_PKCS8).unwrap(),
+            vec![Vec::from(auth_files::TEST_KEK_CERT)],
+            TestRng::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rsa_signer_should_match_reference_signature() {
+        // PKCS#1 v1.5 is deterministic (blinding doesn't change the result),
+        // so re-signing the fixture must reproduce the SignedData that
+        // OpenSSL made for it exactly.
+        let auth = EfiAuthVariable2Ref::from_bytes(auth_files::DB_APPEND).unwrap();
+        let variable = auth.to_auth_variable(
+            "db",
+            &EFI_IMAGE_SECURITY_DATABASE_GUID,
+            efi::VARIABLE_NON_VOLATILE
+                | efi::VARIABLE_BOOTSERVICE_ACCESS
+                | efi::VARIABLE_RUNTIME_ACCESS
+                | efi::VARIABLE_TIME_BASED_AUTHENTICATED_WRITE_ACCESS
+                | efi::VARIABLE_APPEND_WRITE,
+        );
+
+        let mut signer = test_signer();
+        assert_eq!(
+            variable.get_signed_set_variable_data(&mut signer).unwrap(),
+            auth_files::DB_APPEND
+        );
+    }
+
+    #[test]
+    fn rsa_signer_should_reject_mismatched_certificates() {
+        let key = RsaPrivateKey::from_pkcs8_der(auth_files::TEST_KEK_KEY_PKCS8).unwrap();
+        let microsoft_kek = crate::image_authentication::SignatureList::from_bytes(sig_lists::KEK)
+            .unwrap()
+            .elements[0]
+            .data
+            .clone();
+        assert_eq!(
+            RsaSigner::new(key.clone(), vec![microsoft_kek], TestRng::default()).err(),
+            Some(SignerError::KeyMismatch)
+        );
+        assert_eq!(
+            RsaSigner::new(key.clone(), Vec::new(), TestRng::default()).err(),
+            Some(SignerError::EmptyChain)
+        );
+        assert!(matches!(
+            RsaSigner::new(key, vec![vec![0x30, 0x00]], TestRng::default()),
+            Err(SignerError::BadCertificate(_))
+        ));
+    }
+
+    #[test]
+    fn mock_signer_should_record_requests() {
+        let mut signer = MockSigner::new(auth_files::TEST_KEK_CERT);
+        let output = signer.sign(&[0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(signer.requests, vec![vec![0x01, 0x02, 0x03]]);
+        assert_eq!(output.chain.len(), 1);
+
+        let signed_data = SignedData::from_der(&output.signed_data).unwrap();
+        assert_eq!(signed_data.signers[0].signature, &[0xA5; 256][..]);
+        assert!(signed_data
+            .signer_certificate(&signed_data.signers[0])
+            .is_some());
+    }
+}
@@ -1,3 +1,34 @@
 #![allow(dead_code)]
 
+pub mod auth_files;
+pub mod pe_files;
 pub mod sig_lists;
+
+use rsa::rand_core::{impls, CryptoRng, Error, RngCore};
+
+// Deterministic stand-in for EFI_RNG_PROTOCOL. Only good enough to exercise
+// code paths that want randomness (e.g. RSA blinding), never for real keys.
+#[derive(Default)]
+pub struct TestRng(u64);
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        // splitmix64
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+impl CryptoRng for TestRng {}
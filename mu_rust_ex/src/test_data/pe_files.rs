@@ -0,0 +1,12 @@
+// conda's Windows entry point launcher (conda/shell/cli-64.exe from conda
+// 26.3.2, BSD-3-Clause), as signed by Anaconda, Inc. with signtool. Its
+// SpcIndirectDataContent carries the SHA-256 Authenticode digest the signing
+// tool computed, which makes it a known answer that doesn't depend on this
+// crate:
+//   openssl asn1parse -inform DER -in <certificate table past WIN_CERTIFICATE>
+pub const SIGNED_CLI_64: &[u8] = include_bytes!("cli-64.exe");
+
+pub const SIGNED_CLI_64_SHA256: [u8; 32] = [
+    0x15, 0x76, 0xC2, 0xB3, 0xB2, 0x89, 0xC3, 0x03, 0x20, 0x6F, 0x91, 0xB1, 0xE6, 0x38, 0xE6, 0x82,
+    0x74, 0x07, 0xFD, 0x38, 0x65, 0x29, 0x48, 0x42, 0x05, 0x52, 0x49, 0xF5, 0x74, 0xD8, 0x60, 0xB5,
+];
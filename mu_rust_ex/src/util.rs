@@ -5,33 +5,90 @@ use core::fmt;
 use core::fmt::Write;
 use r_efi::efi;
 
-use crate::image_authentication::{EFI_CERT_SHA256_GUID, EFI_CERT_X509_GUID};
+use crate::name_db;
+
+// Registry-format GUID string, e.g. "8BE4DF61-93CA-11D2-AA0D-00E098032B8C".
+pub fn guid_to_string(guid: &efi::Guid) -> String {
+    let guid_bytes = guid.as_bytes();
+    let mut buffer = String::new();
+    // Writing to a String can't fail.
+    let _ = buffer.write_fmt(format_args!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        u32::from_le_bytes(guid_bytes[..4].try_into().unwrap()),
+        u16::from_le_bytes(guid_bytes[4..6].try_into().unwrap()),
+        u16::from_le_bytes(guid_bytes[6..8].try_into().unwrap()),
+        guid_bytes[8],
+        guid_bytes[9],
+        guid_bytes[10],
+        guid_bytes[11],
+        guid_bytes[12],
+        guid_bytes[13],
+        guid_bytes[14],
+        guid_bytes[15],
+    ));
+    buffer
+}
+
+// The inverse of guid_to_string. Either case is accepted, as are surrounding braces.
+pub fn guid_from_str(string: &str) -> Option<efi::Guid> {
+    let string = string.trim();
+    let string = string
+        .strip_prefix('{')
+        .and_then(|inner| inner.strip_suffix('}'))
+        .unwrap_or(string);
+    let groups: Vec<&str> = string.split('-').collect();
+    let group_sizes = [8, 4, 4, 4, 12];
+    if groups.len() != group_sizes.len()
+        || groups
+            .iter()
+            .zip(group_sizes.iter())
+            .any(|(group, size)| group.len() != *size)
+    {
+        return None;
+    }
+
+    let tail = hex_from_str(groups[4])?;
+    Some(efi::Guid::from_fields(
+        u32::from_str_radix(groups[0], 16).ok()?,
+        u16::from_str_radix(groups[1], 16).ok()?,
+        u16::from_str_radix(groups[2], 16).ok()?,
+        u8::from_str_radix(&groups[3][..2], 16).ok()?,
+        u8::from_str_radix(&groups[3][2..], 16).ok()?,
+        tail[..].try_into().ok()?,
+    ))
+}
+
+// Decodes a string of hex digit pairs. ':' separators (as printed for
+// thumbprints) are ignored.
+pub fn hex_from_str(string: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = string.bytes().filter(|byte| *byte != b':').collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks_exact(2)
+        .map(|pair| u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+pub fn hex_to_string(data: &[u8]) -> String {
+    let mut buffer = String::new();
+    for byte in data.iter() {
+        let _ = buffer.write_fmt(format_args!("{:02X}", byte));
+    }
+    buffer
+}
 
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq)]
 pub struct DebugGuid(efi::Guid);
 impl fmt::Debug for DebugGuid {
     fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
-        let inner_string = match self.0 {
-            EFI_CERT_X509_GUID => String::from("EFI_CERT_X509_GUID"),
-            EFI_CERT_SHA256_GUID => String::from("EFI_CERT_SHA256_GUID"),
-            _ => {
-                let guid_bytes = self.0.as_bytes();
+        let inner_string = match name_db::lookup_guid(&self.0) {
+            Some(name) => name,
+            None => {
                 let mut buffer = String::new();
-                buffer.write_fmt(format_args!(
-                    "UNKNOWN({:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X})",
-                    u32::from_le_bytes(guid_bytes[..4].try_into().unwrap()),
-                    u16::from_le_bytes(guid_bytes[4..6].try_into().unwrap()),
-                    u16::from_le_bytes(guid_bytes[6..8].try_into().unwrap()),
-                    guid_bytes[8],
-                    guid_bytes[9],
-                    guid_bytes[10],
-                    guid_bytes[11],
-                    guid_bytes[12],
-                    guid_bytes[13],
-                    guid_bytes[14],
-                    guid_bytes[15],
-                ))?;
+                buffer.write_fmt(format_args!("UNKNOWN({})", guid_to_string(&self.0)))?;
                 buffer
             }
         };
@@ -44,6 +101,36 @@ impl From<efi::Guid> for DebugGuid {
     }
 }
 
+// Checked little-endian readers for pulling fields out of untrusted buffers.
+// These make no assumptions about alignment and return None rather than
+// reading past the end of the buffer.
+pub fn read_u16_le(buffer: &[u8], offset: usize) -> Option<u16> {
+    let bytes = buffer.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub fn read_u32_le(buffer: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buffer.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub fn read_u64_le(buffer: &[u8], offset: usize) -> Option<u64> {
+    let bytes = buffer.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub fn read_guid(buffer: &[u8], offset: usize) -> Option<efi::Guid> {
+    let bytes = buffer.get(offset..offset.checked_add(16)?)?;
+    Some(efi::Guid::from_fields(
+        read_u32_le(bytes, 0)?,
+        read_u16_le(bytes, 4)?,
+        read_u16_le(bytes, 6)?,
+        bytes[8],
+        bytes[9],
+        bytes[10..16].try_into().ok()?,
+    ))
+}
+
 #[derive(Clone)]
 pub struct DebugBuffer<'a>(&'a Vec<u8>);
 impl<'a> DebugBuffer<'a> {
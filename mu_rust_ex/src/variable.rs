@@ -7,9 +7,12 @@ use string::OsString;
 
 extern crate alloc;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::convert::TryFrom;
 
-use crate::{runtime, UefiResult};
+use crate::runtime::{self, SetVariableError};
+use crate::UefiResult;
 
 pub const EFI_GLOBAL_VARIABLE_GUID: efi::Guid = efi::Guid::from_fields(
     0x8BE4DF61,
@@ -38,10 +41,10 @@ impl EfiVariable {
 
         let data_size: usize = rs.get_variable_size(name_ptr, &mut local_guid as *mut _)?;
 
-        let mut data = Vec::<u8>::with_capacity(data_size);
+        let mut data = vec![0u8; data_size];
         let (data_size, attributes) =
             rs.get_variable(name_ptr, &mut local_guid as *mut _, &mut data)?;
-        unsafe { data.set_len(data_size) };
+        data.truncate(data_size);
 
         Ok(Self {
             name: String::from(name),
@@ -50,4 +53,44 @@ impl EfiVariable {
             attributes,
         })
     }
+
+    // Reads a variable and converts its payload into a richer type,
+    // e.g. `get_variable_as::<SignatureDatabase>("db", &EFI_IMAGE_SECURITY_DATABASE_GUID)`.
+    pub fn get_variable_as<T>(name: &str, guid: &efi::Guid) -> UefiResult<T>
+    where
+        T: TryFrom<Self>,
+        efi::Status: From<T::Error>,
+    {
+        Ok(T::try_from(Self::get_variable(name, guid)?)?)
+    }
+
+    // Writes `data` with `attributes`, replacing the current contents.
+    pub fn set(&self) -> Result<(), SetVariableError> {
+        self.write(self.attributes, &self.data)
+    }
+
+    // Adds `data` to the end of the current contents. For signature
+    // databases, firmware drops entries that are already present.
+    pub fn append(&self) -> Result<(), SetVariableError> {
+        self.write(self.attributes | efi::VARIABLE_APPEND_WRITE, &self.data)
+    }
+
+    // Authenticated variables can't be deleted this way; they need a signed
+    // empty payload written with set().
+    pub fn delete(&self) -> Result<(), SetVariableError> {
+        self.write(0, &[])
+    }
+
+    fn write(&self, attributes: u32, data: &[u8]) -> Result<(), SetVariableError> {
+        let rs = runtime::uefi_rs();
+
+        let mut name_string = OsString::from(self.name.as_str());
+        let mut local_guid = self.guid;
+        rs.set_variable(
+            name_string.as_mut_ptr() as *mut efi::Char16,
+            &mut local_guid as *mut _,
+            attributes,
+            data,
+        )
+    }
 }
@@ -0,0 +1,743 @@
+/* @file
+X.509 certificate decoding for the DER certificates held in
+EFI_CERT_X509_GUID signature lists (PK, KEK, db, dbx, ...).
+
+Signatures can be checked one link at a time with verify_issued_by();
+building and trusting a chain is up to the caller.
+
+Copyright (c) Microsoft Corporation.
+SPDX-License-Identifier: BSD-2-Clause-Patent
+
+*/
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use r_efi::efi;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::der::{
+    self, tag_context, tag_context_primitive, DerError, DerObject, DerReader, DerResult, Oid,
+};
+use crate::rustified;
+
+// RFC 5280 KeyUsage bits, numbered from the most significant bit of the first byte.
+pub const KEY_USAGE_DIGITAL_SIGNATURE: u16 = 1 << 0;
+pub const KEY_USAGE_NON_REPUDIATION: u16 = 1 << 1;
+pub const KEY_USAGE_KEY_ENCIPHERMENT: u16 = 1 << 2;
+pub const KEY_USAGE_DATA_ENCIPHERMENT: u16 = 1 << 3;
+pub const KEY_USAGE_KEY_AGREEMENT: u16 = 1 << 4;
+pub const KEY_USAGE_KEY_CERT_SIGN: u16 = 1 << 5;
+pub const KEY_USAGE_CRL_SIGN: u16 = 1 << 6;
+pub const KEY_USAGE_ENCIPHER_ONLY: u16 = 1 << 7;
+pub const KEY_USAGE_DECIPHER_ONLY: u16 = 1 << 8;
+
+const KEY_USAGE_NAMES: &[(u16, &str)] = &[
+    (KEY_USAGE_DIGITAL_SIGNATURE, "DigitalSignature"),
+    (KEY_USAGE_NON_REPUDIATION, "NonRepudiation"),
+    (KEY_USAGE_KEY_ENCIPHERMENT, "KeyEncipherment"),
+    (KEY_USAGE_DATA_ENCIPHERMENT, "DataEncipherment"),
+    (KEY_USAGE_KEY_AGREEMENT, "KeyAgreement"),
+    (KEY_USAGE_KEY_CERT_SIGN, "KeyCertSign"),
+    (KEY_USAGE_CRL_SIGN, "CrlSign"),
+    (KEY_USAGE_ENCIPHER_ONLY, "EncipherOnly"),
+    (KEY_USAGE_DECIPHER_ONLY, "DecipherOnly"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifyError {
+    // The key type or the digest/signature algorithm isn't one we can check.
+    UnsupportedAlgorithm,
+    // The signing certificate isn't included with the signature.
+    MissingCertificate,
+    // The signed messageDigest attribute doesn't match the content.
+    DigestMismatch,
+    // The signature doesn't match the data.
+    BadSignature,
+}
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedAlgorithm => write!(f, "Unsupported signature algorithm"),
+            Self::MissingCertificate => write!(f, "Signing certificate not included"),
+            Self::DigestMismatch => write!(f, "Message digest doesn't match the signed data"),
+            Self::BadSignature => write!(f, "Signature doesn't verify"),
+        }
+    }
+}
+impl From<VerifyError> for efi::Status {
+    fn from(f: VerifyError) -> Self {
+        match f {
+            VerifyError::UnsupportedAlgorithm => efi::Status::UNSUPPORTED,
+            VerifyError::MissingCertificate => efi::Status::SECURITY_VIOLATION,
+            VerifyError::DigestMismatch => efi::Status::SECURITY_VIOLATION,
+            VerifyError::BadSignature => efi::Status::SECURITY_VIOLATION,
+        }
+    }
+}
+
+// Hashes `data` with the digest algorithm named by `algorithm`.
+pub fn compute_digest(algorithm: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    match algorithm {
+        der::OID_SHA1 => Some(Sha1::digest(data).to_vec()),
+        der::OID_SHA256 => Some(Sha256::digest(data).to_vec()),
+        der::OID_SHA384 => Some(Sha384::digest(data).to_vec()),
+        der::OID_SHA512 => Some(Sha512::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+// The digest half of a combined RSA signature algorithm.
+pub fn signature_digest_algorithm(signature_algorithm: &[u8]) -> Option<&'static [u8]> {
+    match signature_algorithm {
+        der::OID_SHA1_WITH_RSA => Some(der::OID_SHA1),
+        der::OID_SHA256_WITH_RSA => Some(der::OID_SHA256),
+        der::OID_SHA384_WITH_RSA => Some(der::OID_SHA384),
+        der::OID_SHA512_WITH_RSA => Some(der::OID_SHA512),
+        _ => None,
+    }
+}
+
+// An X.501 Name. The raw encoding is kept so that issuer/subject
+// comparisons can be done byte-for-byte, the way firmware does them.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Name<'a> {
+    pub raw: &'a [u8],
+}
+
+impl<'a> Name<'a> {
+    pub(crate) fn from_der(object: DerObject<'a>) -> DerResult<Self> {
+        let name = Self {
+            raw: object.expect(der::TAG_SEQUENCE)?.raw,
+        };
+        // Walk it once so that later iteration can't fail.
+        name.try_attributes()?;
+        Ok(name)
+    }
+
+    fn try_attributes(&self) -> DerResult<Vec<(Oid<'a>, String)>> {
+        let mut result = Vec::new();
+        let mut rdns = DerReader::new(self.raw)
+            .read_last(der::TAG_SEQUENCE)?
+            .reader();
+        while !rdns.is_empty() {
+            let mut attributes = rdns.read(der::TAG_SET)?.reader();
+            while !attributes.is_empty() {
+                let mut attribute = attributes.read(der::TAG_SEQUENCE)?.reader();
+                let oid = attribute.read_any()?.as_oid()?;
+                let value = attribute.read_any()?.as_string()?;
+                attribute.finish()?;
+                result.push((oid, value));
+            }
+        }
+        Ok(result)
+    }
+
+    // (type, value) for every attribute, in encoding order.
+    pub fn attributes(&self) -> Vec<(Oid<'a>, String)> {
+        self.try_attributes().unwrap_or_default()
+    }
+
+    pub fn find(&self, oid: &[u8]) -> Option<String> {
+        self.attributes()
+            .into_iter()
+            .find(|(attribute, _)| attribute.0 == oid)
+            .map(|(_, value)| value)
+    }
+
+    pub fn common_name(&self) -> Option<String> {
+        self.find(der::OID_COMMON_NAME)
+    }
+
+    pub fn organization(&self) -> Option<String> {
+        self.find(der::OID_ORGANIZATION)
+    }
+}
+
+// Renders in the familiar "C=US, O=Example, CN=Example CA" form.
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (oid, value)) in self.attributes().iter().enumerate() {
+            if index > 0 {
+                fmtr.write_str(", ")?;
+            }
+            fmtr.write_fmt(format_args!("{:?}={}", oid, value))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for Name<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_fmt(format_args!("Name {{ {} }}", self))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PublicKey<'a> {
+    Rsa {
+        modulus: &'a [u8],
+        exponent: &'a [u8],
+    },
+    Ec {
+        curve: Oid<'a>,
+        point: &'a [u8],
+    },
+    Other {
+        algorithm: Oid<'a>,
+        key: &'a [u8],
+    },
+}
+
+impl<'a> PublicKey<'a> {
+    fn from_der(object: DerObject<'a>) -> DerResult<Self> {
+        let mut spki = object.expect(der::TAG_SEQUENCE)?.reader();
+        let mut algorithm = spki.read(der::TAG_SEQUENCE)?.reader();
+        let algorithm_oid = algorithm.read_any()?.as_oid()?;
+        let parameters = match algorithm.is_empty() {
+            true => None,
+            false => Some(algorithm.read_any()?),
+        };
+        let (key, _) = spki.read_last(der::TAG_BIT_STRING)?.as_bit_string()?;
+
+        Ok(match algorithm_oid.0 {
+            der::OID_RSA_ENCRYPTION => {
+                let mut rsa_key = DerReader::new(key).read_last(der::TAG_SEQUENCE)?.reader();
+                let modulus = rsa_key.read(der::TAG_INTEGER)?.as_unsigned_integer()?;
+                let exponent = rsa_key.read_last(der::TAG_INTEGER)?.as_unsigned_integer()?;
+                Self::Rsa { modulus, exponent }
+            }
+            der::OID_EC_PUBLIC_KEY => Self::Ec {
+                curve: parameters.ok_or(DerError::BadValue)?.as_oid()?,
+                point: key,
+            },
+            _ => Self::Other {
+                algorithm: algorithm_oid,
+                key,
+            },
+        })
+    }
+
+    pub fn algorithm_name(&self) -> &'static str {
+        match self {
+            Self::Rsa { .. } => "RSA",
+            Self::Ec { .. } => "EC",
+            Self::Other { .. } => "Unknown",
+        }
+    }
+
+    // Key size in bits, where it can be determined.
+    pub fn key_bits(&self) -> Option<usize> {
+        match self {
+            Self::Rsa { modulus, .. } => modulus
+                .first()
+                .map(|top| modulus.len() * 8 - top.leading_zeros() as usize),
+            Self::Ec { curve, .. } => match curve.0 {
+                der::OID_EC_P256 => Some(256),
+                der::OID_EC_P384 => Some(384),
+                der::OID_EC_P521 => Some(521),
+                _ => None,
+            },
+            Self::Other { .. } => None,
+        }
+    }
+}
+
+impl<'a> PublicKey<'a> {
+    // Checks an RSA PKCS#1 v1.5 signature over `data`, which is hashed
+    // with `digest_algorithm` first.
+    pub fn verify(
+        &self,
+        digest_algorithm: &[u8],
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<(), VerifyError> {
+        let key = match self {
+            Self::Rsa { modulus, exponent } => RsaPublicKey::new(
+                BigUint::from_bytes_be(modulus),
+                BigUint::from_bytes_be(exponent),
+            )
+            .map_err(|_| VerifyError::UnsupportedAlgorithm)?,
+            _ => return Err(VerifyError::UnsupportedAlgorithm),
+        };
+        let scheme = match digest_algorithm {
+            der::OID_SHA1 => Pkcs1v15Sign::new::<Sha1>(),
+            der::OID_SHA256 => Pkcs1v15Sign::new::<Sha256>(),
+            der::OID_SHA384 => Pkcs1v15Sign::new::<Sha384>(),
+            der::OID_SHA512 => Pkcs1v15Sign::new::<Sha512>(),
+            _ => return Err(VerifyError::UnsupportedAlgorithm),
+        };
+        let digest = compute_digest(digest_algorithm, data).unwrap();
+        key.verify(scheme, &digest, signature)
+            .map_err(|_| VerifyError::BadSignature)
+    }
+}
+
+impl<'a> fmt::Debug for PublicKey<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.key_bits()) {
+            (Self::Ec { curve, .. }, _) => fmtr.write_fmt(format_args!("EC {:?}", curve)),
+            (Self::Other { algorithm, .. }, _) => fmtr.write_fmt(format_args!("{:?}", algorithm)),
+            (_, Some(bits)) => fmtr.write_fmt(format_args!("{} {}", self.algorithm_name(), bits)),
+            (_, None) => fmtr.write_str(self.algorithm_name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extension<'a> {
+    pub oid: Oid<'a>,
+    pub critical: bool,
+    pub value: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    pub path_length: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct Certificate<'a> {
+    raw: &'a [u8],
+    tbs: &'a [u8],
+    pub version: u64,
+    pub serial: &'a [u8],
+    pub signature_algorithm: Oid<'a>,
+    pub issuer: Name<'a>,
+    pub subject: Name<'a>,
+    pub not_before: rustified::Time,
+    pub not_after: rustified::Time,
+    pub public_key: PublicKey<'a>,
+    pub extensions: Vec<Extension<'a>>,
+    pub signature: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    // Decodes a single DER certificate. Any bytes after the certificate
+    // are rejected, since a signature element holds exactly one.
+    pub fn from_der(buffer: &'a [u8]) -> DerResult<Self> {
+        let mut outer = DerReader::new(buffer);
+        let certificate = outer.read_last(der::TAG_SEQUENCE)?;
+        Self::from_der_object(certificate)
+    }
+
+    pub fn from_der_object(certificate: DerObject<'a>) -> DerResult<Self> {
+        let mut certificate_reader = certificate.expect(der::TAG_SEQUENCE)?.reader();
+        let tbs = certificate_reader.read(der::TAG_SEQUENCE)?;
+        let mut outer_algorithm = certificate_reader.read(der::TAG_SEQUENCE)?.reader();
+        let signature_algorithm = outer_algorithm.read_any()?.as_oid()?;
+        let (signature, _) = certificate_reader
+            .read_last(der::TAG_BIT_STRING)?
+            .as_bit_string()?;
+
+        let mut fields = tbs.reader();
+        let version = match fields.read_optional(tag_context(0))? {
+            Some(version) => version
+                .reader()
+                .read_last(der::TAG_INTEGER)?
+                .as_small_integer()?,
+            None => 0,
+        };
+        let serial = fields.read(der::TAG_INTEGER)?.as_unsigned_integer()?;
+        // The inner signature algorithm has to match the outer one anyway.
+        fields.read(der::TAG_SEQUENCE)?;
+        let issuer = Name::from_der(fields.read_any()?)?;
+        let mut validity = fields.read(der::TAG_SEQUENCE)?.reader();
+        let not_before = validity.read_any()?.as_time()?;
+        let not_after = validity.read_any()?.as_time()?;
+        validity.finish()?;
+        let subject = Name::from_der(fields.read_any()?)?;
+        let public_key = PublicKey::from_der(fields.read_any()?)?;
+        // Skip issuerUniqueID and subjectUniqueID.
+        fields.read_optional(tag_context_primitive(1))?;
+        fields.read_optional(tag_context_primitive(2))?;
+
+        let mut extensions = Vec::new();
+        if let Some(extensions_wrapper) = fields.read_optional(tag_context(3))? {
+            let mut extension_list = extensions_wrapper
+                .reader()
+                .read_last(der::TAG_SEQUENCE)?
+                .reader();
+            while !extension_list.is_empty() {
+                let mut extension = extension_list.read(der::TAG_SEQUENCE)?.reader();
+                let oid = extension.read_any()?.as_oid()?;
+                let critical = match extension.read_optional(der::TAG_BOOLEAN)? {
+                    Some(critical) => critical.as_bool()?,
+                    None => false,
+                };
+                let value = extension.read_last(der::TAG_OCTET_STRING)?.contents;
+                extensions.push(Extension {
+                    oid,
+                    critical,
+                    value,
+                });
+            }
+        }
+        fields.finish()?;
+
+        Ok(Self {
+            raw: certificate.raw,
+            tbs: tbs.raw,
+            version,
+            serial,
+            signature_algorithm,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            extensions,
+            signature,
+        })
+    }
+
+    // The complete DER encoding of the certificate.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    // The DER encoding of the TBSCertificate, which is what the issuer signed.
+    pub fn tbs_bytes(&self) -> &'a [u8] {
+        self.tbs
+    }
+
+    // Checks that `issuer`'s key made this certificate's signature.
+    // Names aren't compared; that's part of building the chain.
+    pub fn verify_issued_by(&self, issuer: &Certificate) -> Result<(), VerifyError> {
+        let digest_algorithm = signature_digest_algorithm(self.signature_algorithm.0)
+            .ok_or(VerifyError::UnsupportedAlgorithm)?;
+        issuer
+            .public_key
+            .verify(digest_algorithm, self.tbs, self.signature)
+    }
+
+    pub fn extension(&self, oid: &[u8]) -> Option<&Extension<'a>> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.oid.0 == oid)
+    }
+
+    pub fn basic_constraints(&self) -> DerResult<Option<BasicConstraints>> {
+        let extension = match self.extension(der::OID_BASIC_CONSTRAINTS) {
+            Some(extension) => extension,
+            None => return Ok(None),
+        };
+        let mut constraints = DerReader::new(extension.value)
+            .read_last(der::TAG_SEQUENCE)?
+            .reader();
+        let ca = match constraints.read_optional(der::TAG_BOOLEAN)? {
+            Some(ca) => ca.as_bool()?,
+            None => false,
+        };
+        let path_length = match constraints.read_optional(der::TAG_INTEGER)? {
+            Some(length) => Some(length.as_small_integer()?),
+            None => None,
+        };
+        constraints.finish()?;
+        Ok(Some(BasicConstraints { ca, path_length }))
+    }
+
+    pub fn key_usage(&self) -> DerResult<Option<u16>> {
+        let extension = match self.extension(der::OID_KEY_USAGE) {
+            Some(extension) => extension,
+            None => return Ok(None),
+        };
+        let (bits, _) = DerReader::new(extension.value)
+            .read_last(der::TAG_BIT_STRING)?
+            .as_bit_string()?;
+        // Flip the bit order so that bit n of the result is KeyUsage bit n.
+        let usage = bits
+            .iter()
+            .take(2)
+            .enumerate()
+            .fold(0u16, |usage, (index, byte)| {
+                usage | ((byte.reverse_bits() as u16) << (index * 8))
+            });
+        Ok(Some(usage))
+    }
+
+    pub fn extended_key_usage(&self) -> DerResult<Option<Vec<Oid<'a>>>> {
+        let extension = match self.extension(der::OID_EXTENDED_KEY_USAGE) {
+            Some(extension) => extension,
+            None => return Ok(None),
+        };
+        let mut usages = DerReader::new(extension.value)
+            .read_last(der::TAG_SEQUENCE)?
+            .reader();
+        let mut result = Vec::new();
+        while !usages.is_empty() {
+            result.push(usages.read_any()?.as_oid()?);
+        }
+        Ok(Some(result))
+    }
+
+    pub fn subject_key_identifier(&self) -> DerResult<Option<&'a [u8]>> {
+        match self.extension(der::OID_SUBJECT_KEY_IDENTIFIER) {
+            Some(extension) => Ok(Some(
+                DerReader::new(extension.value)
+                    .read_last(der::TAG_OCTET_STRING)?
+                    .contents,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn authority_key_identifier(&self) -> DerResult<Option<&'a [u8]>> {
+        let extension = match self.extension(der::OID_AUTHORITY_KEY_IDENTIFIER) {
+            Some(extension) => extension,
+            None => return Ok(None),
+        };
+        let mut identifier = DerReader::new(extension.value)
+            .read_last(der::TAG_SEQUENCE)?
+            .reader();
+        Ok(identifier
+            .read_optional(tag_context_primitive(0))?
+            .map(|key_id| key_id.contents))
+    }
+
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer.raw == self.subject.raw
+    }
+
+    pub fn sha1_thumbprint(&self) -> [u8; 20] {
+        Sha1::digest(self.raw).into()
+    }
+
+    pub fn sha256_thumbprint(&self) -> [u8; 32] {
+        Sha256::digest(self.raw).into()
+    }
+}
+
+pub(crate) struct DebugHex<'a>(pub &'a [u8]);
+impl<'a> fmt::Debug for DebugHex<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                fmtr.write_str(":")?;
+            }
+            fmtr.write_fmt(format_args!("{:02X}", byte))?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct DebugTime<'a>(pub &'a rustified::Time);
+impl<'a> fmt::Debug for DebugTime<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, fmtr)
+    }
+}
+
+impl<'a> fmt::Debug for Certificate<'a> {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        if fmtr.alternate() {
+            fmtr.write_str("X509Certificate:\n")?;
+            fmtr.write_fmt(format_args!("\tSubject: {}\n", self.subject))?;
+            fmtr.write_fmt(format_args!("\tIssuer: {}\n", self.issuer))?;
+            fmtr.write_fmt(format_args!("\tSerial: {:?}\n", DebugHex(self.serial)))?;
+            fmtr.write_fmt(format_args!(
+                "\tValidity: {:?} -> {:?}\n",
+                DebugTime(&self.not_before),
+                DebugTime(&self.not_after)
+            ))?;
+            fmtr.write_fmt(format_args!("\tKey: {:?}\n", self.public_key))?;
+            fmtr.write_fmt(format_args!(
+                "\tSignature Algorithm: {:?}\n",
+                self.signature_algorithm
+            ))?;
+            if let Ok(Some(constraints)) = self.basic_constraints() {
+                fmtr.write_fmt(format_args!("\tCA: {}", constraints.ca))?;
+                if let Some(length) = constraints.path_length {
+                    fmtr.write_fmt(format_args!(", PathLength: {}", length))?;
+                }
+                fmtr.write_str("\n")?;
+            }
+            if let Ok(Some(usage)) = self.key_usage() {
+                let mut names = String::new();
+                for (_, name) in KEY_USAGE_NAMES.iter().filter(|(bit, _)| usage & bit != 0) {
+                    if !names.is_empty() {
+                        names.push_str(", ");
+                    }
+                    names.write_str(name)?;
+                }
+                fmtr.write_fmt(format_args!("\tKey Usage: {}\n", names))?;
+            }
+            if let Ok(Some(usages)) = self.extended_key_usage() {
+                fmtr.write_fmt(format_args!("\tExtended Key Usage: {:?}\n", usages))?;
+            }
+            if let Ok(Some(key_id)) = self.subject_key_identifier() {
+                fmtr.write_fmt(format_args!("\tSubject Key Id: {:?}\n", DebugHex(key_id)))?;
+            }
+            if let Ok(Some(key_id)) = self.authority_key_identifier() {
+                fmtr.write_fmt(format_args!("\tAuthority Key Id: {:?}\n", DebugHex(key_id)))?;
+            }
+            fmtr.write_fmt(format_args!(
+                "\tSHA-1: {:?}\n",
+                DebugHex(&self.sha1_thumbprint())
+            ))?;
+            fmtr.write_fmt(format_args!(
+                "\tSHA-256: {:?}\n",
+                DebugHex(&self.sha256_thumbprint())
+            ))?;
+            Ok(())
+        } else {
+            fmtr.write_fmt(format_args!(
+                "X509Certificate {{ Subject: {} }}",
+                self.subject
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_authentication::SignatureListRef;
+    use crate::test_data::{auth_files, sig_lists};
+
+    fn first_cert(list: &[u8]) -> &[u8] {
+        SignatureListRef::from_bytes(list)
+            .unwrap()
+            .elements()
+            .next()
+            .unwrap()
+            .data
+    }
+
+    #[test]
+    fn x509_should_decode_pk_certificate() {
+        let cert = Certificate::from_der(first_cert(sig_lists::PK)).unwrap();
+        assert_eq!(cert.version, 2);
+        assert_eq!(
+            format!("{}", cert.subject),
+            "C=US, ST=Washington, L=Redmond, O=Microsoft Corporation, \
+             CN=Microsoft Surface NFF UEFI PK CA 2015"
+        );
+        assert_eq!(
+            cert.issuer.common_name().unwrap(),
+            "Microsoft Corporation Third Party Marketplace Root"
+        );
+        assert_eq!(
+            cert.serial,
+            &[
+                0x33, 0x00, 0x00, 0x00, 0x13, 0x50, 0x61, 0x5E, 0x24, 0x00, 0xCE, 0xC6, 0x7C, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x13
+            ]
+        );
+        assert_eq!(
+            (
+                cert.not_before.year,
+                cert.not_before.month,
+                cert.not_before.day
+            ),
+            (2015, 11, 5)
+        );
+        assert_eq!(
+            (
+                cert.not_after.year,
+                cert.not_after.hour,
+                cert.not_after.minute
+            ),
+            (2030, 22, 42)
+        );
+        assert_eq!(cert.public_key.algorithm_name(), "RSA");
+        assert_eq!(cert.public_key.key_bits(), Some(2048));
+        assert_eq!(cert.signature_algorithm.0, der::OID_SHA256_WITH_RSA);
+    }
+
+    #[test]
+    fn x509_should_decode_extensions() {
+        let cert = Certificate::from_der(first_cert(sig_lists::KEK)).unwrap();
+        assert_eq!(
+            cert.subject.common_name().unwrap(),
+            "Microsoft Corporation KEK CA 2011"
+        );
+        assert_eq!(
+            cert.basic_constraints().unwrap(),
+            Some(BasicConstraints {
+                ca: true,
+                path_length: None
+            })
+        );
+        assert!(cert.extension(der::OID_BASIC_CONSTRAINTS).unwrap().critical);
+        assert_eq!(
+            cert.key_usage().unwrap(),
+            Some(KEY_USAGE_DIGITAL_SIGNATURE | KEY_USAGE_KEY_CERT_SIGN | KEY_USAGE_CRL_SIGN)
+        );
+        assert_eq!(
+            cert.subject_key_identifier().unwrap().unwrap(),
+            &[
+                0x62, 0xFC, 0x43, 0xCD, 0xA0, 0x3E, 0xA4, 0xCB, 0x67, 0x12, 0xD2, 0x5B, 0xD9, 0x55,
+                0xAC, 0x7B, 0xCC, 0xB6, 0x8A, 0x5F
+            ]
+        );
+        assert_eq!(
+            cert.authority_key_identifier().unwrap().unwrap(),
+            &[
+                0x45, 0x66, 0x52, 0x43, 0xE1, 0x7E, 0x58, 0x11, 0xBF, 0xD6, 0x4E, 0x9E, 0x23, 0x55,
+                0x08, 0x3B, 0x3A, 0x22, 0x6A, 0xA8
+            ]
+        );
+    }
+
+    #[test]
+    fn x509_should_compute_thumbprints() {
+        let cert = Certificate::from_der(first_cert(sig_lists::DB)).unwrap();
+        assert_eq!(
+            cert.subject.common_name().unwrap(),
+            "Microsoft Windows Production PCA 2011"
+        );
+        assert_eq!(
+            cert.sha1_thumbprint(),
+            [
+                0x58, 0x0A, 0x6F, 0x4C, 0xC4, 0xE4, 0xB6, 0x69, 0xB9, 0xEB, 0xDC, 0x1B, 0x2B, 0x3E,
+                0x08, 0x7B, 0x80, 0xD0, 0x67, 0x8D
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", DebugHex(&cert.sha256_thumbprint())),
+            "E8:E9:5F:07:33:A5:5E:8B:AD:7B:E0:A1:41:3E:E2:3C:\
+             51:FC:EA:64:B3:C8:FA:6A:78:69:35:FD:DC:C7:19:61"
+        );
+    }
+
+    #[test]
+    fn x509_should_reject_truncated_certificates() {
+        let cert = first_cert(sig_lists::PK);
+        for length in [0, 1, 4, 100, cert.len() - 1].iter() {
+            assert!(Certificate::from_der(&cert[..*length]).is_err());
+        }
+
+        let mut trailing = Vec::from(cert);
+        trailing.push(0x00);
+        assert_eq!(
+            Certificate::from_der(&trailing).err(),
+            Some(DerError::BadLength)
+        );
+    }
+
+    #[test]
+    fn x509_should_verify_issuer_signatures() {
+        let test_kek = Certificate::from_der(auth_files::TEST_KEK_CERT).unwrap();
+        let pk = Certificate::from_der(first_cert(sig_lists::PK)).unwrap();
+        assert_eq!(test_kek.verify_issued_by(&test_kek), Ok(()));
+        assert_eq!(
+            test_kek.verify_issued_by(&pk),
+            Err(VerifyError::BadSignature)
+        );
+
+        let mut tampered = Vec::from(auth_files::TEST_KEK_CERT);
+        // Flip a bit in the subject CN.
+        tampered[0xAD] ^= 0x01;
+        let tampered = Certificate::from_der(&tampered).unwrap();
+        assert_eq!(
+            tampered.verify_issued_by(&test_kek),
+            Err(VerifyError::BadSignature)
+        );
+    }
+}
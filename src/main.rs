@@ -6,6 +6,7 @@ extern crate panic;
 extern crate uefi_bs_allocator as uefi_allocator;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt::Write;
 use core::ptr::NonNull;
 use menu::*;
@@ -13,12 +14,38 @@ use r_efi::efi;
 
 use core_con_out::{print, println};
 use mu_rust_ex::{
-    con_in::ConIn, con_in::InputKey, protocol_utility::RustProtocol,
+    auth_variable::{
+        get_current_timestamp, get_secure_boot_variable_guid, EfiAuthVariable2,
+        EfiAuthVariable2Ref, EFI_KEY_EXCHANGE_KEY_NAME, EFI_PLATFORM_KEY_NAME,
+        EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES,
+    },
+    authenticode::{self, HashAlgorithm},
+    catalog,
+    con_in::ConIn,
+    con_in::InputKey,
+    image_authentication::SignatureDatabase,
+    image_policy,
+    image_signature::{self, DigestCheck, ImageSignature},
+    key_file::{self, KeyMaterial},
+    known_bad_keys, name_db,
+    ownership::{plan_ownership, take_ownership},
+    pe::{self, PeImage},
+    preflight::{preflight, TrustState},
+    protocol_utility::{RustProtocol, RustProtocolError},
+    rng_protocol::Protocol as RngProtocol,
+    rotation::{self, DEFAULT_ROTATION_WINDOW_DAYS},
+    secure_boot_status::{DatabaseSnapshot, SecureBootStatus},
     shell_parameters_protocol::Protocol as ShellParametersProtocol,
-    shell_protocol::Protocol as ShellProtocol, UefiResult,
+    shell_protocol::Protocol as ShellProtocol,
+    util,
+    variable::EfiVariable,
+    UefiResult,
 };
 
-struct PrintOutput;
+// Menu context. Commands that need to prompt (e.g. for a key password) use con_in.
+struct PrintOutput {
+    con_in: ConIn,
+}
 impl core::fmt::Write for PrintOutput {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
         print!("{}", s);
@@ -29,6 +56,229 @@ impl core::fmt::Write for PrintOutput {
 const ROOT_MENU: Menu<PrintOutput> = Menu {
     label: "root",
     items: &[
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_auth_preview,
+                parameters: &[
+                    Parameter::Mandatory {
+                        parameter_name: "file",
+                        help: Some("signed .auth update to inspect"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "variable",
+                        help: Some("PK, KEK, db, dbx, dbt or dbr"),
+                    },
+                    Parameter::Named {
+                        parameter_name: "replace",
+                        help: Some("treat the update as a replacement rather than an append"),
+                    },
+                ],
+            },
+            command: "auth-preview",
+            help: Some("show what a signed update would change"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_db_diff,
+                parameters: &[
+                    Parameter::Mandatory {
+                        parameter_name: "old",
+                        help: Some("signature lists before the change"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "new",
+                        help: Some("signature lists after the change"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "out",
+                        argument_name: "FILE",
+                        help: Some("also write the differences to this file"),
+                    },
+                ],
+            },
+            command: "db-diff",
+            help: Some("compare two signature databases"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_status,
+                parameters: &[],
+            },
+            command: "status",
+            help: Some("show Secure Boot mode and key databases"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_rotation,
+                parameters: &[Parameter::NamedValue {
+                    parameter_name: "window",
+                    argument_name: "DAYS",
+                    help: Some("flag certificates expiring this soon (default 180)"),
+                }],
+            },
+            command: "rotation",
+            help: Some("check certificate expiry and CA rotation"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_check_keys,
+                parameters: &[
+                    Parameter::NamedValue {
+                        parameter_name: "pk-key",
+                        argument_name: "FILE",
+                        help: Some("new PK key, to take ownership if bad keys are found"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "pk-cert",
+                        argument_name: "FILE",
+                        help: Some("new PK certificate, if it isn't in the key file"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "kek",
+                        argument_name: "FILE",
+                        help: Some("certificates to enroll in KEK"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "owner",
+                        argument_name: "GUID",
+                        help: Some("SignatureOwner for the new PK and KEK"),
+                    },
+                ],
+            },
+            command: "check-keys",
+            help: Some("look for test and leaked keys in PK, KEK and db"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_pe_info,
+                parameters: &[Parameter::Mandatory {
+                    parameter_name: "file",
+                    help: Some("EFI image or PCI option ROM"),
+                }],
+            },
+            command: "pe-info",
+            help: Some("show the headers of an EFI image or option ROM"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_image_hash,
+                parameters: &[Parameter::Mandatory {
+                    parameter_name: "file",
+                    help: Some("EFI image"),
+                }],
+            },
+            command: "image-hash",
+            help: Some("compute the Authenticode digests db and dbx use"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_image_signatures,
+                parameters: &[Parameter::Mandatory {
+                    parameter_name: "file",
+                    help: Some("EFI image"),
+                }],
+            },
+            command: "image-signatures",
+            help: Some("decode the Authenticode signatures embedded in an EFI image"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_image_policy,
+                parameters: &[
+                    Parameter::Mandatory {
+                        parameter_name: "file",
+                        help: Some("EFI image"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "db",
+                        argument_name: "FILE",
+                        help: Some("signature lists to use instead of the current db"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "dbx",
+                        argument_name: "FILE",
+                        help: Some("signature lists to use instead of the current dbx"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "dbt",
+                        argument_name: "FILE",
+                        help: Some("signature lists to use instead of the current dbt"),
+                    },
+                ],
+            },
+            command: "image-policy",
+            help: Some("check whether Secure Boot would allow an EFI image to run"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_take_ownership,
+                parameters: &[
+                    Parameter::Mandatory {
+                        parameter_name: "pk-key",
+                        help: Some("PK private key (PEM, DER, PKCS#8 or PKCS#12)"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "kek",
+                        help: Some("KEK certificate(s) to enroll (DER or PEM)"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "owner",
+                        help: Some("SignatureOwner GUID for the new entries"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "pk-cert",
+                        argument_name: "FILE",
+                        help: Some("PK certificate, if it isn't in the key file"),
+                    },
+                    Parameter::Named {
+                        parameter_name: "dry-run",
+                        help: Some("build and check the updates without writing them"),
+                    },
+                ],
+            },
+            command: "take-ownership",
+            help: Some("enroll PK and KEK while in Setup Mode"),
+        },
+        &Item {
+            item_type: ItemType::Callback {
+                function: select_install_catalog,
+                parameters: &[
+                    Parameter::Mandatory {
+                        parameter_name: "selection",
+                        help: Some("microsoft, microsoft-3p, custom, or entry names (a,b,...)"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "variable",
+                        help: Some("KEK or db"),
+                    },
+                    Parameter::Mandatory {
+                        parameter_name: "key",
+                        help: Some("signing key: PK for KEK, KEK for db"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "cert",
+                        argument_name: "FILE",
+                        help: Some("signing certificate, if it isn't in the key file"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "certs",
+                        argument_name: "FILE",
+                        help: Some("certificates for unbundled entries, plus any custom ones"),
+                    },
+                    Parameter::NamedValue {
+                        parameter_name: "owner",
+                        argument_name: "GUID",
+                        help: Some("SignatureOwner for custom certificates"),
+                    },
+                    Parameter::Named {
+                        parameter_name: "dry-run",
+                        help: Some("build and check the update without writing it"),
+                    },
+                ],
+            },
+            command: "install-catalog",
+            help: Some("append well-known KEK or db certificates"),
+        },
         &Item {
             item_type: ItemType::Callback {
                 function: select_bar,
@@ -86,6 +336,580 @@ fn select_bar<'a>(
     writeln!(context, "In select_bar. Args = {:?}", args).unwrap();
 }
 
+fn select_status<'a>(
+    _menu: &Menu<PrintOutput>,
+    _item: &Item<PrintOutput>,
+    _args: &[&str],
+    context: &mut PrintOutput,
+) {
+    match SecureBootStatus::read_current() {
+        Ok(status) => writeln!(context, "{:#?}", status).unwrap(),
+        Err(err) => writeln!(context, "Failed to read Secure Boot state: {:?}", err).unwrap(),
+    }
+}
+
+fn select_rotation<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let window = match argument_finder(item, args, "window").unwrap() {
+        Some(days) => match days.parse::<u32>() {
+            Ok(days) => days,
+            Err(_) => {
+                writeln!(context, "Invalid window: {}", days).unwrap();
+                return;
+            }
+        },
+        None => DEFAULT_ROTATION_WINDOW_DAYS,
+    };
+    let result = SecureBootStatus::read_current().and_then(|status| {
+        Ok(rotation::analyze(
+            &status.current,
+            &get_current_timestamp()?,
+            window,
+        ))
+    });
+    match result {
+        Ok(report) => writeln!(context, "{:#?}", report).unwrap(),
+        Err(err) => writeln!(context, "Failed to read Secure Boot state: {:?}", err).unwrap(),
+    }
+}
+
+fn select_check_keys<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let status = match SecureBootStatus::read_current() {
+        Ok(status) => status,
+        Err(err) => {
+            writeln!(context, "Failed to read Secure Boot state: {:?}", err).unwrap();
+            return;
+        }
+    };
+    let warnings = known_bad_keys::scan(&status.current);
+    if warnings.is_empty() {
+        writeln!(context, "No known test or leaked keys found").unwrap();
+        return;
+    }
+    for warning in warnings.iter() {
+        writeln!(context, "{:#?}", warning).unwrap();
+    }
+    if !warnings.iter().any(|warning| {
+        warning.variable == EFI_PLATFORM_KEY_NAME || warning.variable == EFI_KEY_EXCHANGE_KEY_NAME
+    }) {
+        writeln!(
+            context,
+            "Remove the flagged db entries with a KEK-signed update"
+        )
+        .unwrap();
+        return;
+    }
+
+    writeln!(
+        context,
+        "This platform's keys can't be trusted. Clear PK in firmware setup to enter Setup Mode, then take ownership with keys you control."
+    )
+    .unwrap();
+    write!(context, "Start take-ownership now? [y/N] ").unwrap();
+    let answer = context.con_in.get_char();
+    writeln!(context).unwrap();
+    if !matches!(answer, Ok(InputKey::Char('y')) | Ok(InputKey::Char('Y'))) {
+        return;
+    }
+
+    let pk_key = argument_finder(item, args, "pk-key").unwrap();
+    let pk_cert = argument_finder(item, args, "pk-cert").unwrap();
+    let kek = argument_finder(item, args, "kek").unwrap();
+    let owner = argument_finder(item, args, "owner").unwrap();
+    let (pk_key, kek, owner) = match (pk_key, kek, owner) {
+        (Some(pk_key), Some(kek), Some(owner)) => (pk_key, kek, owner),
+        _ => {
+            writeln!(
+                context,
+                "Run check-keys with --pk-key, --kek and --owner, or use take-ownership directly"
+            )
+            .unwrap();
+            return;
+        }
+    };
+    // Outside Setup/Audit Mode the plan can still be built and checked.
+    let dry_run = !status.mode().accepts_self_signed_pk();
+    if dry_run {
+        writeln!(context, "Not in Setup or Audit Mode; doing a dry run").unwrap();
+    }
+    if let Err(err) = take_platform_ownership(pk_key, pk_cert, kek, owner, dry_run, context) {
+        writeln!(context, "Failed to take ownership: {:?}", err).unwrap();
+    }
+}
+
+fn select_take_ownership<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let pk_key = argument_finder(item, args, "pk-key").unwrap().unwrap();
+    let kek = argument_finder(item, args, "kek").unwrap().unwrap();
+    let owner = argument_finder(item, args, "owner").unwrap().unwrap();
+    let pk_cert = argument_finder(item, args, "pk-cert").unwrap();
+    let dry_run = argument_finder(item, args, "dry-run").unwrap().is_some();
+    if let Err(err) = take_platform_ownership(pk_key, pk_cert, kek, owner, dry_run, context) {
+        writeln!(context, "Failed to take ownership: {:?}", err).unwrap();
+    }
+}
+
+fn take_platform_ownership(
+    pk_key: &str,
+    pk_cert: Option<&str>,
+    kek: &str,
+    owner: &str,
+    dry_run: bool,
+    context: &mut PrintOutput,
+) -> UefiResult<()> {
+    let owner = util::guid_from_str(owner).ok_or(efi::Status::INVALID_PARAMETER)?;
+    let status = SecureBootStatus::read_current()?;
+    writeln!(context, "Current mode: {:?}", status.mode()).unwrap();
+    if !status.mode().accepts_self_signed_pk() && !dry_run {
+        writeln!(
+            context,
+            "Ownership can only be taken in Setup or Audit Mode"
+        )
+        .unwrap();
+        return Err(efi::Status::ACCESS_DENIED);
+    }
+
+    let shell = ShellProtocol::first()?;
+    let material = KeyMaterial::load(&shell, &context.con_in, pk_key, pk_cert)?;
+    let pk_certificate = material
+        .chain
+        .first()
+        .cloned()
+        .ok_or(efi::Status::NOT_FOUND)?;
+    let kek_certificates = key_file::load_certificates(&shell, kek)?;
+    let mut signer = material.into_signer(RngProtocol::first()?)?;
+
+    let plan = match plan_ownership(
+        &pk_certificate,
+        &kek_certificates,
+        &owner,
+        &get_current_timestamp()?,
+        &mut signer,
+    ) {
+        Ok(plan) => plan,
+        Err(err) => {
+            writeln!(context, "{}", err).unwrap();
+            return Err(err.into());
+        }
+    };
+    for write in plan.iter() {
+        writeln!(context, "{:?}", write).unwrap();
+    }
+    if dry_run {
+        writeln!(context, "Dry run; nothing written").unwrap();
+        return Ok(());
+    }
+
+    match take_ownership(&plan) {
+        Ok(report) => writeln!(context, "{:#?}", report).unwrap(),
+        Err(err) => {
+            writeln!(context, "{}", err).unwrap();
+            return Err(err.into());
+        }
+    }
+    Ok(())
+}
+
+fn select_install_catalog<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let selection = argument_finder(item, args, "selection").unwrap().unwrap();
+    let variable = argument_finder(item, args, "variable").unwrap().unwrap();
+    let key = argument_finder(item, args, "key").unwrap().unwrap();
+    let cert = argument_finder(item, args, "cert").unwrap();
+    let certs = argument_finder(item, args, "certs").unwrap();
+    let owner = argument_finder(item, args, "owner").unwrap();
+    let dry_run = argument_finder(item, args, "dry-run").unwrap().is_some();
+    if let Err(err) = install_catalog(
+        selection, variable, key, cert, certs, owner, dry_run, context,
+    ) {
+        writeln!(context, "Failed to install certificates: {:?}", err).unwrap();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn install_catalog(
+    selection: &str,
+    variable: &str,
+    key: &str,
+    cert: Option<&str>,
+    certs: Option<&str>,
+    owner: Option<&str>,
+    dry_run: bool,
+    context: &mut PrintOutput,
+) -> UefiResult<()> {
+    let guid = get_secure_boot_variable_guid(variable).ok_or(efi::Status::INVALID_PARAMETER)?;
+    let owner = match owner {
+        Some(owner) => Some(util::guid_from_str(owner).ok_or(efi::Status::INVALID_PARAMETER)?),
+        None => None,
+    };
+    let shell = ShellProtocol::first()?;
+    let supplied = match certs {
+        Some(path) => key_file::load_certificates(&shell, path)?,
+        None => Vec::new(),
+    };
+
+    let entries = catalog::select(selection)?;
+    let database = match catalog::build_append(&entries, variable, &supplied, owner.as_ref()) {
+        Ok(database) => database,
+        Err(err) => {
+            writeln!(context, "{}", err).unwrap();
+            return Err(err.into());
+        }
+    };
+    if database.entries.is_empty() {
+        writeln!(context, "Nothing in the selection belongs in {}", variable).unwrap();
+        return Ok(());
+    }
+    writeln!(context, "{:#?}", database).unwrap();
+
+    let mut signer =
+        KeyMaterial::load(&shell, &context.con_in, key, cert)?.into_signer(RngProtocol::first()?)?;
+    let attributes = EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES | efi::VARIABLE_APPEND_WRITE;
+    let payload = EfiAuthVariable2 {
+        variable: EfiVariable {
+            name: String::from(variable),
+            guid,
+            data: database.to_bytes()?,
+            attributes,
+        },
+        time: get_current_timestamp()?,
+    }
+    .get_signed_set_variable_data(&mut signer)?;
+
+    let update = EfiAuthVariable2Ref::from_bytes(&payload)?;
+    let state = TrustState::read_current()?;
+    match preflight(&update, variable, attributes, &state, None) {
+        Ok(report) => writeln!(context, "{:#?}", report).unwrap(),
+        Err(err) => {
+            writeln!(context, "Firmware would reject this update: {}", err).unwrap();
+            return Err(err.into());
+        }
+    }
+    if dry_run {
+        writeln!(context, "Dry run; nothing written").unwrap();
+        return Ok(());
+    }
+
+    EfiVariable {
+        name: String::from(variable),
+        guid,
+        data: payload,
+        attributes,
+    }
+    .append()?;
+    writeln!(context, "Appended to {}", variable).unwrap();
+    Ok(())
+}
+
+fn select_pe_info<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let file = argument_finder(item, args, "file").unwrap().unwrap();
+    if let Err(err) = show_pe_info(file, context) {
+        writeln!(context, "Failed to parse image: {:?}", err).unwrap();
+    }
+}
+
+fn show_pe_info(file: &str, context: &mut PrintOutput) -> UefiResult<()> {
+    let shell = ShellProtocol::first()?;
+    let file = shell.open_file_by_name(file, r_efi::protocols::file::MODE_READ)?;
+    let bytes = file.read_count(file.get_size()?)?;
+
+    // Anything that isn't a PE image might be an option ROM holding some.
+    let error = match PeImage::from_bytes(&bytes) {
+        Ok(image) => {
+            writeln!(context, "{:#?}", image).unwrap();
+            return Ok(());
+        }
+        Err(err) => err,
+    };
+    let images = match pe::parse_option_rom(&bytes) {
+        Ok(images) => images,
+        Err(_) => {
+            writeln!(context, "{}", error).unwrap();
+            return Err(error.into());
+        }
+    };
+    for image in images.iter() {
+        writeln!(context, "Option ROM image {:?}", image).unwrap();
+        match image.efi_image(&bytes).map(PeImage::from_bytes) {
+            Some(Ok(pe)) => writeln!(context, "{:#?}", pe).unwrap(),
+            Some(Err(err)) => writeln!(context, "\t{}", err).unwrap(),
+            None => (),
+        }
+    }
+    Ok(())
+}
+
+fn select_image_hash<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let file = argument_finder(item, args, "file").unwrap().unwrap();
+    if let Err(err) = show_image_hash(file, context) {
+        writeln!(context, "Failed to hash image: {:?}", err).unwrap();
+    }
+}
+
+fn show_image_hash(file: &str, context: &mut PrintOutput) -> UefiResult<()> {
+    let shell = ShellProtocol::first()?;
+    let mut file = shell.open_file_by_name(file, r_efi::protocols::file::MODE_READ)?;
+    let (image, headers) = authenticode::read_headers(&mut file)?;
+    let digests = authenticode::compute_digests(&image, &headers, &mut file, &HashAlgorithm::ALL)?;
+    for (algorithm, digest) in HashAlgorithm::ALL.iter().zip(digests.iter()) {
+        writeln!(context, "{:?}: {}", algorithm, util::hex_to_string(digest)).unwrap();
+    }
+    Ok(())
+}
+
+fn select_image_signatures<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let file = argument_finder(item, args, "file").unwrap().unwrap();
+    if let Err(err) = show_image_signatures(file, context) {
+        writeln!(context, "Failed to read image signatures: {:?}", err).unwrap();
+    }
+}
+
+fn show_image_signatures(file: &str, context: &mut PrintOutput) -> UefiResult<()> {
+    let shell = ShellProtocol::first()?;
+    let mut file = shell.open_file_by_name(file, r_efi::protocols::file::MODE_READ)?;
+    let (image, headers) = authenticode::read_headers(&mut file)?;
+    let digests = authenticode::compute_digests(&image, &headers, &mut file, &HashAlgorithm::ALL)?;
+    let computed: Vec<(HashAlgorithm, Vec<u8>)> =
+        HashAlgorithm::ALL.iter().copied().zip(digests).collect();
+
+    let table = image_signature::read_certificate_table(&image, &mut file)?;
+    let entries = image_signature::parse_certificate_table(&table)?;
+    if entries.is_empty() {
+        writeln!(context, "Image is not signed").unwrap();
+    }
+    for entry in entries.iter() {
+        writeln!(context, "{:?}", entry).unwrap();
+        let signature = match entry.signed_data().and_then(ImageSignature::from_der) {
+            Ok(signature) => signature,
+            Err(err) => {
+                writeln!(context, "\t{}", err).unwrap();
+                continue;
+            }
+        };
+        for signature in signature.all() {
+            writeln!(context, "{:#?}", signature).unwrap();
+            match signature.verify() {
+                Ok(_) => writeln!(context, "\tSignature: valid").unwrap(),
+                Err(err) => writeln!(context, "\tSignature: INVALID ({:?})", err).unwrap(),
+            }
+            match signature.check_digest(&computed) {
+                DigestCheck::Match => writeln!(context, "\tImage Digest: matches").unwrap(),
+                DigestCheck::Mismatch => writeln!(
+                    context,
+                    "\tImage Digest: MISMATCH, the image was modified after signing"
+                )
+                .unwrap(),
+                DigestCheck::Unsupported => {
+                    writeln!(context, "\tImage Digest: algorithm not supported").unwrap()
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn select_image_policy<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let file = argument_finder(item, args, "file").unwrap().unwrap();
+    let overrides = [
+        ("db", argument_finder(item, args, "db").unwrap()),
+        ("dbx", argument_finder(item, args, "dbx").unwrap()),
+        ("dbt", argument_finder(item, args, "dbt").unwrap()),
+    ];
+    if let Err(err) = evaluate_image_policy(file, &overrides, context) {
+        writeln!(context, "Failed to evaluate image policy: {:?}", err).unwrap();
+    }
+}
+
+fn evaluate_image_policy(
+    file: &str,
+    overrides: &[(&'static str, Option<&str>)],
+    context: &mut PrintOutput,
+) -> UefiResult<()> {
+    let status = SecureBootStatus::read_current()?;
+    let mut databases = status.current.clone();
+    let shell = ShellProtocol::first()?;
+    for (name, path) in overrides.iter() {
+        let path = match path {
+            Some(path) => path,
+            None => continue,
+        };
+        let override_file = shell.open_file_by_name(path, r_efi::protocols::file::MODE_READ)?;
+        let data = override_file.read_count(override_file.get_size()?)?;
+        let snapshot = Some(DatabaseSnapshot::from_bytes(name, &data));
+        match *name {
+            "db" => databases.db = snapshot,
+            "dbx" => databases.dbx = snapshot,
+            "dbt" => databases.dbt = snapshot,
+            _ => return Err(efi::Status::INVALID_PARAMETER),
+        }
+        writeln!(context, "Using {} from {}", name, path).unwrap();
+    }
+
+    let mut file = shell.open_file_by_name(file, r_efi::protocols::file::MODE_READ)?;
+    let report = image_policy::evaluate_image(&databases, &mut file)?;
+    writeln!(context, "{:#?}", report).unwrap();
+    if !status.is_secure_boot_enabled() {
+        writeln!(
+            context,
+            "Secure Boot is not enabled, so firmware won't enforce this"
+        )
+        .unwrap();
+    }
+    Ok(())
+}
+
+fn select_auth_preview<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let file = argument_finder(item, args, "file").unwrap().unwrap();
+    let variable = argument_finder(item, args, "variable").unwrap().unwrap();
+    let append = argument_finder(item, args, "replace").unwrap().is_none();
+    if let Err(err) = preview_auth_update(file, variable, append, context) {
+        writeln!(context, "Failed to preview update: {:?}", err).unwrap();
+    }
+}
+
+fn preview_auth_update(
+    file: &str,
+    variable: &str,
+    append: bool,
+    context: &mut PrintOutput,
+) -> UefiResult<()> {
+    let guid = get_secure_boot_variable_guid(variable).ok_or(efi::Status::INVALID_PARAMETER)?;
+
+    let shell = ShellProtocol::first()?;
+    let file = shell.open_file_by_name(file, r_efi::protocols::file::MODE_READ)?;
+    let bytes = file.read_count(file.get_size()?)?;
+    let update = EfiAuthVariable2Ref::from_bytes(&bytes)?;
+    writeln!(context, "{:#?}", update).unwrap();
+    let payload = update.payload()?;
+
+    // A variable that doesn't exist yet is the same as an empty one.
+    let current = match EfiVariable::get_variable_as::<SignatureDatabase>(variable, &guid) {
+        Ok(current) => current,
+        Err(efi::Status::NOT_FOUND) => SignatureDatabase {
+            entries: Vec::new(),
+        },
+        Err(err) => return Err(err),
+    };
+
+    let mut updated = current.clone();
+    if append {
+        let report = updated.merge(&payload);
+        writeln!(
+            context,
+            "Append to {}: {} new, {} already present",
+            variable,
+            report.added.len(),
+            report.duplicates.len()
+        )
+        .unwrap();
+    } else {
+        updated = payload;
+        writeln!(context, "Replace {}", variable).unwrap();
+    }
+    writeln!(context, "{:#?}", current.diff(&updated)).unwrap();
+
+    // The stored TimeStamp can't be read back, so that check is skipped.
+    let attributes = EFI_SECURE_BOOT_VARIABLE_ATTRIBUTES
+        | if append {
+            efi::VARIABLE_APPEND_WRITE
+        } else {
+            0
+        };
+    let state = TrustState::read_current()?;
+    match preflight(&update, variable, attributes, &state, None) {
+        Ok(report) => writeln!(context, "{:#?}", report).unwrap(),
+        Err(err) => writeln!(context, "Firmware would reject this update: {}", err).unwrap(),
+    }
+
+    Ok(())
+}
+
+fn select_db_diff<'a>(
+    _menu: &Menu<PrintOutput>,
+    item: &Item<PrintOutput>,
+    args: &[&str],
+    context: &mut PrintOutput,
+) {
+    let old = argument_finder(item, args, "old").unwrap().unwrap();
+    let new = argument_finder(item, args, "new").unwrap().unwrap();
+    let out = argument_finder(item, args, "out").unwrap();
+    if let Err(err) = show_db_diff(old, new, out, context) {
+        writeln!(context, "Failed to compare databases: {:?}", err).unwrap();
+    }
+}
+
+fn show_db_diff(
+    old: &str,
+    new: &str,
+    out: Option<&str>,
+    context: &mut PrintOutput,
+) -> UefiResult<()> {
+    let shell = ShellProtocol::first()?;
+    let old_file = shell.open_file_by_name(old, r_efi::protocols::file::MODE_READ)?;
+    let old_db = SignatureDatabase::from_bytes(&old_file.read_count(old_file.get_size()?)?)?;
+    let new_file = shell.open_file_by_name(new, r_efi::protocols::file::MODE_READ)?;
+    let new_db = SignatureDatabase::from_bytes(&new_file.read_count(new_file.get_size()?)?)?;
+
+    let diff = old_db.diff(&new_db);
+    writeln!(context, "{:#?}", diff).unwrap();
+    if let Some(out) = out {
+        // CreateFile keeps an existing file's contents, so start from nothing.
+        match shell.delete_file_by_name(out) {
+            Ok(_) | Err(RustProtocolError::Efi(efi::Status::NOT_FOUND)) => (),
+            Err(err) => return Err(err.into()),
+        }
+        let mut file = shell.create_file(
+            out,
+            r_efi::protocols::file::MODE_CREATE | r_efi::protocols::file::MODE_WRITE,
+        )?;
+        write!(file, "{:#?}", diff).map_err(|_| efi::Status::DEVICE_ERROR)?;
+        writeln!(context, "Wrote differences to {}", out).unwrap();
+    }
+    Ok(())
+}
+
 fn enter_sub(_menu: &Menu<PrintOutput>, context: &mut PrintOutput) {
     writeln!(context, "In enter_sub").unwrap();
 }
@@ -136,6 +960,10 @@ impl AppInstance {
         let args = shell_params.get_args()?;
         println!("{:?}", args);
 
+        if let Err(err) = name_db::load_user_database(&shell, name_db::DEFAULT_USER_DATABASE_PATH) {
+            println!("User name database not loaded: {}", err);
+        }
+
         let mut iter = args.iter();
         let mut output_file: Option<String> = None;
         let mut input_file: Option<String> = None;
@@ -177,7 +1005,10 @@ impl AppInstance {
 
         let con_in = unsafe { ConIn::new(self.st.as_ptr())? };
         let mut buffer = [0u8; 64];
-        let mut r = Runner::new(&ROOT_MENU, &mut buffer, PrintOutput);
+        let context = PrintOutput {
+            con_in: unsafe { ConIn::new(self.st.as_ptr())? },
+        };
+        let mut r = Runner::new(&ROOT_MENU, &mut buffer, context);
         loop {
             match con_in.get_char()? {
                 InputKey::Char('\n') => r.input_byte(b'\n'),